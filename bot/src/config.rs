@@ -0,0 +1,376 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use clap::Parser;
+use humanoid::{read_config, Joint, JointProfile, PlaybackOptions};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    k_bot::K_BOT_PROFILE,
+    mini_robot::ZEROTH_PROFILE,
+    playback::{load_motion, save_motion, PlaybackRequest, DEFAULT_MOTIONS_DIR},
+    telemetry::{DEFAULT_TELEMETRY_HISTORY_SECS, DEFAULT_TELEMETRY_INTERVAL_MS},
+    CONTROL_RATE_HZ,
+};
+
+/// Address of the Zeroth when connected to its access point.
+pub const DEFAULT_ZEROTH_ADDRESS: &str = "grpc://192.168.42.1:50051";
+
+pub const DEFAULT_BIND: &str = "0.0.0.0:8020";
+
+pub const DEFAULT_CALIBRATION_FILE: &str = "calibration.json";
+
+/// Highest control rate, and motion frame rate, the bot accepts.
+pub const MAX_RATE_HZ: f32 = 1000.0;
+
+/// Fail unless `rate` is a usable rate in Hz, above zero and at most [`MAX_RATE_HZ`].
+fn check_rate(name: &str, rate: f32) -> eyre::Result<()> {
+    eyre::ensure!(
+        rate.is_finite() && rate > 0.0 && rate <= MAX_RATE_HZ,
+        "{} must be above 0 and at most {}, got {}",
+        name,
+        MAX_RATE_HZ,
+        rate
+    );
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum Backend {
+    #[default]
+    Zeroth,
+    Kbot,
+    /// An in-process simulated robot, no hardware needed.
+    Sim,
+}
+
+/// Settings for the bot server, read from a config file and overridden on the command line.
+///
+/// Relative paths in a config file are relative to the file itself.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BotConfig {
+    pub backend: Backend,
+    /// gRPC address of the robot. Defaults to the Zeroth's access point address, and is required
+    /// for the K-Bot.
+    pub robot_address: Option<String>,
+    /// Address the HTTP server listens on.
+    pub bind: String,
+    /// Address to receive binary frames on over UDP, see [`crate::udp`]. Off by default.
+    pub udp_bind: Option<String>,
+    pub calibration_file: PathBuf,
+    /// Probe the robot even if the saved calibration matches it.
+    pub recalibrate: bool,
+    /// Joint mapping profile, defaults to the one built in for the backend.
+    pub profile: Option<PathBuf>,
+    /// Safety limits, defaults to the built-in `safety.toml`.
+    pub safety: Option<PathBuf>,
+    /// Balance controller gains, see `balance.toml`. The robot only balances if this is set.
+    pub balance: Option<PathBuf>,
+    /// Fall detection thresholds, see `fall.toml`. Falls are only detected if this is set.
+    pub fall: Option<PathBuf>,
+    /// Thermal and overcurrent rules, see `protection.toml`. Servos are only protected if this
+    /// is set, and telemetry is on.
+    pub protection: Option<PathBuf>,
+    /// Pose to move to before accepting frames, defaults to the backend's standing pose.
+    pub initial_pose: Option<BTreeMap<Joint, f32>>,
+    pub control_rate_hz: f32,
+    /// Stop the robot when streamed frames stop arriving for this many milliseconds. Off
+    /// unless set.
+    pub watchdog_ms: Option<u64>,
+    /// How often to read servo temperature, current and voltage, see `GET /telemetry`. 0 turns
+    /// it off.
+    pub telemetry_interval_ms: u64,
+    /// How many seconds of servo readings `GET /telemetry/history` keeps.
+    pub telemetry_history_secs: u64,
+    /// Reject frames with values outside the robot's joint ranges instead of clamping them.
+    pub strict: bool,
+    /// Where `POST /playback` reads motion files from.
+    pub motions_dir: PathBuf,
+}
+
+impl Default for BotConfig {
+    fn default() -> Self {
+        Self {
+            backend: Backend::default(),
+            robot_address: None,
+            bind: DEFAULT_BIND.to_string(),
+            udp_bind: None,
+            calibration_file: PathBuf::from(DEFAULT_CALIBRATION_FILE),
+            recalibrate: false,
+            profile: None,
+            safety: None,
+            balance: None,
+            fall: None,
+            protection: None,
+            initial_pose: None,
+            control_rate_hz: CONTROL_RATE_HZ,
+            watchdog_ms: None,
+            telemetry_interval_ms: DEFAULT_TELEMETRY_INTERVAL_MS,
+            telemetry_history_secs: DEFAULT_TELEMETRY_HISTORY_SECS,
+            strict: false,
+            motions_dir: PathBuf::from(DEFAULT_MOTIONS_DIR),
+        }
+    }
+}
+
+impl BotConfig {
+    /// Load a TOML or JSON config file.
+    pub fn load(path: impl AsRef<Path>) -> eyre::Result<Self> {
+        let path = path.as_ref();
+        let mut config: Self = read_config(path)?;
+
+        if let Some(dir) = path.parent() {
+            config.calibration_file = dir.join(&config.calibration_file);
+            config.profile = config.profile.map(|profile| dir.join(profile));
+            config.safety = config.safety.map(|safety| dir.join(safety));
+            config.balance = config.balance.map(|balance| dir.join(balance));
+            config.fall = config.fall.map(|fall| dir.join(fall));
+            config.protection = config.protection.map(|protection| dir.join(protection));
+            config.motions_dir = dir.join(&config.motions_dir);
+        }
+
+        Ok(config)
+    }
+
+    pub fn validate(&self) -> eyre::Result<()> {
+        check_rate("control_rate_hz", self.control_rate_hz)?;
+        eyre::ensure!(self.watchdog_ms != Some(0), "watchdog_ms must be positive");
+        eyre::ensure!(
+            self.telemetry_history_secs > 0,
+            "telemetry_history_secs must be positive"
+        );
+        eyre::ensure!(
+            self.protection.is_none() || self.telemetry_interval_ms > 0,
+            "protection needs telemetry, telemetry_interval_ms must be positive"
+        );
+        eyre::ensure!(
+            self.backend != Backend::Kbot || self.robot_address.is_some(),
+            "robot_address is required for the kbot backend"
+        );
+        Ok(())
+    }
+
+    pub fn robot_address(&self) -> &str {
+        self.robot_address
+            .as_deref()
+            .unwrap_or(DEFAULT_ZEROTH_ADDRESS)
+    }
+
+    /// The `profile` file, or the profile built in for the backend. The simulator uses the
+    /// K-Bot's.
+    pub fn joint_profile(&self) -> eyre::Result<JointProfile> {
+        match (&self.profile, self.backend) {
+            (Some(path), _) => JointProfile::load(path),
+            (None, Backend::Zeroth) => JointProfile::from_toml(ZEROTH_PROFILE),
+            (None, Backend::Kbot | Backend::Sim) => JointProfile::from_toml(K_BOT_PROFILE),
+        }
+    }
+}
+
+/// Stream pose frames from HTTP to a robot.
+#[derive(Debug, Default, Parser)]
+#[command(version, about)]
+pub struct Cli {
+    /// TOML or JSON config file. Any options given here override it.
+    #[arg(short, long)]
+    pub config: Option<PathBuf>,
+    #[arg(long, value_enum)]
+    pub backend: Option<Backend>,
+    /// gRPC address of the robot, e.g. grpc://192.168.42.1:50051
+    #[arg(long)]
+    pub robot_address: Option<String>,
+    /// Address for the HTTP server, e.g. 0.0.0.0:8020
+    #[arg(long)]
+    pub bind: Option<String>,
+    /// Address to receive binary frames on over UDP, e.g. 0.0.0.0:8021
+    #[arg(long)]
+    pub udp_bind: Option<String>,
+    #[arg(long)]
+    pub calibration_file: Option<PathBuf>,
+    /// Probe the robot even if the saved calibration matches it
+    #[arg(long)]
+    pub recalibrate: bool,
+    /// Joint mapping profile
+    #[arg(long)]
+    pub profile: Option<PathBuf>,
+    /// Safety limits file
+    #[arg(long)]
+    pub safety: Option<PathBuf>,
+    /// Balance controller gains, enables balancing while standing
+    #[arg(long)]
+    pub balance: Option<PathBuf>,
+    /// Fall detection thresholds, enables going limp when the robot falls
+    #[arg(long)]
+    pub fall: Option<PathBuf>,
+    /// Thermal and overcurrent rules, enables throttling hot servos
+    #[arg(long)]
+    pub protection: Option<PathBuf>,
+    /// TOML or JSON file mapping joint names to the initial pose
+    #[arg(long)]
+    pub initial_pose: Option<PathBuf>,
+    #[arg(long)]
+    pub control_rate_hz: Option<f32>,
+    /// Stop the robot when streamed frames stop arriving for this many milliseconds
+    #[arg(long)]
+    pub watchdog_ms: Option<u64>,
+    /// How often to read servo health in milliseconds, 0 turns it off
+    #[arg(long)]
+    pub telemetry_interval_ms: Option<u64>,
+    /// Seconds of servo health history to keep
+    #[arg(long)]
+    pub telemetry_history_secs: Option<u64>,
+    /// Reject frames with values outside the robot's joint ranges instead of clamping them
+    #[arg(long)]
+    pub strict: bool,
+    /// Directory motion files are played from
+    #[arg(long)]
+    pub motions_dir: Option<PathBuf>,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Clone, clap::Subcommand)]
+pub enum Command {
+    /// Play a motion file on the robot, then exit
+    Play(PlayArgs),
+    /// Convert a motion between legacy JSON arrays of frames, motion clips and .krec episodes
+    Convert(ConvertArgs),
+}
+
+#[derive(Debug, Clone, clap::Args)]
+pub struct PlayArgs {
+    /// Motion clip, .krec episode, or legacy JSON array of frames such as
+    /// pose_mappings/flapping_motion.json
+    pub file: PathBuf,
+    /// Times to play the motion, 0 repeats it until Ctrl-C
+    #[arg(long, default_value_t = 1)]
+    pub loops: u32,
+    /// Playback rate, e.g. 2 plays twice as fast
+    #[arg(long, default_value_t = 1.0)]
+    pub speed: f32,
+    /// Rate a legacy motion was recorded at, defaults to the control rate
+    #[arg(long)]
+    pub fps: Option<f32>,
+}
+
+impl PlayArgs {
+    pub fn request(&self, config: &BotConfig) -> eyre::Result<PlaybackRequest> {
+        let fps = self.fps.unwrap_or(config.control_rate_hz);
+        check_rate("fps", fps)?;
+        Ok(PlaybackRequest {
+            clip: Some(load_motion(&self.file, fps, &config.joint_profile()?)?),
+            options: PlaybackOptions {
+                loops: self.loops,
+                speed: self.speed,
+            },
+            ..Default::default()
+        })
+    }
+}
+
+#[derive(Debug, Clone, clap::Args)]
+pub struct ConvertArgs {
+    /// Motion clip, .krec episode, or legacy JSON array of frames
+    pub input: PathBuf,
+    /// Where to write the clip, as a .krec episode for a .krec path, TOML for a .toml path or
+    /// JSON otherwise
+    pub output: PathBuf,
+    /// Rate legacy frames were recorded at, defaults to the control rate
+    #[arg(long)]
+    pub fps: Option<f32>,
+    /// Robot the motion was made for, e.g. zeroth-01
+    #[arg(long)]
+    pub robot: Option<String>,
+}
+
+impl ConvertArgs {
+    /// Convert between motion formats. Joints are mapped to .krec actuator ids with the
+    /// configured profile.
+    pub fn run(&self, config: &BotConfig) -> eyre::Result<()> {
+        let profile = config.joint_profile()?;
+        let fps = self.fps.unwrap_or(config.control_rate_hz);
+        check_rate("fps", fps)?;
+
+        let mut clip = load_motion(&self.input, fps, &profile)?;
+        if self.robot.is_some() {
+            clip.robot = self.robot.clone();
+        }
+        save_motion(&clip, &profile, &self.output)?;
+
+        println!(
+            "Wrote {} frames over {:?} to {}",
+            clip.frames.len(),
+            clip.duration(),
+            self.output.display()
+        );
+        Ok(())
+    }
+}
+
+impl Cli {
+    /// The config file, if any, with every option given on the command line applied on top.
+    pub fn into_config(self) -> eyre::Result<BotConfig> {
+        let mut config = match &self.config {
+            Some(path) => BotConfig::load(path)?,
+            None => BotConfig::default(),
+        };
+
+        if let Some(backend) = self.backend {
+            config.backend = backend;
+        }
+        if let Some(robot_address) = self.robot_address {
+            config.robot_address = Some(robot_address);
+        }
+        if let Some(bind) = self.bind {
+            config.bind = bind;
+        }
+        if let Some(udp_bind) = self.udp_bind {
+            config.udp_bind = Some(udp_bind);
+        }
+        if let Some(calibration_file) = self.calibration_file {
+            config.calibration_file = calibration_file;
+        }
+        config.recalibrate |= self.recalibrate;
+        config.strict |= self.strict;
+        if let Some(profile) = self.profile {
+            config.profile = Some(profile);
+        }
+        if let Some(safety) = self.safety {
+            config.safety = Some(safety);
+        }
+        if let Some(balance) = self.balance {
+            config.balance = Some(balance);
+        }
+        if let Some(fall) = self.fall {
+            config.fall = Some(fall);
+        }
+        if let Some(protection) = self.protection {
+            config.protection = Some(protection);
+        }
+        if let Some(initial_pose) = self.initial_pose {
+            config.initial_pose = Some(read_config(initial_pose)?);
+        }
+        if let Some(motions_dir) = self.motions_dir {
+            config.motions_dir = motions_dir;
+        }
+        if let Some(control_rate_hz) = self.control_rate_hz {
+            config.control_rate_hz = control_rate_hz;
+        }
+        if let Some(watchdog_ms) = self.watchdog_ms {
+            config.watchdog_ms = Some(watchdog_ms);
+        }
+        if let Some(telemetry_interval_ms) = self.telemetry_interval_ms {
+            config.telemetry_interval_ms = telemetry_interval_ms;
+        }
+        if let Some(telemetry_history_secs) = self.telemetry_history_secs {
+            config.telemetry_history_secs = telemetry_history_secs;
+        }
+
+        config.validate()?;
+        Ok(config)
+    }
+}
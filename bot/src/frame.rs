@@ -0,0 +1,146 @@
+use std::collections::BTreeMap;
+
+use humanoid::{parse_joint, Frame, Joint, JointInfo};
+use serde::{Deserialize, Serialize};
+
+/// How a frame is added to the [`humanoid::FrameQueue`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QueueMode {
+    /// Replace anything queued and move there straight away. Best for live control.
+    #[default]
+    Overwrite,
+    /// Append to the queue, to play a scripted sequence.
+    Push,
+}
+
+/// Why a joint in a posted frame was refused.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "reason", rename_all = "snake_case")]
+pub enum FrameError {
+    /// The frame is not a JSON object of joints to values.
+    NotAnObject,
+    /// The key is neither a [`Joint`] id nor a [`Joint`] name.
+    UnknownJoint { joint: String },
+    NotANumber {
+        joint: Joint,
+        value: serde_json::Value,
+    },
+    /// The value is too large to send to the robot.
+    NotFinite { joint: Joint, value: f64 },
+    /// The robot has no such joint.
+    UnsupportedJoint { joint: Joint },
+    /// Only reported in strict mode, otherwise the value is clamped.
+    OutOfRange {
+        joint: Joint,
+        value: f32,
+        min: f32,
+        max: f32,
+    },
+}
+
+impl FrameError {
+    /// Whether the request itself is malformed, as opposed to naming joints or values this robot
+    /// cannot use.
+    pub fn is_malformed(&self) -> bool {
+        matches!(
+            self,
+            FrameError::NotAnObject
+                | FrameError::UnknownJoint { .. }
+                | FrameError::NotANumber { .. }
+                | FrameError::NotFinite { .. }
+        )
+    }
+}
+
+impl std::fmt::Display for FrameError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FrameError::NotAnObject => write!(f, "Expected a JSON object of joints to values"),
+            FrameError::UnknownJoint { joint } => write!(f, "Unknown joint {:?}", joint),
+            FrameError::NotANumber { joint, value } => {
+                write!(f, "{:?}: expected a number, got {}", joint, value)
+            }
+            FrameError::NotFinite { joint, value } => {
+                write!(f, "{:?}: {} is not a finite number", joint, value)
+            }
+            FrameError::UnsupportedJoint { joint } => {
+                write!(f, "{:?} is not supported by this robot", joint)
+            }
+            FrameError::OutOfRange {
+                joint,
+                value,
+                min,
+                max,
+            } => write!(f, "{:?}: {} is outside {}..={}", joint, value, min, max),
+        }
+    }
+}
+
+impl std::error::Error for FrameError {}
+
+/// Parse a JSON object of joints to values, collecting every invalid entry.
+pub fn parse_frame(json: &serde_json::Value) -> Result<Frame, Vec<FrameError>> {
+    let object = json
+        .as_object()
+        .ok_or_else(|| vec![FrameError::NotAnObject])?;
+
+    let mut joints = BTreeMap::new();
+    let mut errors = Vec::new();
+    for (key, value) in object {
+        let Some(joint) = parse_joint(key) else {
+            errors.push(FrameError::UnknownJoint { joint: key.clone() });
+            continue;
+        };
+        match value.as_f64() {
+            Some(value) if !(value as f32).is_finite() => {
+                errors.push(FrameError::NotFinite { joint, value })
+            }
+            Some(value) => {
+                joints.insert(joint, value as f32);
+            }
+            None => errors.push(FrameError::NotANumber {
+                joint,
+                value: value.clone(),
+            }),
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(Frame { joints })
+    } else {
+        Err(errors)
+    }
+}
+
+/// Check `frame` against the robot's joints, and in `strict` mode against their ranges too.
+pub fn check_frame(
+    frame: &Frame,
+    supported: &[JointInfo],
+    strict: bool,
+) -> Result<(), Vec<FrameError>> {
+    let errors: Vec<_> = frame
+        .joints
+        .iter()
+        .filter_map(
+            |(&joint, &value)| match supported.iter().find(|info| info.joint == joint) {
+                None => Some(FrameError::UnsupportedJoint { joint }),
+                Some(info) if strict && !(info.min..=info.max).contains(&value) => {
+                    Some(FrameError::OutOfRange {
+                        joint,
+                        value,
+                        min: info.min,
+                        max: info.max,
+                    })
+                }
+                Some(_) => None,
+            },
+        )
+        .collect();
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
@@ -0,0 +1,54 @@
+use axum::{extract::State, http::StatusCode, Json};
+
+use humanoid::Halt;
+
+use crate::AppState;
+
+type JsonResponse = (StatusCode, Json<serde_json::Value>);
+
+/// Whether the robot is being driven, and why not if it is halted.
+pub(crate) async fn health_handler(State(state): State<AppState>) -> Json<serde_json::Value> {
+    let halt = state.frame_queue.halted();
+    let status = if halt.is_some() { "halted" } else { "ok" };
+    Json(serde_json::json!({ "status": status, "halt": halt }))
+}
+
+/// Cut torque and stop driving the robot until `POST /halt/reset`. Halted even if cutting
+/// torque fails, in which case the control loop keeps trying.
+pub(crate) async fn estop_handler(State(state): State<AppState>) -> JsonResponse {
+    match state.frame_queue.emergency_stop(Halt::EmergencyStop).await {
+        Ok(_) => (StatusCode::OK, status_response(&state)),
+        Err(e) => {
+            println!("Failed to cut torque: {:?}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({
+                    "error": format!("Halted, but failed to cut torque: {}", e),
+                    "halt": state.frame_queue.halted(),
+                })),
+            )
+        }
+    }
+}
+
+fn status_response(state: &AppState) -> Json<serde_json::Value> {
+    Json(serde_json::json!({ "halt": state.frame_queue.halted() }))
+}
+
+pub(crate) async fn status_handler(State(state): State<AppState>) -> Json<serde_json::Value> {
+    status_response(&state)
+}
+
+/// Drive the robot again after a fall or emergency stop.
+pub(crate) async fn reset_handler(State(state): State<AppState>) -> JsonResponse {
+    match state.frame_queue.reset_halt() {
+        Some(halt) => (
+            StatusCode::OK,
+            Json(serde_json::json!({ "reset": halt, "halt": state.frame_queue.halted() })),
+        ),
+        None => (
+            StatusCode::CONFLICT,
+            Json(serde_json::json!({ "error": "Not halted" })),
+        ),
+    }
+}
@@ -1,136 +1,191 @@
 use std::collections::BTreeMap;
 use std::sync::Arc;
 
-use bon::Builder;
 use kbot::ActuatorId;
 use tokio::sync::Mutex;
 
+use humanoid::check_joints;
+use humanoid::BalanceConfig;
+use humanoid::BalanceController;
+use humanoid::Calibrate;
+use humanoid::Calibration;
 use humanoid::Humanoid;
+use humanoid::Imu;
+use humanoid::ImuReading;
 use humanoid::Joint;
+use humanoid::JointInfo;
 use humanoid::JointPosition;
+use humanoid::JointProfile;
+use humanoid::Quaternion;
+use humanoid::RobotIdentity;
+use humanoid::ServoCalibration;
+use humanoid::ServoHealth;
+use humanoid::ServoProtection;
+use humanoid::ServoTelemetry;
+use humanoid::TorqueCutoff;
+
+/// Profile used by [`KBot::new`], see `profiles/kbot.toml`.
+pub const K_BOT_PROFILE: &str = include_str!("../profiles/kbot.toml");
+
+/// Actuators run at full torque unless throttled.
+pub const NOMINAL_TORQUE: f32 = 100.0;
 
 #[derive(Clone)]
 pub struct KBot {
     client: Arc<Mutex<kbot::Client>>,
-    calibration: KBotCalibration,
-}
-
-#[derive(Builder, Clone, Default)]
-pub struct KBotCalibration {
-    // shoulder
-    pub right_arm_shoulder_pitch_min: f32,
-    pub right_arm_shoulder_pitch_max: f32,
-    pub right_arm_shoulder_roll_min: f32,
-    pub right_arm_shoulder_roll_max: f32,
-    pub right_arm_shoulder_yaw_min: f32,
-    pub right_arm_shoulder_yaw_max: f32,
-    pub right_arm_elbow_pitch_min: f32,
-    pub right_arm_elbow_pitch_max: f32,
-    pub right_arm_elbow_roll_min: f32,
-    pub right_arm_elbow_roll_max: f32,
-
-    pub left_arm_shoulder_pitch_min: f32,
-    pub left_arm_shoulder_pitch_max: f32,
-    pub left_arm_shoulder_roll_min: f32,
-    pub left_arm_shoulder_roll_max: f32,
-    pub left_arm_shoulder_yaw_min: f32,
-    pub left_arm_shoulder_yaw_max: f32,
-    pub left_arm_elbow_pitch_min: f32,
-    pub left_arm_elbow_pitch_max: f32,
-    pub left_arm_elbow_roll_min: f32,
-    pub left_arm_elbow_roll_max: f32,
+    profile: JointProfile,
+    calibration: Option<Calibration>,
+    balance: Option<BalanceController>,
+    // Not behind the lock, for emergency stops
+    cutoff_client: kbot::Client,
 }
 
 impl KBot {
     pub fn new(client: kbot::Client) -> Self {
+        let profile = JointProfile::from_toml(K_BOT_PROFILE).expect("valid built-in profile");
+        Self::with_profile(client, profile)
+    }
+
+    pub fn with_profile(client: kbot::Client, profile: JointProfile) -> Self {
+        let cutoff_client = client.clone();
         let client = Arc::new(tokio::sync::Mutex::new(client));
 
         KBot {
             client,
-            calibration: Default::default(),
+            profile,
+            calibration: None,
+            balance: None,
+            cutoff_client,
         }
     }
-}
 
-fn no_such_servo() -> eyre::Report {
-    eyre::eyre!("No such servo")
+    /// Keep the torso upright with `config` while standing, see [`Humanoid::stabilize`]. Fails
+    /// if `config` moves joints the profile does not map, and the built-in profile drives the
+    /// arms alone.
+    pub fn with_balance(mut self, config: BalanceConfig) -> eyre::Result<Self> {
+        config.check_joints(&self.supported_joints())?;
+        self.balance = Some(BalanceController::new(config));
+        Ok(self)
+    }
+
+    pub fn profile(&self) -> &JointProfile {
+        &self.profile
+    }
+
+    async fn send(&self, joints: BTreeMap<Joint, f32>) -> eyre::Result<()> {
+        let joints = joints
+            .into_iter()
+            .map(|(joint, value)| {
+                let mapping = self.profile.mapping(joint)?;
+                eyre::Ok((ActuatorId::try_from(mapping.id)?, mapping.to_servo(value)))
+            })
+            .collect::<Result<BTreeMap<_, _>, _>>()?;
+
+        self.client.lock().await.set_positions(joints).await?;
+        Ok(())
+    }
+
+    fn actuator(&self, joint: Joint) -> eyre::Result<ActuatorId> {
+        Ok(ActuatorId::try_from(self.profile.mapping(joint)?.id)?)
+    }
+
+    fn actuators(&self) -> eyre::Result<Vec<ActuatorId>> {
+        self.profile
+            .joints
+            .values()
+            .map(|mapping| Ok(ActuatorId::try_from(mapping.id)?))
+            .collect()
+    }
 }
 
 impl Humanoid for KBot {
     type JointId = ActuatorId;
 
+    fn supported_joints(&self) -> Vec<JointInfo> {
+        self.profile.supported_joints()
+    }
+
+    /// Runs the robot's own calibration routine on every mapped actuator. The actuators keep
+    /// the result, so only which ones were calibrated is recorded.
     async fn calibrate(&mut self) -> eyre::Result<()> {
+        let robot = self.identity().await?;
+        let actuators = self.actuators()?;
+
+        self.client
+            .lock()
+            .await
+            .calibrate_actuators(&actuators)
+            .await?;
+
+        let servos = actuators
+            .into_iter()
+            .map(|id| ServoCalibration {
+                id: id.into(),
+                min: None,
+                max: None,
+            })
+            .collect();
+        self.calibration = Some(Calibration::new(robot, servos));
+
         Ok(())
     }
 
     fn translate(&self, joint: Joint, value: f32) -> f32 {
-        let map = |min: f32, max: f32, val: f32| {
-            if (max - min).abs() < f32::EPSILON {
-                0.0
-            } else {
-                min + (max - min) * (val / 90.0)
-            }
-        };
+        // Unmapped joints are rejected by set_joints before translating
+        self.profile
+            .joints
+            .get(&joint)
+            .map_or(value, |mapping| mapping.to_servo(value))
+    }
+
+    /// Offset the hips and ankles against the orientation reported by the IMU, if balancing.
+    async fn stabilize(&mut self) -> eyre::Result<()> {
+        if self.balance.is_none() {
+            return Ok(());
+        }
 
-        let out = match joint {
-            Joint::LeftShoulderPitch => map(
-                self.calibration.left_arm_shoulder_pitch_min,
-                self.calibration.left_arm_shoulder_pitch_max,
-                value,
-            ),
-            Joint::LeftShoulderYaw => map(
-                self.calibration.left_arm_shoulder_yaw_min,
-                self.calibration.left_arm_shoulder_yaw_max,
-                value,
-            ),
-            Joint::LeftElbowPitch => map(
-                self.calibration.left_arm_elbow_pitch_min,
-                self.calibration.left_arm_elbow_pitch_max,
-                value,
-            ),
-            Joint::LeftElbowYaw => map(
-                self.calibration.left_arm_elbow_roll_min,
-                self.calibration.left_arm_elbow_roll_max,
-                value,
-            ),
-            Joint::RightShoulderPitch => map(
-                self.calibration.right_arm_shoulder_pitch_min,
-                self.calibration.right_arm_shoulder_pitch_max,
-                value,
-            ),
-            Joint::RightShoulderYaw => map(
-                self.calibration.right_arm_shoulder_yaw_min,
-                self.calibration.right_arm_shoulder_yaw_max,
-                value,
-            ),
-            Joint::RightElbowPitch => map(
-                self.calibration.right_arm_elbow_pitch_min,
-                self.calibration.right_arm_elbow_pitch_max,
-                value,
-            ),
-            Joint::RightElbowYaw => map(
-                self.calibration.right_arm_elbow_roll_min,
-                self.calibration.right_arm_elbow_roll_max,
-                value,
-            ),
-            _ => 0.0,
+        let orientation = self.read_imu().await?.euler();
+        let joints = match self.balance.as_mut() {
+            Some(balance) => balance.update(orientation),
+            None => return Ok(()),
         };
+        if joints.is_empty() {
+            return Ok(());
+        }
 
-        out.clamp(-170., 170.)
+        self.send(joints).await
     }
 
-    async fn stabilize(&mut self) -> eyre::Result<()> {
+    async fn set_torque_enabled(&mut self, enabled: bool) -> eyre::Result<()> {
+        let actuators = self.actuators()?;
+        self.client
+            .lock()
+            .await
+            .set_torque_enabled(&actuators, enabled)
+            .await?;
         Ok(())
     }
 
+    /// Zero torque on every mapped actuator. `None` if the profile maps a joint to an id that
+    /// is not an actuator.
+    fn torque_cutoff(&self) -> Option<TorqueCutoff> {
+        let client = self.cutoff_client.clone();
+        let actuators = self.actuators().ok()?;
+        Some(TorqueCutoff::new(move || {
+            let client = client.clone();
+            let actuators = actuators.clone();
+            async move { Ok(client.set_torque_enabled(&actuators, false).await?) }
+        }))
+    }
+
     async fn get_joint(&self, joint: Joint) -> eyre::Result<JointPosition> {
-        let servo_id: i32 = joint.into();
+        let mapping = self.profile.mapping(joint)?;
 
         let state = self
             .client
             .lock()
             .await
-            .get_actuator_state(ActuatorId::try_from(servo_id)?)
+            .get_actuator_state(ActuatorId::try_from(mapping.id)?)
             .await?;
 
         Ok(JointPosition {
@@ -149,18 +204,115 @@ impl Humanoid for KBot {
         &mut self,
         joints: std::collections::BTreeMap<Joint, f32>,
     ) -> eyre::Result<()> {
-        let joints = joints
+        check_joints(&self.supported_joints(), joints.keys())?;
+
+        let joints = match self.balance.as_mut() {
+            Some(balance) => balance.apply(joints),
+            None => joints,
+        };
+        self.send(joints).await
+    }
+}
+
+impl Imu for KBot {
+    /// Raw values and the orientation estimated by KOS itself.
+    async fn read_imu(&mut self) -> eyre::Result<ImuReading> {
+        let mut client = self.client.lock().await;
+        let values = client.get_imu_values().await?;
+        let quaternion = client.get_quaternion().await?;
+
+        Ok(ImuReading {
+            accel: values.accel.map(|a| a as f32),
+            gyro: values.gyro.map(|g| g as f32),
+            mag: values.mag.map(|mag| mag.map(|m| m as f32)),
+            orientation: Quaternion::new(
+                quaternion.w as f32,
+                quaternion.x as f32,
+                quaternion.y as f32,
+                quaternion.z as f32,
+            )
+            .normalize(),
+        })
+    }
+}
+
+impl ServoTelemetry for KBot {
+    /// Every mapped actuator, in one request.
+    async fn servo_health(&self) -> eyre::Result<Vec<ServoHealth>> {
+        let health = self
+            .client
+            .lock()
+            .await
+            .get_actuator_health(&self.actuators()?)
+            .await?;
+
+        Ok(health
             .into_iter()
-            .map(|(joint, value)| {
-                let servo_id: i32 = joint.into();
-                eyre::Ok((
-                    servo_id.try_into()?,
-                    self.translate(joint.clone(), value.clone()),
-                ))
+            .filter_map(|actuator| {
+                let id = actuator.id as i32;
+                Some(ServoHealth {
+                    joint: self.profile.joint_for_id(id)?,
+                    id,
+                    temperature: actuator.temperature.map(|t| t as f32),
+                    current: actuator.current,
+                    voltage: actuator.voltage,
+                })
             })
-            .collect::<Result<BTreeMap<_, _>, _>>()?;
+            .collect())
+    }
+}
 
-        self.client.lock().await.set_positions(joints).await?;
+impl ServoProtection for KBot {
+    fn nominal_torque(&self) -> f32 {
+        NOMINAL_TORQUE
+    }
+
+    async fn set_torque_limit(&mut self, joint: Joint, torque: f32) -> eyre::Result<()> {
+        let id = self.actuator(joint)?;
+        self.client
+            .lock()
+            .await
+            .set_max_torque(&[id], torque)
+            .await?;
+        Ok(())
+    }
+
+    async fn set_joint_torque_enabled(&mut self, joint: Joint, enabled: bool) -> eyre::Result<()> {
+        let id = self.actuator(joint)?;
+        self.client
+            .lock()
+            .await
+            .set_torque_enabled(&[id], enabled)
+            .await?;
+        Ok(())
+    }
+}
+
+impl Calibrate for KBot {
+    async fn identity(&self) -> eyre::Result<RobotIdentity> {
+        let servo_ids = self.client.lock().await.actuator_ids().await?;
+
+        Ok(RobotIdentity {
+            model: self.profile.name.clone(),
+            servo_ids: servo_ids.into_iter().map(|id| id as i32).collect(),
+        })
+    }
+
+    fn calibration(&self) -> Option<Calibration> {
+        self.calibration.clone()
+    }
+
+    async fn restore_calibration(&mut self, calibration: Calibration) -> eyre::Result<()> {
+        self.calibration = Some(calibration);
         Ok(())
     }
+
+    /// A calibration is still valid as long as it covers every mapped actuator.
+    async fn verify_calibration(&self, calibration: &Calibration) -> eyre::Result<bool> {
+        Ok(self
+            .profile
+            .joints
+            .values()
+            .all(|mapping| calibration.servo(mapping.id).is_some()))
+    }
 }
@@ -0,0 +1,228 @@
+//! Conversion between [`MotionClip`]s and K-Scale `.krec` episodes.
+//!
+//! Clip frames become krec frames of actuator commands, feedback samples frames of actuator
+//! states and IMU samples frames of IMU values, merged into one frame per timestamp. Commands
+//! are mapped between frame values and actuator positions with the robot's [`JointProfile`].
+//! Feedback is already in actuator units and is copied as is.
+
+use std::{collections::BTreeMap, path::Path};
+
+use humanoid::{ClipFrame, ImuSample, JointProfile, MotionClip};
+use kbot::krec::{
+    ActuatorCommand, ActuatorConfig, ActuatorState, ImuQuaternion, ImuValues, KRec, KRecFrame,
+    KRecHeader, Vec3,
+};
+
+pub const KREC_EXTENSION: &str = "krec";
+
+const NANOS_PER_SEC: f64 = 1e9;
+
+pub fn is_krec(path: impl AsRef<Path>) -> bool {
+    path.as_ref()
+        .extension()
+        .is_some_and(|ext| ext == KREC_EXTENSION)
+}
+
+fn path_str(path: &Path) -> eyre::Result<&str> {
+    path.to_str()
+        .ok_or_else(|| eyre::eyre!("{} is not valid UTF-8", path.display()))
+}
+
+/// Load a krec episode as a clip named after the file, unless the episode names its task.
+pub fn load(path: impl AsRef<Path>, profile: &JointProfile) -> eyre::Result<MotionClip> {
+    let path = path.as_ref();
+    let krec = KRec::load(path_str(path)?)?;
+    let name = path.file_stem().unwrap_or_default().to_string_lossy();
+    krec_to_clip(&krec, profile, name)
+}
+
+pub fn save(clip: &MotionClip, profile: &JointProfile, path: impl AsRef<Path>) -> eyre::Result<()> {
+    clip_to_krec(clip, profile)?.save(path_str(path.as_ref())?)
+}
+
+fn timestamp(start: u64, time: f32) -> u64 {
+    start + (time as f64 * NANOS_PER_SEC).round() as u64
+}
+
+fn frame_at(frames: &mut BTreeMap<u64, KRecFrame>, timestamp: u64) -> &mut KRecFrame {
+    frames.entry(timestamp).or_insert_with(|| KRecFrame {
+        real_timestamp: timestamp,
+        ..Default::default()
+    })
+}
+
+fn vec3([x, y, z]: [f32; 3]) -> Vec3 {
+    Vec3 {
+        x: x as f64,
+        y: y as f64,
+        z: z as f64,
+    }
+}
+
+fn from_vec3(v: &Vec3) -> [f32; 3] {
+    [v.x as f32, v.y as f32, v.z as f32]
+}
+
+/// A krec episode of `clip`, timestamped in nanoseconds from its `created_at`.
+pub fn clip_to_krec(clip: &MotionClip, profile: &JointProfile) -> eyre::Result<KRec> {
+    let start = clip.created_at * 1_000_000_000;
+    let mut frames = BTreeMap::new();
+
+    for (clip_frame, frame) in clip.frames.iter().zip(clip.frames()) {
+        let commands = frame
+            .joints
+            .iter()
+            .map(|(&joint, &value)| {
+                let mapping = profile.mapping(joint)?;
+                Ok(ActuatorCommand {
+                    actuator_id: mapping.id as u32,
+                    position: mapping.to_servo(value),
+                    velocity: 0.0,
+                    torque: 0.0,
+                })
+            })
+            .collect::<eyre::Result<Vec<_>>>()?;
+        frame_at(&mut frames, timestamp(start, clip_frame.time)).actuator_commands = commands;
+    }
+
+    for sample in &clip.feedback {
+        let states = sample
+            .joints
+            .iter()
+            .map(|(&joint, &position)| {
+                Ok(ActuatorState {
+                    actuator_id: profile.mapping(joint)?.id as u32,
+                    online: true,
+                    position: Some(position as f64),
+                    ..Default::default()
+                })
+            })
+            .collect::<eyre::Result<Vec<_>>>()?;
+        frame_at(&mut frames, timestamp(start, sample.time)).actuator_states = states;
+    }
+
+    for sample in &clip.imu {
+        frame_at(&mut frames, timestamp(start, sample.time)).imu_values = Some(ImuValues {
+            accel: sample.accel.map(vec3),
+            gyro: sample.gyro.map(vec3),
+            mag: sample.mag.map(vec3),
+            quaternion: sample.quaternion.map(|[x, y, z, w]| ImuQuaternion {
+                x: x as f64,
+                y: y as f64,
+                z: z as f64,
+                w: w as f64,
+            }),
+        });
+    }
+
+    let header = KRecHeader {
+        task: clip.name.clone(),
+        robot_platform: clip.robot.clone().unwrap_or_else(|| profile.name.clone()),
+        start_timestamp: start,
+        end_timestamp: frames.keys().next_back().copied().unwrap_or(start),
+        actuator_configs: profile
+            .joints
+            .iter()
+            .map(|(joint, mapping)| ActuatorConfig {
+                actuator_id: mapping.id as u32,
+                name: Some(format!("{:?}", joint)),
+                ..Default::default()
+            })
+            .collect(),
+        ..Default::default()
+    };
+
+    let mut krec = KRec::new(header);
+    for (step, mut frame) in frames.into_values().enumerate() {
+        frame.inference_step = step as u64;
+        krec.add_frame(frame);
+    }
+    Ok(krec)
+}
+
+/// A clip of the commands, actuator states and IMU values in `krec`.
+///
+/// Actuators that are not in `profile` are left out, so that a whole-body episode can be played
+/// on a robot that only drives some of its joints.
+pub fn krec_to_clip(
+    krec: &KRec,
+    profile: &JointProfile,
+    name: impl Into<String>,
+) -> eyre::Result<MotionClip> {
+    let header = &krec.header;
+    let start = krec
+        .frames
+        .iter()
+        .map(|frame| frame.real_timestamp)
+        .chain((header.start_timestamp != 0).then_some(header.start_timestamp))
+        .min()
+        .unwrap_or_default();
+    let time = |timestamp: u64| (timestamp.saturating_sub(start) as f64 / NANOS_PER_SEC) as f32;
+
+    let mut clip = MotionClip::new(name);
+    if !header.task.is_empty() {
+        clip.name = header.task.clone();
+    }
+    if !header.robot_platform.is_empty() {
+        clip.robot = Some(header.robot_platform.clone());
+    }
+    clip.created_at = start / 1_000_000_000;
+
+    let mut skipped = std::collections::BTreeSet::new();
+    let mut joint_for = |id: u32| {
+        let joint = profile.joint_for_id(id as i32);
+        if joint.is_none() {
+            skipped.insert(id);
+        }
+        joint
+    };
+
+    for frame in &krec.frames {
+        let time = time(frame.real_timestamp);
+
+        let joints: BTreeMap<_, _> = frame
+            .actuator_commands
+            .iter()
+            .filter_map(|command| {
+                let joint = joint_for(command.actuator_id)?;
+                let mapping = profile.mapping(joint).ok()?;
+                Some((joint, mapping.from_servo(command.position)))
+            })
+            .collect();
+        if !joints.is_empty() {
+            clip.frames.push(ClipFrame { time, joints });
+        }
+
+        let joints: BTreeMap<_, _> = frame
+            .actuator_states
+            .iter()
+            .filter_map(|state| Some((joint_for(state.actuator_id)?, state.position? as f32)))
+            .collect();
+        if !joints.is_empty() {
+            clip.feedback.push(ClipFrame { time, joints });
+        }
+
+        if let Some(imu) = &frame.imu_values {
+            clip.imu.push(ImuSample {
+                time,
+                accel: imu.accel.as_ref().map(from_vec3),
+                gyro: imu.gyro.as_ref().map(from_vec3),
+                mag: imu.mag.as_ref().map(from_vec3),
+                quaternion: imu
+                    .quaternion
+                    .as_ref()
+                    .map(|q| [q.x as f32, q.y as f32, q.z as f32, q.w as f32]),
+            });
+        }
+    }
+
+    if !skipped.is_empty() {
+        println!(
+            "Skipped actuators {:?}, which are not in the {} profile",
+            skipped, profile.name
+        );
+    }
+
+    clip.validate()?;
+    Ok(clip)
+}
@@ -0,0 +1,649 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use ::humanoid::{
+    load_or_calibrate, BalanceConfig, Calibrate, CallErrors, ControlLoop, ControlLoopStats,
+    FallConfig, Frame, FrameQueue, Humanoid, Imu, Instrumented, Joint, JointProfile,
+    ProtectionConfig, ProtectionEvents, QueueError, Runtime, SafetyConfig, SafetyLimits,
+    ServoProtection, SimulatedHumanoid, TelemetryLog,
+};
+use serde::Deserialize;
+
+use axum::{
+    extract::{rejection::JsonRejection, Query, State},
+    http::StatusCode,
+    routing::{get, post},
+    Json, Router,
+};
+
+use config::{Backend, BotConfig};
+use frame::{FrameError, QueueMode};
+use playback::PlaybackRequest;
+
+pub mod config;
+pub mod frame;
+pub mod halt;
+pub mod k_bot;
+pub mod krec;
+pub mod metrics;
+pub mod mini_robot;
+pub mod playback;
+pub mod recording;
+pub mod telemetry;
+pub mod udp;
+pub mod ws;
+
+/// Default rate at which frames are sent to the robot.
+pub const CONTROL_RATE_HZ: f32 = 50.0;
+
+/// Limits used when no safety file is configured, see `safety.toml`.
+pub const SAFETY_LIMITS: &str = include_str!("../safety.toml");
+
+/// Connect to the robot chosen in `config`, calibrate it, move it to the initial pose and
+/// stream frames to it until Ctrl-C.
+pub async fn run(config: BotConfig) -> eyre::Result<()> {
+    start(config, None).await
+}
+
+/// Like [`run`], but play `motion` straight away and exit once it finishes.
+pub async fn play(config: BotConfig, motion: PlaybackRequest) -> eyre::Result<()> {
+    start(config, Some(motion)).await
+}
+
+async fn start(config: BotConfig, motion: Option<PlaybackRequest>) -> eyre::Result<()> {
+    let safety = match &config.safety {
+        Some(path) => SafetyConfig::load(path)?,
+        None => SafetyConfig::from_toml(SAFETY_LIMITS)?,
+    };
+    // Commands are limited per control tick
+    let period = ControlLoop::new(config.control_rate_hz).period()?;
+    let profile = config
+        .profile
+        .as_ref()
+        .map(JointProfile::load)
+        .transpose()?;
+    let balance = config
+        .balance
+        .as_ref()
+        .map(BalanceConfig::load)
+        .transpose()?;
+
+    match config.backend {
+        Backend::Zeroth => {
+            let mut client = zeroth::Client::connect(config.robot_address()).await?;
+            println!("Connected to {}", config.robot_address());
+            client.enable_movement().await?;
+
+            let mut robot = match profile {
+                Some(profile) => mini_robot::MiniRobot::with_profile(client, profile),
+                None => mini_robot::MiniRobot::new(client),
+            };
+            if let Some(balance) = balance {
+                robot = robot.with_balance(balance)?;
+            }
+            serve_calibrated(
+                SafetyLimits::new(robot, safety).with_period(period),
+                &config,
+                motion,
+            )
+            .await
+        }
+        Backend::Kbot => {
+            let client = kbot::Client::connect(config.robot_address()).await?;
+            println!("Connected to {}", config.robot_address());
+
+            let mut robot = match profile {
+                Some(profile) => k_bot::KBot::with_profile(client, profile),
+                None => k_bot::KBot::new(client),
+            };
+            if let Some(balance) = balance {
+                robot = robot.with_balance(balance)?;
+            }
+            serve_calibrated(
+                SafetyLimits::new(robot, safety).with_period(period),
+                &config,
+                motion,
+            )
+            .await
+        }
+        Backend::Sim => {
+            let mut robot = SimulatedHumanoid::default();
+            if let Some(balance) = balance {
+                robot = robot.with_balance(balance)?;
+            }
+            let robot = Instrumented::new(
+                SafetyLimits::new(robot, safety).with_period(period),
+                metrics::is_robot_call_error,
+            );
+            serve(Runtime::new(robot), &config, motion).await
+        }
+    }
+}
+
+async fn serve_calibrated<H: Calibrate + Imu + ServoProtection>(
+    robot: H,
+    config: &BotConfig,
+    motion: Option<PlaybackRequest>,
+) -> eyre::Result<()> {
+    let robot = Runtime::new(Instrumented::new(robot, metrics::is_robot_call_error));
+    load_or_calibrate(
+        &mut *robot.lock().await,
+        &config.calibration_file,
+        config.recalibrate,
+    )
+    .await?;
+    println!("Calibrated");
+
+    tokio::time::sleep(Duration::from_secs(1)).await;
+    serve(robot, config, motion).await
+}
+
+async fn serve<H: Imu + ServoProtection>(
+    robot: Runtime<Instrumented<H>>,
+    config: &BotConfig,
+    motion: Option<PlaybackRequest>,
+) -> eyre::Result<()> {
+    let fall = config.fall.as_ref().map(FallConfig::load).transpose()?;
+    let protection = config
+        .protection
+        .as_ref()
+        .map(ProtectionConfig::load)
+        .transpose()?;
+    let pose = match &config.initial_pose {
+        Some(pose) => pose.clone(),
+        None if config.backend == Backend::Zeroth => zeroth_initial_pose(),
+        None => BTreeMap::new(),
+    };
+    if !pose.is_empty() {
+        set_pose(&robot, &pose).await?;
+        tokio::time::sleep(Duration::from_secs(2)).await;
+    }
+
+    let mut state = AppState::from_config(robot.queue(), config)?;
+    state.call_errors = robot.lock().await.errors();
+
+    let udp = match &config.udp_bind {
+        Some(addr) => {
+            let listener = udp::UdpListener::bind(addr, robot.queue(), config.strict).await?;
+            let stats = listener.stats();
+            tokio::spawn(async move {
+                if let Err(e) = listener.run().await {
+                    println!("UDP listener stopped: {}", e);
+                }
+            });
+            Some(stats)
+        }
+        None => None,
+    };
+
+    state.udp = udp.clone();
+    let fall_monitor = fall.map(|fall| robot.detect_falls(fall));
+    let watchdog = config
+        .watchdog_ms
+        .map(|ms| robot.watch_frames(Duration::from_millis(ms)));
+    let telemetry = (config.telemetry_interval_ms > 0).then(|| {
+        robot.poll_telemetry(
+            Duration::from_millis(config.telemetry_interval_ms),
+            state.telemetry.clone(),
+        )
+    });
+    let protection = protection.map(|protection| {
+        robot.protect_servos(protection, &state.telemetry, state.protection.clone())
+    });
+
+    serve_http(robot, state, config, motion).await?;
+
+    for monitor in [fall_monitor, watchdog, telemetry, protection]
+        .into_iter()
+        .flatten()
+    {
+        monitor.stop().await?;
+    }
+    if let Some(stats) = udp {
+        println!("UDP frames: {:?}", stats.snapshot());
+    }
+    Ok(())
+}
+
+/// Shared by the HTTP routes.
+#[derive(Clone)]
+pub struct AppState {
+    pub frame_queue: Arc<FrameQueue>,
+    /// Reject frames with values outside the robot's joint ranges instead of clamping them.
+    pub strict: bool,
+    /// Where `POST /playback` reads motion files from.
+    pub motions_dir: PathBuf,
+    /// Rate motion frames were recorded at, unless a playback request says otherwise.
+    pub motion_fps: f32,
+    /// Maps joints to actuator ids in `.krec` episodes.
+    pub profile: JointProfile,
+    /// Servo health read by [`Runtime::poll_telemetry`], see `GET /telemetry`.
+    pub telemetry: Arc<TelemetryLog>,
+    /// Rule hits from [`Runtime::protect_servos`], see `GET /protection`.
+    pub protection: Arc<ProtectionEvents>,
+    /// Counters of the running control loop, for `GET /metrics`.
+    pub control: Option<Arc<ControlLoopStats>>,
+    /// Calls that failed talking to the robot, see [`Instrumented`] and
+    /// [`metrics::is_robot_call_error`].
+    pub call_errors: Arc<CallErrors>,
+    pub udp: Option<Arc<udp::UdpStats>>,
+}
+
+impl AppState {
+    pub fn new(frame_queue: Arc<FrameQueue>) -> Self {
+        Self {
+            frame_queue,
+            strict: false,
+            motions_dir: PathBuf::from(playback::DEFAULT_MOTIONS_DIR),
+            motion_fps: CONTROL_RATE_HZ,
+            profile: JointProfile::from_toml(k_bot::K_BOT_PROFILE).expect("valid built-in profile"),
+            telemetry: Arc::new(TelemetryLog::for_history(
+                Duration::from_secs(telemetry::DEFAULT_TELEMETRY_HISTORY_SECS),
+                Duration::from_millis(telemetry::DEFAULT_TELEMETRY_INTERVAL_MS),
+            )),
+            protection: Default::default(),
+            control: None,
+            call_errors: Default::default(),
+            udp: None,
+        }
+    }
+
+    pub fn from_config(frame_queue: Arc<FrameQueue>, config: &BotConfig) -> eyre::Result<Self> {
+        Ok(Self {
+            frame_queue,
+            strict: config.strict,
+            motions_dir: config.motions_dir.clone(),
+            motion_fps: config.control_rate_hz,
+            profile: config.joint_profile()?,
+            telemetry: Arc::new(TelemetryLog::for_history(
+                Duration::from_secs(config.telemetry_history_secs),
+                Duration::from_millis(config.telemetry_interval_ms),
+            )),
+            protection: Default::default(),
+            control: None,
+            call_errors: Default::default(),
+            udp: None,
+        })
+    }
+
+    /// Parse a JSON frame and check it against the robot's joints.
+    pub fn parse_frame(&self, json: &serde_json::Value) -> Result<Frame, Vec<FrameError>> {
+        let frame = frame::parse_frame(json)?;
+        frame::check_frame(&frame, self.frame_queue.supported_joints(), self.strict)?;
+        Ok(frame)
+    }
+
+    pub fn enqueue(&self, frame: Frame, mode: QueueMode) -> Result<(), QueueError> {
+        match mode {
+            QueueMode::Overwrite => self.frame_queue.overwrite(frame),
+            QueueMode::Push => self.frame_queue.push(frame),
+        }
+    }
+}
+
+/// HTTP routes for streaming frames into the frame queue.
+///
+/// - `POST /frame` overwrites the queue with one frame, or appends it with `?mode=push`.
+/// - `POST /frames` appends a batch of frames to play in order.
+/// - `GET /queue` returns the queue length and current frame, `DELETE /queue` clears it.
+/// - `POST /playback` plays a motion file, see [`PlaybackRequest`]. `GET /playback` reports
+///   its progress and `POST /playback/{pause,resume,stop}` control it.
+/// - `POST /recording/start` records every frame sent to the robot, see
+///   [`recording::RecordRequest`]. `POST /recording/stop` saves it to the motions directory,
+///   where it can be played back by name.
+/// - `GET /status` reports whether the robot is being driven. `POST /estop` cuts torque and
+///   halts it, as do falls and the frame watchdog. `GET /halt` reports why it is halted and
+///   `POST /halt/reset` drives it again.
+/// - `GET /telemetry` returns the latest servo temperature, current and voltage.
+///   `GET /telemetry/history?since=` returns the readings kept, and `GET /telemetry/stream`
+///   sends each new one as a server-sent event. `GET /protection` lists the thermal and
+///   overcurrent rules hit.
+/// - `GET /metrics` exposes counters, queue depth, step latency, servo health and the halt
+///   state in the Prometheus text format.
+/// - `/ws` streams frames over a WebSocket, see [`ws::FrameMessage`].
+pub fn router(state: AppState) -> Router {
+    Router::new()
+        .route("/status", get(halt::health_handler))
+        .route("/estop", post(halt::estop_handler))
+        .route("/frame", post(frame_handler))
+        .route("/frames", post(frames_handler))
+        .route("/queue", get(queue_handler).delete(clear_queue_handler))
+        .route(
+            "/playback",
+            get(playback::status_handler).post(playback::play_handler),
+        )
+        .route("/playback/pause", post(playback::pause_handler))
+        .route("/playback/resume", post(playback::resume_handler))
+        .route("/playback/stop", post(playback::stop_handler))
+        .route("/recording", get(recording::status_handler))
+        .route("/recording/start", post(recording::start_handler))
+        .route("/recording/stop", post(recording::stop_handler))
+        .route("/halt", get(halt::status_handler))
+        .route("/halt/reset", post(halt::reset_handler))
+        .route("/telemetry", get(telemetry::latest_handler))
+        .route("/telemetry/history", get(telemetry::history_handler))
+        .route("/telemetry/stream", get(telemetry::stream_handler))
+        .route("/protection", get(telemetry::protection_handler))
+        .route("/metrics", get(metrics::metrics_handler))
+        .route("/ws", get(ws::ws_handler))
+        .with_state(state)
+}
+
+pub async fn stream_frame_from_server<H: Humanoid>(
+    robot: Runtime<H>,
+    config: &BotConfig,
+) -> eyre::Result<()> {
+    let state = AppState::from_config(robot.queue(), config)?;
+    serve_http(robot, state, config, None).await
+}
+
+/// Serve HTTP and run the control loop until Ctrl-C, or until `motion` finishes playing.
+async fn serve_http<H: Humanoid>(
+    robot: Runtime<H>,
+    mut state: AppState,
+    config: &BotConfig,
+    motion: Option<PlaybackRequest>,
+    // frame_queue: Arc<crossbeam::queue::SegQueue<Frame>>,
+) -> eyre::Result<()> {
+    let tcp_listener = tokio::net::TcpListener::bind(&config.bind).await?;
+    println!("Run loop started");
+    let control_loop = robot.run(config.control_rate_hz)?;
+    state.control = Some(control_loop.stats().clone());
+    let app = router(state.clone());
+
+    // run our app with hyper, listening globally on port 3000
+    // let listener = tokio::net::TcpListener::bind("0.0.0.0:3000").await.unwrap();
+
+    // tokio::task::spawn(async move {
+    //     loop {
+    //         println!("LOOPing {}", robot.queue.len());
+    //         let out = robot.step().await.unwrap();
+    //         if !out {
+    //             break;
+    //         }
+    //     }
+    // }).await.unwrap();
+
+    let _handle = tokio::spawn(async {
+        println!("Listening on http://{}", tcp_listener.local_addr().unwrap());
+
+        axum::serve(tcp_listener, app.into_make_service())
+            .await
+            .unwrap();
+    });
+
+    match motion {
+        Some(motion) => {
+            state.play(&motion)?;
+            tokio::select! {
+                result = tokio::signal::ctrl_c() => result?,
+                _ = async {
+                    while state.frame_queue.is_playing() {
+                        tokio::time::sleep(Duration::from_millis(100)).await;
+                    }
+                } => println!("Playback finished"),
+            }
+        }
+        None => tokio::signal::ctrl_c().await?,
+    }
+    println!("Stopping run loop");
+    control_loop.stop().await?;
+
+    Ok(())
+}
+
+/// Read a motion file into frames, see [`playback::read_motion`].
+pub fn file_to_frames(file: impl AsRef<Path>) -> eyre::Result<Vec<Frame>> {
+    playback::read_motion(file)?
+        .into_iter()
+        .enumerate()
+        .map(|(i, frame)| {
+            frame_json_to_frame(frame).map_err(|e| e.wrap_err(format!("Frame {}", i)))
+        })
+        .collect()
+}
+
+/// Parse a JSON object of joint ids or names to values, see [`frame::parse_frame`].
+pub fn frame_json_to_frame(frame_json: serde_json::Value) -> eyre::Result<Frame> {
+    frame::parse_frame(&frame_json).map_err(|mut errors| errors.swap_remove(0).into())
+}
+
+/// Standing pose the Zeroth moves to before accepting frames.
+pub fn zeroth_initial_pose() -> BTreeMap<Joint, f32> {
+    let mut initial_joints_btree = BTreeMap::new();
+    initial_joints_btree.insert(Joint::RightElbowYaw, 0.0);
+    initial_joints_btree.insert(Joint::LeftElbowYaw, 0.0);
+    initial_joints_btree.insert(Joint::RightShoulderPitch, 90.0);
+    initial_joints_btree.insert(Joint::LeftShoulderPitch, 90.0);
+
+    initial_joints_btree.insert(Joint::RightShoulderYaw, 0.0);
+    initial_joints_btree.insert(Joint::LeftShoulderYaw, 0.0);
+    initial_joints_btree.insert(Joint::LeftAnklePitch, -20.0);
+    initial_joints_btree.insert(Joint::RightAnklePitch, 20.0); // TODO: REFVESRSE
+    initial_joints_btree.insert(Joint::LeftHipYaw, 90.0); // TODO: REVERSE
+    initial_joints_btree.insert(Joint::RightHipYaw, 0.0);
+    initial_joints_btree.insert(Joint::LeftKneeYaw, 45.0); // is center, move to 0
+    initial_joints_btree.insert(Joint::LeftKneePitch, 10.0);
+    initial_joints_btree.insert(Joint::RightKneeYaw, 45.0);
+    initial_joints_btree.insert(Joint::RightKneePitch, 80.0);
+    initial_joints_btree.insert(Joint::LeftHipPitch, 65.0); // recenter on 0
+    initial_joints_btree.insert(Joint::RightHipPitch, 25.0); // Reverse, recenter on 0
+
+    // initial_joints_btree.insert(Joint::RightKneePitch, -90.0);
+
+    initial_joints_btree
+}
+
+pub async fn initial_position<H: Humanoid>(robot: &Runtime<H>) -> eyre::Result<()> {
+    set_pose(robot, &zeroth_initial_pose()).await
+}
+
+/// Move every joint in `pose` directly, bypassing the frame queue.
+pub async fn set_pose<H: Humanoid>(
+    robot: &Runtime<H>,
+    pose: &BTreeMap<Joint, f32>,
+) -> eyre::Result<()> {
+    // robot
+    //     .lock()
+    //     .await
+    //     .set_joints(initial_joints_btree)
+    //     .await
+    //     .unwrap();
+    let mut lock = robot.lock().await;
+    for (joint, value) in pose {
+        lock.set_joint(*joint, *value).await?;
+    }
+
+    Ok(())
+}
+
+// 0 -90 90
+
+async fn frame_handler(
+    State(state): State<AppState>,
+    Query(params): Query<FrameParams>,
+    payload: Result<Json<FrameData>, JsonRejection>,
+) -> (StatusCode, Json<serde_json::Value>) {
+    let payload = match payload {
+        Ok(Json(payload)) => payload,
+        Err(e) => return json_rejection_response(e),
+    };
+
+    println!("Received frame: {:?}", payload.joints);
+    let frame = match state.parse_frame(&payload.joints) {
+        Ok(frame) => frame,
+        Err(errors) => return frame_errors_response(&errors),
+    };
+
+    println!("Received frame: {:?}", frame);
+    if let Err(e) = state.enqueue(frame, params.mode) {
+        return queue_error_response(e);
+    }
+
+    (
+        StatusCode::CREATED,
+        Json(serde_json::json!({ "queue_len": state.frame_queue.len() })),
+    )
+}
+
+/// Append a batch of frames. Nothing is queued unless every frame is valid, and a halt either
+/// refuses the whole batch or drops it, see [`FrameQueue::push_all`].
+async fn frames_handler(
+    State(state): State<AppState>,
+    payload: Result<Json<FramesData>, JsonRejection>,
+) -> (StatusCode, Json<serde_json::Value>) {
+    let payload = match payload {
+        Ok(Json(payload)) => payload,
+        Err(e) => return json_rejection_response(e),
+    };
+
+    let mut frames = Vec::with_capacity(payload.frames.len());
+    let mut errors = Vec::new();
+    for (index, json) in payload.frames.iter().enumerate() {
+        match state.parse_frame(json) {
+            Ok(frame) => frames.push(frame),
+            Err(e) => errors.extend(e.into_iter().map(|e| (Some(index), e))),
+        }
+    }
+    if !errors.is_empty() {
+        return errors_response(&errors);
+    }
+
+    let queued = frames.len();
+    if let Err(e) = state.frame_queue.push_all(frames) {
+        return queue_error_response(e);
+    }
+
+    (
+        StatusCode::CREATED,
+        Json(serde_json::json!({
+            "queued": queued,
+            "queue_len": state.frame_queue.len(),
+        })),
+    )
+}
+
+async fn queue_handler(State(state): State<AppState>) -> Json<serde_json::Value> {
+    Json(serde_json::json!({
+        "len": state.frame_queue.len(),
+        "current": state.frame_queue.current(),
+        "playing": state.frame_queue.is_playing(),
+    }))
+}
+
+async fn clear_queue_handler(State(state): State<AppState>) -> Json<serde_json::Value> {
+    let cleared = state.frame_queue.clear();
+    println!("Cleared {} queued frames", cleared);
+    Json(serde_json::json!({ "cleared": cleared }))
+}
+
+pub(crate) fn json_rejection_response(e: JsonRejection) -> (StatusCode, Json<serde_json::Value>) {
+    (
+        e.status(),
+        Json(serde_json::json!({
+            "error": e.body_text(),
+            "errors": [{ "reason": "invalid_json" }],
+        })),
+    )
+}
+
+/// 409 while halted, otherwise as [`errors_response`].
+pub(crate) fn queue_error_response(e: QueueError) -> (StatusCode, Json<serde_json::Value>) {
+    match e {
+        QueueError::Unsupported(e) => {
+            let errors: Vec<_> = e
+                .joints
+                .into_iter()
+                .map(|joint| FrameError::UnsupportedJoint { joint })
+                .collect();
+            frame_errors_response(&errors)
+        }
+        QueueError::Halted(halt) => {
+            println!("Rejected frame while halted: {:?}", halt);
+            (
+                StatusCode::CONFLICT,
+                Json(serde_json::json!({
+                    "error": "Robot is halted, POST /halt/reset to drive it again",
+                    "halt": halt,
+                })),
+            )
+        }
+    }
+}
+
+fn frame_errors_response(errors: &[FrameError]) -> (StatusCode, Json<serde_json::Value>) {
+    let errors: Vec<_> = errors.iter().map(|e| (None, e.clone())).collect();
+    errors_response(&errors)
+}
+
+/// 400 for malformed frames, 422 for frames this robot cannot use, with every error listed.
+/// Errors from a batch carry the index of their frame.
+pub(crate) fn errors_response(
+    errors: &[(Option<usize>, FrameError)],
+) -> (StatusCode, Json<serde_json::Value>) {
+    let status = if errors.iter().any(|(_, e)| e.is_malformed()) {
+        StatusCode::BAD_REQUEST
+    } else {
+        StatusCode::UNPROCESSABLE_ENTITY
+    };
+
+    let messages: Vec<_> = errors
+        .iter()
+        .map(|(index, e)| match index {
+            Some(index) => format!("frame {}: {}", index, e),
+            None => e.to_string(),
+        })
+        .collect();
+    println!("Rejected frame: {}", messages.join(", "));
+
+    let details: Vec<_> = errors
+        .iter()
+        .map(|(index, e)| {
+            let mut detail = serde_json::to_value(e).expect("frame errors serialize");
+            detail["message"] = e.to_string().into();
+            if let Some(index) = index {
+                detail["frame"] = (*index).into();
+            }
+            detail
+        })
+        .collect();
+
+    (
+        status,
+        Json(serde_json::json!({
+            "error": messages.join(", "),
+            "errors": details,
+        })),
+    )
+}
+
+// the input to our `create_user` handler
+#[derive(Deserialize, Debug)]
+struct FrameData {
+    joints: serde_json::Value,
+}
+
+#[derive(Deserialize, Debug)]
+struct FramesData {
+    /// Each entry is the `joints` object of a `POST /frame`.
+    frames: Vec<serde_json::Value>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct FrameParams {
+    #[serde(default)]
+    mode: QueueMode,
+}
+
+/*
+{
+ joints: {
+ "15": 0.0,
+ }
+}
+
+*/
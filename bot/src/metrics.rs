@@ -0,0 +1,235 @@
+//! `GET /metrics` in the Prometheus text format, for scraping into dashboards.
+
+use std::fmt::{Display, Write};
+
+use axum::{extract::State, http::header, response::IntoResponse};
+
+use crate::AppState;
+
+/// Content type of the Prometheus text format.
+pub const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// Metrics in the Prometheus text exposition format.
+#[derive(Debug, Default)]
+struct Exposition {
+    text: String,
+}
+
+impl Exposition {
+    /// Start a metric family. Every sample of it must follow.
+    fn family(&mut self, name: &str, kind: &str, help: &str) {
+        let _ = writeln!(self.text, "# HELP {} {}", name, help);
+        let _ = writeln!(self.text, "# TYPE {} {}", name, kind);
+    }
+
+    fn sample(&mut self, name: &str, labels: &[(&str, &str)], value: impl Display) {
+        self.text.push_str(name);
+        if !labels.is_empty() {
+            let labels: Vec<_> = labels
+                .iter()
+                .map(|(label, value)| format!("{}=\"{}\"", label, escape(value)))
+                .collect();
+            let _ = write!(self.text, "{{{}}}", labels.join(","));
+        }
+        let _ = writeln!(self.text, " {}", value);
+    }
+
+    /// A family with a single unlabelled sample.
+    fn single(&mut self, name: &str, kind: &str, help: &str, value: impl Display) {
+        self.family(name, kind, help);
+        self.sample(name, &[], value);
+    }
+
+    fn into_string(self) -> String {
+        self.text
+    }
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Whether `e` came from talking to the Zeroth or K-Bot, a failed connection or a request the
+/// robot answered with an error. Calls the bot refuses itself, e.g. for joints the profile does
+/// not map, are not robot call errors.
+pub fn is_robot_call_error(e: &eyre::Report) -> bool {
+    e.chain().any(|e| {
+        matches!(
+            e.downcast_ref::<zeroth::Error>(),
+            Some(zeroth::Error::Connection { .. } | zeroth::Error::Request { .. })
+        ) || matches!(
+            e.downcast_ref::<kbot::Error>(),
+            Some(kbot::Error::Connection { .. } | kbot::Error::Request { .. })
+        )
+    })
+}
+
+/// Everything the bot counts, see [`AppState`].
+pub fn render(state: &AppState) -> String {
+    let mut out = Exposition::default();
+    let queue = &state.frame_queue;
+
+    out.single(
+        "bot_frames_received_total",
+        "counter",
+        "Frames queued over HTTP, WebSocket or UDP.",
+        queue.frames_received(),
+    );
+    out.single(
+        "bot_queue_depth",
+        "gauge",
+        "Frames waiting in the queue.",
+        queue.len(),
+    );
+
+    if let Some(control) = &state.control {
+        out.single(
+            "bot_frames_applied_total",
+            "counter",
+            "Frames sent to the robot by the control loop.",
+            control.commands(),
+        );
+        out.single(
+            "bot_control_ticks_total",
+            "counter",
+            "Control loop ticks.",
+            control.ticks(),
+        );
+        out.single(
+            "bot_control_missed_deadlines_total",
+            "counter",
+            "Control loop ticks that ran past the next one.",
+            control.missed_deadlines(),
+        );
+        out.single(
+            "bot_control_errors_total",
+            "counter",
+            "Frames, balance corrections and torque changes the robot failed to apply.",
+            control.errors(),
+        );
+
+        let latency = control.step_latency();
+        out.family(
+            "bot_step_latency_seconds",
+            "histogram",
+            "Time from each control loop tick to the end of its work.",
+        );
+        for (le, count) in latency.buckets() {
+            out.sample(
+                "bot_step_latency_seconds_bucket",
+                &[("le", &le.to_string())],
+                count,
+            );
+        }
+        out.sample(
+            "bot_step_latency_seconds_bucket",
+            &[("le", "+Inf")],
+            latency.count(),
+        );
+        out.sample(
+            "bot_step_latency_seconds_sum",
+            &[],
+            latency.sum().as_secs_f64(),
+        );
+        out.sample("bot_step_latency_seconds_count", &[], latency.count());
+    }
+
+    out.family(
+        "bot_robot_call_errors_total",
+        "counter",
+        "Calls that failed talking to the robot, by method.",
+    );
+    for (method, count) in state.call_errors.counts() {
+        out.sample("bot_robot_call_errors_total", &[("method", method)], count);
+    }
+
+    let halt = queue.halted();
+    out.single(
+        "bot_halted",
+        "gauge",
+        "1 while the robot is halted by an emergency stop, fall, watchdog or overcurrent.",
+        u8::from(halt.is_some()),
+    );
+    out.family(
+        "bot_halt_cause",
+        "gauge",
+        "1 for the cause of the current halt.",
+    );
+    if let Some(halt) = halt {
+        let cause = serde_json::to_value(&halt).expect("halts serialize");
+        if let Some(cause) = cause["cause"].as_str() {
+            out.sample("bot_halt_cause", &[("cause", cause)], 1);
+        }
+    }
+
+    if let Some(snapshot) = state.telemetry.latest() {
+        let servos = &snapshot.servos;
+        let families = [
+            (
+                "bot_servo_temperature_celsius",
+                "Servo temperature.",
+                servos.iter().map(|s| s.temperature).collect::<Vec<_>>(),
+            ),
+            (
+                "bot_servo_current_amperes",
+                "Current drawn by each servo.",
+                servos.iter().map(|s| s.current).collect(),
+            ),
+            (
+                "bot_servo_voltage_volts",
+                "Supply voltage at each servo.",
+                servos.iter().map(|s| s.voltage).collect(),
+            ),
+        ];
+        for (name, help, values) in families {
+            out.family(name, "gauge", help);
+            for (servo, value) in servos.iter().zip(values) {
+                if let Some(value) = value {
+                    let joint = format!("{:?}", servo.joint);
+                    let id = servo.id.to_string();
+                    out.sample(name, &[("joint", &joint), ("id", &id)], value);
+                }
+            }
+        }
+    }
+    out.single(
+        "bot_protection_events_total",
+        "counter",
+        "Thermal and overcurrent rules hit.",
+        state.protection.count(),
+    );
+
+    if let Some(udp) = &state.udp {
+        let udp = udp.snapshot();
+        out.family(
+            "bot_udp_datagrams_total",
+            "counter",
+            "UDP frame datagrams, by what became of them.",
+        );
+        for (outcome, count) in [
+            ("received", udp.received),
+            ("applied", udp.applied),
+            ("reordered", udp.reordered),
+            ("duplicate", udp.duplicates),
+            ("malformed", udp.malformed),
+            ("rejected", udp.rejected),
+        ] {
+            out.sample("bot_udp_datagrams_total", &[("outcome", outcome)], count);
+        }
+        out.single(
+            "bot_udp_lost_total",
+            "counter",
+            "UDP frame sequence numbers skipped over.",
+            udp.lost,
+        );
+    }
+
+    out.into_string()
+}
+
+pub(crate) async fn metrics_handler(State(state): State<AppState>) -> impl IntoResponse {
+    ([(header::CONTENT_TYPE, CONTENT_TYPE)], render(&state))
+}
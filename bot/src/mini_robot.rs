@@ -1,275 +1,170 @@
 use std::sync::Arc;
 
-use bon::Builder;
 use eyre::Ok;
 use tokio::sync::Mutex;
 use zeroth::ServoId;
 
+use humanoid::check_joints;
+use humanoid::BalanceConfig;
+use humanoid::BalanceController;
+use humanoid::Calibrate;
+use humanoid::Calibration;
+use humanoid::ComplementaryFilter;
 use humanoid::Humanoid;
+use humanoid::Imu;
+use humanoid::ImuReading;
 use humanoid::Joint;
+use humanoid::JointInfo;
 use humanoid::JointPosition;
+use humanoid::JointProfile;
+use humanoid::RobotIdentity;
+use humanoid::ServoCalibration;
+use humanoid::ServoHealth;
+use humanoid::ServoProtection;
+use humanoid::ServoTelemetry;
+use humanoid::TorqueCutoff;
+use humanoid::STANDARD_GRAVITY;
 use zeroth::TorqueEnableSetting;
+use zeroth::TorqueSetting;
+
+/// How far outside its calibrated range a servo may read before the calibration is considered
+/// stale.
+pub const CALIBRATION_TOLERANCE: f32 = 5.0;
+
+/// Percent of full torque every servo is set to when torque is enabled.
+pub const NOMINAL_TORQUE: f32 = 50.0;
+
+/// Profile used by [`MiniRobot::new`], see `profiles/zeroth.toml`.
+pub const ZEROTH_PROFILE: &str = include_str!("../profiles/zeroth.toml");
 
 #[derive(Clone)]
 pub struct MiniRobot {
     client: Arc<Mutex<zeroth::Client>>,
-    calibration: MiniRobotCalibration,
+    profile: JointProfile,
+    calibration: Option<Calibration>,
+    balance: Option<BalanceController>,
+    imu_filter: ComplementaryFilter,
+    // Not behind the lock, for emergency stops
+    cutoff_client: zeroth::Client,
 }
 
 impl MiniRobot {
-    pub async fn disable_movement(&mut self) {
-        self.client.lock().await.disable_movement().await.unwrap();
+    pub async fn disable_movement(&mut self) -> eyre::Result<()> {
+        self.client.lock().await.disable_movement().await?;
+        Ok(())
     }
 
-    pub async fn enable_movement(&mut self) {
-        self.client.lock().await.enable_movement().await.unwrap();
+    pub async fn enable_movement(&mut self) -> eyre::Result<()> {
+        self.client.lock().await.enable_movement().await?;
+        Ok(())
     }
 }
 
-#[derive(Builder, Clone, Default)]
-pub struct MiniRobotCalibration {
-    // shoulder
-    pub left_shoulder_yaw_min: f32,
-    pub left_shoulder_yaw_max: f32,
-    pub left_elbow_yaw_min: f32,
-    pub left_elbow_yaw_max: f32,
-    pub right_elbow_yaw_min: f32,
-    pub right_elbow_yaw_max: f32,
-    pub left_shoulder_pitch_min: f32,
-    pub left_shoulder_pitch_max: f32,
-    pub right_shoulder_pitch_min: f32,
-    pub right_shoulder_pitch_max: f32,
-    pub right_shoulder_yaw_min: f32,
-    pub right_shoulder_yaw_max: f32,
-
-    // hip
-    pub left_hip_pitch_min: f32,
-    pub left_hip_pitch_max: f32,
-    pub left_hip_yaw_min: f32,
-    pub left_hip_yaw_max: f32,
-    // pub left_hip_roll_min: f32,
-    // pub left_hip_roll_max: f32,
-    pub right_hip_pitch_min: f32,
-    pub right_hip_pitch_max: f32,
-    pub right_hip_yaw_min: f32,
-    pub right_hip_yaw_max: f32,
-    // pub right_hip_roll_min: f32,
-    // pub right_hip_roll_max: f32,
-
-    // knee
-    pub left_knee_pitch_min: f32,
-    pub left_knee_pitch_max: f32,
-    pub right_knee_pitch_min: f32,
-    pub right_knee_pitch_max: f32,
-    pub left_knee_yaw_min: f32,
-    pub left_knee_yaw_max: f32,
-    pub right_knee_yaw_min: f32,
-    pub right_knee_yaw_max: f32,
-
-    // ankle
-    pub left_ankle_pitch_min: f32,
-    pub left_ankle_pitch_max: f32,
-    pub right_ankle_pitch_min: f32,
-    pub right_ankle_pitch_max: f32,
-}
-
 impl MiniRobot {
     pub fn new(client: zeroth::Client) -> Self {
+        let profile = JointProfile::from_toml(ZEROTH_PROFILE).expect("valid built-in profile");
+        Self::with_profile(client, profile)
+    }
+
+    pub fn with_profile(client: zeroth::Client, profile: JointProfile) -> Self {
+        let cutoff_client = client.clone();
         let client = Arc::new(tokio::sync::Mutex::new(client));
 
         MiniRobot {
             client,
-            calibration: Default::default(),
+            profile,
+            calibration: None,
+            balance: None,
+            imu_filter: ComplementaryFilter::default(),
+            cutoff_client,
         }
     }
-}
-
-fn no_such_servo() -> eyre::Report {
-    eyre::eyre!("No such servo")
-}
-
-impl Humanoid for MiniRobot {
-    type JointId = ServoId;
 
-    async fn stabilize(&mut self) -> eyre::Result<()> {
-        println!("Stabilization not implemented");
-        Ok(())
+    /// Keep the torso upright with `config` while standing, see [`Humanoid::stabilize`]. Fails
+    /// if `config` moves joints the profile does not map.
+    pub fn with_balance(mut self, config: BalanceConfig) -> eyre::Result<Self> {
+        config.check_joints(&self.supported_joints())?;
+        self.balance = Some(BalanceController::new(config));
+        Ok(self)
     }
 
-    async fn calibrate(&mut self) -> eyre::Result<()> {
-        // let left_shoulder_yaw_info = self.client.get_servo_info(id: ServoId::LeftShoulderYaw).await.unwrap().unwrap();
-        // let right_shoulder_yaw_info = self.client.get_servo_info(12).await.unwrap().unwrap(); // Assuming ID 12 for right shoulder
-        // shoulder info functions
-        let left_shoulder_yaw_info = self
-            .client
-            .lock()
-            .await
-            .get_servo_info(ServoId::LeftShoulderYaw)
-            .await?
-            .ok_or_else(no_such_servo)?;
-
-        let right_shoulder_yaw_info = self
-            .client
-            .lock()
-            .await
-            .get_servo_info(ServoId::RightShoulderYaw)
-            .await?
-            .ok_or_else(no_such_servo)?;
-        let right_elbow_yaw_info = self
-            .client
-            .lock()
-            .await
-            .get_servo_info(ServoId::RightElbowYaw)
-            .await?
-            .ok_or_else(no_such_servo)?;
-
-        let left_elbow_yaw_info = self
-            .client
-            .lock()
-            .await
-            .get_servo_info(ServoId::LeftElbowYaw)
-            .await?
-            .ok_or_else(no_such_servo)?;
-
-        let left_shoulder_pitch_info = self
-            .client
-            .lock()
-            .await
-            .get_servo_info(ServoId::LeftShoulderPitch)
-            .await?
-            .ok_or_else(no_such_servo)?;
-
-        let right_shoulder_pitch_info = self
-            .client
-            .lock()
-            .await
-            .get_servo_info(ServoId::RightShoulderPitch)
-            .await?
-            .ok_or_else(no_such_servo)?;
-
-        //hip info functions
-        let left_hip_pitch_info = self
-            .client
-            .lock()
-            .await
-            .get_servo_info(ServoId::LeftHipPitch)
-            .await?
-            .ok_or_else(no_such_servo)?;
+    /// Estimate orientation with `filter` in place of a default [`ComplementaryFilter`].
+    pub fn with_imu_filter(mut self, filter: ComplementaryFilter) -> Self {
+        self.imu_filter = filter;
+        self
+    }
 
-        let left_hip_yaw_info = self
-            .client
-            .lock()
-            .await
-            .get_servo_info(ServoId::LeftHipYaw)
-            .await?
-            .ok_or_else(no_such_servo)?;
+    /// The joint mappings in use, including any fitted during calibration.
+    pub fn profile(&self) -> &JointProfile {
+        &self.profile
+    }
+}
 
-        let right_hip_pitch_info = self
-            .client
-            .lock()
-            .await
-            .get_servo_info(ServoId::LeftHipPitch)
-            .await?
-            .ok_or_else(no_such_servo)?;
+/// Disable torque on every servo, and movement along with it.
+async fn go_limp(client: &mut zeroth::Client) -> eyre::Result<()> {
+    client
+        .set_torque_enable(
+            (1..=16)
+                .map(|id| TorqueEnableSetting {
+                    id: ServoId::try_from(id).expect("valid servo id"),
+                    enable: false,
+                })
+                .collect(),
+        )
+        .await?;
+    client.disable_movement().await?;
 
-        let right_hip_yaw_info = self
-            .client
-            .lock()
-            .await
-            .get_servo_info(ServoId::RightHipYaw)
-            .await?
-            .ok_or_else(no_such_servo)?;
+    Ok(())
+}
 
-        // knee info
-        let left_knee_pitch_info = self
-            .client
-            .lock()
-            .await
-            .get_servo_info(ServoId::LeftKneePitch)
-            .await?
-            .ok_or_else(no_such_servo)?;
+fn no_such_servo() -> eyre::Report {
+    eyre::eyre!("No such servo")
+}
 
-        let right_knee_pitch_info = self
-            .client
-            .lock()
-            .await
-            .get_servo_info(ServoId::RightKneePitch)
-            .await?
-            .ok_or_else(no_such_servo)?;
+impl MiniRobot {
+    /// Fit the profile to the servo limits in `calibration`, if the profile asks for it.
+    fn apply_calibration(&mut self, calibration: &Calibration) -> eyre::Result<()> {
+        if !self.profile.fit_to_servo_limits {
+            return Ok(());
+        }
 
-        let right_knee_yaw_info = self
-            .client
-            .lock()
-            .await
-            .get_servo_info(ServoId::RightHipRoll)
-            .await?
-            .ok_or_else(no_such_servo)?;
+        for mapping in self.profile.joints.values_mut() {
+            let servo = calibration
+                .servo(mapping.id)
+                .ok_or_else(|| eyre::eyre!("Calibration has no servo {}", mapping.id))?;
+            let (Some(min), Some(max)) = (servo.min, servo.max) else {
+                eyre::bail!("Calibration has no limits for servo {}", mapping.id);
+            };
 
-        let left_knee_yaw_info = self
-            .client
-            .lock()
-            .await
-            .get_servo_info(ServoId::LeftHipRoll)
-            .await?
-            .ok_or_else(no_such_servo)?;
+            mapping.fit(min, max);
+        }
 
-        // ANKLE INFO
-        let left_ankle_pitch_info = self
-            .client
-            .lock()
-            .await
-            .get_servo_info(ServoId::LeftAnklePitch)
-            .await?
-            .ok_or_else(no_such_servo)?;
+        Ok(())
+    }
 
-        let right_ankle_pitch_info = self
-            .client
+    async fn send(&self, joints: std::collections::BTreeMap<Joint, f32>) -> eyre::Result<()> {
+        self.client
             .lock()
             .await
-            .get_servo_info(ServoId::RightAnklePitch)
-            .await?
-            .ok_or_else(no_such_servo)?;
+            .set_positions(
+                joints
+                    .into_iter()
+                    .map(|(joint, value)| {
+                        let mapping = self.profile.mapping(joint)?;
+                        Ok(zeroth::JointPosition {
+                            id: ServoId::try_from(mapping.id)?,
+                            position: mapping.to_servo(value),
+                            speed: 30.0,
+                        })
+                    })
+                    .collect::<Result<Vec<_>, _>>()?,
+            )
+            .await?;
 
-        let calibration_builder = MiniRobotCalibration::builder()
-            .left_shoulder_yaw_max(left_shoulder_yaw_info.max_position)
-            .left_shoulder_yaw_min(left_shoulder_yaw_info.min_position)
-            .left_elbow_yaw_max(left_elbow_yaw_info.max_position)
-            .left_elbow_yaw_min(left_elbow_yaw_info.min_position)
-            .right_elbow_yaw_max(right_elbow_yaw_info.max_position)
-            .right_elbow_yaw_min(right_elbow_yaw_info.min_position)
-            .left_shoulder_pitch_max(left_shoulder_pitch_info.max_position)
-            .left_shoulder_pitch_min(left_shoulder_pitch_info.min_position)
-            .right_shoulder_pitch_max(right_shoulder_pitch_info.max_position)
-            .right_shoulder_pitch_min(right_shoulder_pitch_info.min_position)
-            .right_shoulder_yaw_max(right_shoulder_yaw_info.max_position)
-            .right_shoulder_yaw_min(right_shoulder_yaw_info.min_position)
-            .left_hip_pitch_min(left_hip_pitch_info.min_position)
-            .left_hip_pitch_max(left_hip_pitch_info.max_position)
-            .left_hip_yaw_min(left_hip_yaw_info.min_position)
-            .left_hip_yaw_max(left_hip_yaw_info.max_position)
-            // .left_hip_roll_min(left_hip_roll_info.min_position) // mapped to knee yaw
-            // .left_hip_roll_max(left_hip_roll_info.max_position) // mapped to knee yaw
-            .right_hip_pitch_min(right_hip_pitch_info.min_position)
-            .right_hip_pitch_max(right_hip_pitch_info.max_position)
-            .right_hip_yaw_min(right_hip_yaw_info.min_position)
-            .right_hip_yaw_max(right_hip_yaw_info.max_position)
-            // .right_hip_roll_min(right_hip_roll_info.min_position) // mapped to knee yaw
-            // .right_hip_roll_max(right_hip_roll_info.max_position) // mapped to knee yaw
-            .left_ankle_pitch_min(left_ankle_pitch_info.min_position)
-            .left_ankle_pitch_max(left_ankle_pitch_info.max_position)
-            .right_ankle_pitch_min(right_ankle_pitch_info.min_position)
-            .right_ankle_pitch_max(right_ankle_pitch_info.max_position)
-            .left_knee_pitch_min(left_knee_pitch_info.min_position)
-            .left_knee_pitch_max(left_knee_pitch_info.max_position)
-            .right_knee_pitch_min(right_knee_pitch_info.min_position)
-            .right_knee_pitch_max(right_knee_pitch_info.max_position)
-            .right_knee_yaw_min(right_knee_yaw_info.min_position)
-            .right_knee_yaw_max(right_knee_yaw_info.max_position)
-            .left_knee_yaw_min(left_knee_yaw_info.min_position)
-            .left_knee_yaw_max(left_knee_yaw_info.max_position);
-
-        self.calibration = calibration_builder.build();
+        Ok(())
+    }
 
+    async fn enable_torque(&mut self) -> eyre::Result<()> {
         // self.client.lock().await.disable_movement().await?;
 
         self.client
@@ -292,7 +187,7 @@ impl Humanoid for MiniRobot {
                     .map(|id| zeroth::TorqueSetting {
                         // 1..16 is the range of servo ids
                         id: ServoId::try_from(id).expect("valid servo id"),
-                        torque: 50.0,
+                        torque: NOMINAL_TORQUE,
                     })
                     .collect(),
             )
@@ -300,411 +195,266 @@ impl Humanoid for MiniRobot {
 
         Ok(())
     }
+}
 
-    fn translate(&self, joint: Joint, value: f32) -> f32 {
-        let value = match joint {
-            humanoid::Joint::LeftKneeYaw => {
-                value * (self.calibration.left_knee_yaw_max - self.calibration.left_knee_yaw_min)
-                    / 90.0
-                    + self.calibration.left_knee_yaw_min
-            }
-            humanoid::Joint::LeftHipPitch => {
-                value * (self.calibration.left_hip_pitch_max - self.calibration.left_hip_pitch_min)
-                    / 90.0
-                    + self.calibration.left_hip_pitch_min
-            }
-            humanoid::Joint::LeftHipYaw => {
-                value * (self.calibration.left_hip_yaw_max - self.calibration.left_hip_yaw_min)
-                    / 90.0
-                    + self.calibration.left_hip_yaw_min
-            }
-            humanoid::Joint::RightKneeYaw => {
-                value * (self.calibration.right_knee_yaw_max - self.calibration.right_knee_yaw_min)
-                    / 90.0
-                    + self.calibration.right_knee_yaw_min
-            }
-            humanoid::Joint::RightHipPitch => {
-                value
-                    * (self.calibration.right_hip_pitch_max - self.calibration.right_hip_pitch_min)
-                    / 90.0
-                    + self.calibration.right_hip_pitch_min
-            }
-            humanoid::Joint::RightHipYaw => {
-                value * (self.calibration.right_hip_yaw_max - self.calibration.right_hip_yaw_min)
-                    / 90.0
-                    + self.calibration.right_hip_yaw_min
-            }
-            humanoid::Joint::LeftKneePitch => {
-                value
-                    * (self.calibration.left_knee_pitch_max - self.calibration.left_knee_pitch_min)
-                    / 90.0
-                    + self.calibration.left_knee_pitch_min
-            }
-            humanoid::Joint::RightKneePitch => {
-                value
-                    * (self.calibration.right_knee_pitch_max
-                        - self.calibration.right_knee_pitch_min)
-                    / 90.0
-                    + self.calibration.right_knee_pitch_min
-            }
-            humanoid::Joint::LeftHipRoll => todo!(),
-            humanoid::Joint::RightHipRoll => todo!(),
-            humanoid::Joint::LeftAnklePitch => {
-                (value + 45.0)
-                    * (self.calibration.left_ankle_pitch_max
-                        - self.calibration.left_ankle_pitch_min)
-                    / 90.0
-                    + self.calibration.left_ankle_pitch_min
-            }
-            humanoid::Joint::LeftAnkleYaw => todo!(),
-            humanoid::Joint::RightAnklePitch => {
-                (value + 45.0)
-                    * (self.calibration.right_ankle_pitch_max
-                        - self.calibration.right_ankle_pitch_min)
-                    / 90.0
-                    + self.calibration.right_ankle_pitch_min
-            }
-            humanoid::Joint::RightAnkleYaw => todo!(),
-            humanoid::Joint::LeftShoulderPitch => {
-                (value + 45.0)
-                    * (self.calibration.left_shoulder_pitch_max
-                        - self.calibration.left_shoulder_pitch_min)
-                    / 90.0
-                    + self.calibration.left_shoulder_pitch_min
-            }
-            humanoid::Joint::LeftShoulderYaw => {
-                value
-                    * (self.calibration.left_shoulder_yaw_max
-                        - self.calibration.left_shoulder_yaw_min)
-                    / 90.0
-                    + self.calibration.left_shoulder_yaw_min
-            }
-            humanoid::Joint::RightShoulderPitch => {
-                (45.0 - value)
-                    * (self.calibration.right_shoulder_pitch_max
-                        - self.calibration.right_shoulder_pitch_min)
-                    / 90.0
-                    + self.calibration.right_shoulder_pitch_min
-            }
-            humanoid::Joint::RightShoulderYaw => {
-                (90.0 - value)
-                    * (self.calibration.right_shoulder_yaw_max
-                        - self.calibration.right_shoulder_yaw_min)
-                    / 90.0
-                    + self.calibration.right_shoulder_yaw_min
-            }
-            humanoid::Joint::LeftElbowPitch => todo!(),
-            humanoid::Joint::LeftElbowYaw => {
-                (value + 90.0)
-                    * (self.calibration.left_elbow_yaw_max - self.calibration.left_elbow_yaw_min)
-                    / 180.0
-                    + self.calibration.left_elbow_yaw_min
-            }
-            humanoid::Joint::RightElbowPitch => todo!(),
-            humanoid::Joint::RightElbowYaw => {
-                (90.0 - value)
-                    * (self.calibration.right_elbow_yaw_max - self.calibration.right_elbow_yaw_min)
-                    / 180.0
-                    + self.calibration.right_elbow_yaw_min
-            }
-            humanoid::Joint::LeftWristPitch => todo!(),
-            humanoid::Joint::LeftWristYaw => todo!(),
-            humanoid::Joint::RightWristPitch => todo!(),
-            humanoid::Joint::RightWristYaw => todo!(),
-            humanoid::Joint::NeckPitch => todo!(),
-            humanoid::Joint::NeckYaw => todo!(),
+impl Humanoid for MiniRobot {
+    type JointId = ServoId;
+
+    fn supported_joints(&self) -> Vec<JointInfo> {
+        self.profile.supported_joints()
+    }
+
+    /// Offset the hips and ankles against the tilt read from the IMU, if balancing.
+    async fn stabilize(&mut self) -> eyre::Result<()> {
+        if self.balance.is_none() {
+            return Ok(());
+        }
+
+        let orientation = self.read_imu().await?.euler();
+        let joints = match self.balance.as_mut() {
+            Some(balance) => balance.update(orientation),
+            None => return Ok(()),
         };
+        if joints.is_empty() {
+            return Ok(());
+        }
 
-        value
+        self.send(joints).await
+    }
 
-        // value.clamp(0.0, 90.0)
+    async fn calibrate(&mut self) -> eyre::Result<()> {
+        let robot = self.identity().await?;
+
+        let mut servos = Vec::new();
+        for mapping in self.profile.joints.values() {
+            let info = self
+                .client
+                .lock()
+                .await
+                .get_servo_info(ServoId::try_from(mapping.id)?)
+                .await?
+                .ok_or_else(no_such_servo)?;
+
+            servos.push(ServoCalibration {
+                id: mapping.id,
+                min: Some(info.min_position),
+                max: Some(info.max_position),
+            });
+        }
+
+        let calibration = Calibration::new(robot, servos);
+        self.apply_calibration(&calibration)?;
+        self.calibration = Some(calibration);
+
+        self.enable_torque().await
     }
 
-    async fn get_joint(&self, joint: humanoid::Joint) -> eyre::Result<humanoid::JointPosition> {
-        match joint {
-            humanoid::Joint::RightHipRoll => Err(zeroth::Error::ServoNotFound.into()),
-            humanoid::Joint::LeftHipRoll => Err(zeroth::Error::ServoNotFound.into()),
-            humanoid::Joint::LeftKneeYaw => {
-                let position = self
-                    .client
-                    .lock()
-                    .await
-                    .get_servo_info(ServoId::LeftHipRoll)
-                    .await?
-                    .unwrap();
-                Ok(humanoid::JointPosition {
-                    joint,
-                    speed: position.speed,
-                    position: position.current_position,
-                })
-            }
-            humanoid::Joint::LeftHipPitch => {
-                let position = self
-                    .client
-                    .lock()
-                    .await
-                    .get_servo_info(ServoId::LeftHipPitch)
-                    .await?
-                    .unwrap();
-                println!("Base position: {:?}", position.current_position);
-                Ok(humanoid::JointPosition {
-                    joint,
-                    speed: position.speed,
-                    position: position.current_position,
-                })
-            }
-            humanoid::Joint::LeftHipYaw => {
-                let position = self
-                    .client
-                    .lock()
-                    .await
-                    .get_servo_info(ServoId::LeftHipYaw)
-                    .await?
-                    .unwrap();
-                Ok(JointPosition {
-                    joint,
-                    speed: position.speed,
-                    position: position.current_position,
-                })
-            }
-            humanoid::Joint::RightKneeYaw => {
-                let position = self
-                    .client
-                    .lock()
-                    .await
-                    .get_servo_info(ServoId::RightHipRoll)
-                    .await?
-                    .unwrap();
-                Ok(JointPosition {
-                    joint,
-                    speed: position.speed,
-                    position: position.current_position,
-                })
-            }
-            humanoid::Joint::RightHipPitch => {
-                let position = self
-                    .client
-                    .lock()
-                    .await
-                    .get_servo_info(ServoId::RightHipPitch)
-                    .await?
-                    .unwrap();
-                Ok(JointPosition {
-                    joint,
-                    speed: position.speed,
-                    position: position.current_position,
-                })
-            }
-            humanoid::Joint::RightHipYaw => {
-                let position = self
-                    .client
-                    .lock()
-                    .await
-                    .get_servo_info(ServoId::RightHipYaw)
-                    .await?
-                    .unwrap();
-                Ok(JointPosition {
-                    joint,
-                    speed: position.speed,
-                    position: position.current_position,
-                })
-            }
-            humanoid::Joint::LeftKneePitch => {
-                let position = self
-                    .client
-                    .lock()
-                    .await
-                    .get_servo_info(ServoId::LeftKneePitch)
-                    .await?
-                    .unwrap();
-                Ok(JointPosition {
-                    joint,
-                    speed: position.speed,
-                    position: position.current_position,
-                })
-            }
-            humanoid::Joint::RightKneePitch => {
-                let position = self
-                    .client
-                    .lock()
-                    .await
-                    .get_servo_info(ServoId::RightKneePitch)
-                    .await?
-                    .unwrap();
-                Ok(JointPosition {
-                    joint,
-                    speed: position.speed,
-                    position: position.current_position,
-                })
-            }
-            humanoid::Joint::LeftAnklePitch => {
-                let position = self
-                    .client
-                    .lock()
-                    .await
-                    .get_servo_info(ServoId::LeftAnklePitch)
-                    .await?
-                    .unwrap();
-                Ok(JointPosition {
-                    joint,
-                    speed: position.speed,
-                    position: position.current_position,
-                })
-            }
-            humanoid::Joint::LeftAnkleYaw => Err(zeroth::Error::ServoNotFound.into()),
-            humanoid::Joint::RightAnklePitch => {
-                let position = self
-                    .client
-                    .lock()
-                    .await
-                    .get_servo_info(ServoId::RightAnklePitch)
-                    .await?
-                    .unwrap();
-                Ok(JointPosition {
-                    joint,
-                    speed: position.speed,
-                    position: position.current_position,
-                })
-            }
-            humanoid::Joint::RightAnkleYaw => Err(zeroth::Error::ServoNotFound.into()),
-            humanoid::Joint::LeftShoulderPitch => {
-                let position = self
-                    .client
-                    .lock()
-                    .await
-                    .get_servo_info(ServoId::LeftShoulderPitch)
-                    .await?
-                    .unwrap();
-                Ok(JointPosition {
-                    joint,
-                    speed: position.speed,
-                    position: position.current_position,
-                })
-            }
-            humanoid::Joint::LeftShoulderYaw => {
-                let position = self
-                    .client
-                    .lock()
-                    .await
-                    .get_servo_info(ServoId::LeftShoulderYaw)
-                    .await?
-                    .unwrap();
-                Ok(JointPosition {
-                    joint,
-                    speed: position.speed,
-                    position: position.current_position,
-                })
-            }
-            humanoid::Joint::RightShoulderPitch => {
-                let position = self
-                    .client
-                    .lock()
-                    .await
-                    .get_servo_info(ServoId::RightShoulderPitch)
-                    .await?
-                    .unwrap();
-                Ok(JointPosition {
-                    joint,
-                    speed: position.speed,
-                    position: position.current_position,
-                })
-            }
-            humanoid::Joint::RightShoulderYaw => {
-                let position = self
-                    .client
-                    .lock()
-                    .await
-                    .get_servo_info(ServoId::RightShoulderYaw)
-                    .await?
-                    .unwrap();
-                Ok(JointPosition {
-                    joint,
-                    speed: position.speed,
-                    position: position.current_position,
-                })
-            }
-            humanoid::Joint::LeftElbowPitch => Err(zeroth::Error::ServoNotFound.into()),
-            humanoid::Joint::LeftElbowYaw => {
-                let position = self
-                    .client
-                    .lock()
-                    .await
-                    .get_servo_info(ServoId::LeftElbowYaw)
-                    .await?
-                    .unwrap();
-                Ok(JointPosition {
-                    joint,
-                    speed: position.speed,
-                    position: position.current_position,
-                })
-            }
-            humanoid::Joint::RightElbowPitch => Err(zeroth::Error::ServoNotFound.into()),
-            humanoid::Joint::RightElbowYaw => {
-                let position = self
-                    .client
-                    .lock()
-                    .await
-                    .get_servo_info(zeroth::ServoId::RightElbowYaw)
-                    .await?
-                    .unwrap();
-                Ok(JointPosition {
-                    joint,
-                    speed: position.speed,
-                    position: position.current_position,
-                })
-            }
-            humanoid::Joint::LeftWristPitch => Err(zeroth::Error::ServoNotFound.into()),
-            humanoid::Joint::LeftWristYaw => Err(zeroth::Error::ServoNotFound.into()),
-            humanoid::Joint::RightWristPitch => Err(zeroth::Error::ServoNotFound.into()),
-            humanoid::Joint::RightWristYaw => Err(zeroth::Error::ServoNotFound.into()),
-            humanoid::Joint::NeckPitch => Err(zeroth::Error::ServoNotFound.into()),
-            humanoid::Joint::NeckYaw => Err(zeroth::Error::ServoNotFound.into()),
+    fn translate(&self, joint: Joint, value: f32) -> f32 {
+        // Unmapped joints are rejected by set_joints before translating
+        self.profile
+            .joints
+            .get(&joint)
+            .map_or(value, |mapping| mapping.to_servo(value))
+    }
+
+    /// Movement is switched along with torque, as the Zeroth only moves while both are on.
+    async fn set_torque_enabled(&mut self, enabled: bool) -> eyre::Result<()> {
+        if enabled {
+            self.client.lock().await.enable_movement().await?;
+            return self.enable_torque().await;
         }
+
+        go_limp(&mut *self.client.lock().await).await
+    }
+
+    fn torque_cutoff(&self) -> Option<TorqueCutoff> {
+        let client = self.cutoff_client.clone();
+        Some(TorqueCutoff::new(move || {
+            let mut client = client.clone();
+            async move { go_limp(&mut client).await }
+        }))
+    }
+
+    async fn get_joint(&self, joint: humanoid::Joint) -> eyre::Result<humanoid::JointPosition> {
+        let mapping = self.profile.mapping(joint)?;
+        let position = self
+            .client
+            .lock()
+            .await
+            .get_servo_info(ServoId::try_from(mapping.id)?)
+            .await?
+            .ok_or_else(no_such_servo)?;
+
+        Ok(JointPosition {
+            joint,
+            speed: position.speed,
+            position: position.current_position,
+        })
     }
 
     async fn set_joints(
         &mut self,
         joints: std::collections::BTreeMap<humanoid::Joint, f32>,
     ) -> eyre::Result<()> {
-        self.client
-            .lock()
-            .await
-            .set_positions(
-                joints
-                    .into_iter()
-                    .map(|(joint, value)| {
-                        let servo_id: i32 = joint.into();
-                        Ok(zeroth::JointPosition {
-                            id: ServoId::try_from(servo_id)?,
-                            position: self.translate(joint, value),
-                            speed: 30.0,
-                        })
-                    })
-                    .collect::<Result<Vec<_>, _>>()?,
-            )
-            .await
-            .unwrap();
+        check_joints(&self.supported_joints(), joints.keys())?;
 
-        Ok(())
+        let joints = match self.balance.as_mut() {
+            Some(balance) => balance.apply(joints),
+            None => joints,
+        };
+        self.send(joints).await
     }
 
     async fn set_joint(&mut self, joint: Joint, position: f32) -> eyre::Result<()> {
-        let servo_id: i32 = joint.into();
+        let mapping = self.profile.mapping(joint)?;
+        let position = match self.balance.as_mut() {
+            Some(balance) => balance.apply([(joint, position)].into())[&joint],
+            None => position,
+        };
         self.client
             .lock()
             .await
             .set_position(zeroth::JointPosition {
-                id: zeroth::ServoId::try_from(servo_id)?,
-                position: self.translate(joint, position),
+                id: zeroth::ServoId::try_from(mapping.id)?,
+                position: mapping.to_servo(position),
                 speed: 100.0,
             })
+            .await?;
+
+        Ok(())
+    }
+}
+
+impl Imu for MiniRobot {
+    /// The Zeroth reports raw readings only, so orientation comes from a
+    /// [`ComplementaryFilter`], assuming x points forward and z up.
+    async fn read_imu(&mut self) -> eyre::Result<ImuReading> {
+        let imu = self.client.lock().await.get_imu_data().await?;
+        let accel = imu
+            .accel
+            .ok_or_else(|| eyre::eyre!("IMU reported no acceleration"))?;
+        let gyro = imu
+            .gyro
+            .ok_or_else(|| eyre::eyre!("IMU reported no angular velocity"))?;
+
+        // Acceleration is in milli-g
+        let accel = [accel.x, accel.y, accel.z].map(|a| a / 1000.0 * STANDARD_GRAVITY);
+        let gyro = [gyro.x, gyro.y, gyro.z];
+        Ok(ImuReading {
+            accel,
+            gyro,
+            mag: None,
+            orientation: self.imu_filter.update(accel, gyro),
+        })
+    }
+}
+
+impl ServoTelemetry for MiniRobot {
+    /// Every mapped servo the board reports.
+    async fn servo_health(&self) -> eyre::Result<Vec<ServoHealth>> {
+        let mut client = self.client.lock().await;
+
+        let mut servos = Vec::new();
+        for (joint, mapping) in &self.profile.joints {
+            let Some(info) = client
+                .get_servo_info(ServoId::try_from(mapping.id)?)
+                .await?
+            else {
+                continue;
+            };
+
+            servos.push(ServoHealth {
+                joint: *joint,
+                id: mapping.id,
+                temperature: Some(info.temperature),
+                current: Some(info.current),
+                voltage: Some(info.voltage),
+            });
+        }
+
+        Ok(servos)
+    }
+}
+
+impl ServoProtection for MiniRobot {
+    fn nominal_torque(&self) -> f32 {
+        NOMINAL_TORQUE
+    }
+
+    async fn set_torque_limit(&mut self, joint: Joint, torque: f32) -> eyre::Result<()> {
+        let id = ServoId::try_from(self.profile.mapping(joint)?.id)?;
+        self.client
+            .lock()
             .await
-            .unwrap();
+            .set_torque(vec![TorqueSetting { id, torque }])
+            .await?;
+        Ok(())
+    }
 
+    async fn set_joint_torque_enabled(&mut self, joint: Joint, enabled: bool) -> eyre::Result<()> {
+        let id = ServoId::try_from(self.profile.mapping(joint)?.id)?;
+        self.client
+            .lock()
+            .await
+            .set_torque_enable_single(id, enabled)
+            .await?;
         Ok(())
     }
 }
 
+impl Calibrate for MiniRobot {
+    async fn identity(&self) -> eyre::Result<RobotIdentity> {
+        let mut servo_ids = self.client.lock().await.scan().await?;
+        servo_ids.sort_unstable();
+
+        Ok(RobotIdentity {
+            model: self.profile.name.clone(),
+            servo_ids,
+        })
+    }
+
+    fn calibration(&self) -> Option<Calibration> {
+        self.calibration.clone()
+    }
+
+    async fn restore_calibration(&mut self, calibration: Calibration) -> eyre::Result<()> {
+        self.apply_calibration(&calibration)?;
+        self.calibration = Some(calibration);
+
+        self.enable_torque().await
+    }
+
+    /// Checks every servo in a single `get_positions` call, rather than asking each for its
+    /// limits again.
+    async fn verify_calibration(&self, calibration: &Calibration) -> eyre::Result<bool> {
+        let positions = self.client.lock().await.get_positions().await?;
+
+        for mapping in self.profile.joints.values() {
+            let Some(servo) = calibration.servo(mapping.id) else {
+                return Ok(false);
+            };
+            let Some(position) = positions
+                .iter()
+                .find(|position| i32::from(position.id) == mapping.id)
+            else {
+                return Ok(false);
+            };
+
+            let (min, max) = (
+                servo.min.unwrap_or(f32::NEG_INFINITY),
+                servo.max.unwrap_or(f32::INFINITY),
+            );
+            if position.position < min - CALIBRATION_TOLERANCE
+                || position.position > max + CALIBRATION_TOLERANCE
+            {
+                return Ok(false);
+            }
+        }
+
+        Ok(true)
+    }
+}
+
 pub struct ServoIdConversion(pub zeroth::ServoId);
 
 impl std::fmt::Display for ServoIdConversion {
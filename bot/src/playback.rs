@@ -0,0 +1,283 @@
+use std::path::{Path, PathBuf};
+
+use axum::{
+    extract::{rejection::JsonRejection, State},
+    http::StatusCode,
+    Json,
+};
+use humanoid::{
+    Halt, Interpolation, JointProfile, MotionClip, PlaybackOptions, QueueError, Trajectory,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    errors_response,
+    frame::{check_frame, FrameError},
+    json_rejection_response, krec, AppState,
+};
+
+/// Where `POST /playback` looks for motion files by default.
+pub const DEFAULT_MOTIONS_DIR: &str = "pose_mappings";
+
+/// A motion to play, as sent to `POST /playback`. Exactly one of `file`, `frames` or `clip`
+/// must be given.
+///
+/// ```json
+/// { "file": "flapping_motion.json", "loops": 2, "speed": 1.5 }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PlaybackRequest {
+    /// Name of a [`MotionClip`], `.krec` episode or legacy motion file in the motions directory.
+    #[serde(default)]
+    pub file: Option<String>,
+    /// Legacy frames to play, each the `joints` object of a `POST /frame`.
+    #[serde(default)]
+    pub frames: Option<Vec<serde_json::Value>>,
+    #[serde(default)]
+    pub clip: Option<MotionClip>,
+    /// Rate legacy frames were recorded at, defaults to the control rate. Clips carry their own
+    /// timing.
+    #[serde(default)]
+    pub fps: Option<f32>,
+    #[serde(flatten)]
+    pub options: PlaybackOptions,
+}
+
+#[derive(Debug)]
+pub enum PlaybackError {
+    /// Not exactly one of `file`, `frames` or `clip` was given.
+    NoMotion,
+    /// `file` is not a plain file name inside the motions directory.
+    InvalidFileName(String),
+    NotFound(String),
+    InvalidMotion(eyre::Report),
+    InvalidOptions(String),
+    InvalidFrames(Vec<(Option<usize>, FrameError)>),
+    /// Nothing plays until the halt is reset.
+    Halted(Halt),
+}
+
+impl std::fmt::Display for PlaybackError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PlaybackError::NoMotion => {
+                write!(f, "Expected exactly one of `file`, `frames` or `clip`")
+            }
+            PlaybackError::InvalidFileName(name) => {
+                write!(f, "{:?} is not a file name in the motions directory", name)
+            }
+            PlaybackError::NotFound(name) => write!(f, "Motion {:?} not found", name),
+            PlaybackError::InvalidMotion(e) => write!(f, "Invalid motion: {}", e),
+            PlaybackError::InvalidOptions(e) => write!(f, "{}", e),
+            PlaybackError::InvalidFrames(errors) => {
+                write!(f, "{} invalid frame entries", errors.len())
+            }
+            PlaybackError::Halted(halt) => write!(f, "Robot is halted: {:?}", halt),
+        }
+    }
+}
+
+impl std::error::Error for PlaybackError {}
+
+/// Read a legacy motion file: a JSON array of frames, each an object of joint ids or names to
+/// values.
+pub fn read_motion(path: impl AsRef<Path>) -> eyre::Result<Vec<serde_json::Value>> {
+    let contents = std::fs::read_to_string(path)?;
+    Ok(serde_json::from_str(&contents)?)
+}
+
+/// Load a motion clip, a `.krec` episode mapped onto joints with `profile`, or a legacy motion
+/// recorded at `legacy_fps`.
+pub fn load_motion(
+    path: impl AsRef<Path>,
+    legacy_fps: f32,
+    profile: &JointProfile,
+) -> eyre::Result<MotionClip> {
+    if krec::is_krec(&path) {
+        krec::load(path, profile)
+    } else {
+        MotionClip::load_any(path, legacy_fps)
+    }
+}
+
+/// Save `clip` as a `.krec` episode for a `.krec` path, otherwise see [`MotionClip::save`].
+pub fn save_motion(
+    clip: &MotionClip,
+    profile: &JointProfile,
+    path: impl AsRef<Path>,
+) -> eyre::Result<()> {
+    if krec::is_krec(&path) {
+        krec::save(clip, profile, path)
+    } else {
+        clip.save(path)
+    }
+}
+
+/// Resolve `name` inside `dir`, refusing anything that could escape it.
+pub(crate) fn motion_path(dir: &Path, name: &str) -> Result<PathBuf, PlaybackError> {
+    let mut components = Path::new(name).components();
+    match (components.next(), components.next()) {
+        (Some(std::path::Component::Normal(file)), None) => Ok(dir.join(file)),
+        _ => Err(PlaybackError::InvalidFileName(name.to_string())),
+    }
+}
+
+impl PlaybackRequest {
+    /// Load and validate the motion, interpolating linearly between its frames.
+    pub fn trajectory(&self, state: &AppState) -> Result<Trajectory, PlaybackError> {
+        let speed = self.options.speed;
+        if !(speed.is_finite() && speed > 0.0) {
+            return Err(PlaybackError::InvalidOptions(format!(
+                "speed must be positive, got {}",
+                speed
+            )));
+        }
+        let fps = self.fps.unwrap_or(state.motion_fps);
+        if !(fps.is_finite() && fps > 0.0) {
+            return Err(PlaybackError::InvalidOptions(format!(
+                "fps must be positive, got {}",
+                fps
+            )));
+        }
+
+        let clip = match (&self.file, &self.frames, &self.clip) {
+            (Some(name), None, None) => {
+                let path = motion_path(&state.motions_dir, name)?;
+                if !path.is_file() {
+                    return Err(PlaybackError::NotFound(name.clone()));
+                }
+                load_motion(path, fps, &state.profile).map_err(PlaybackError::InvalidMotion)?
+            }
+            (None, Some(json), None) => {
+                let mut frames = Vec::with_capacity(json.len());
+                let mut errors = Vec::new();
+                for (index, json) in json.iter().enumerate() {
+                    match crate::frame::parse_frame(json) {
+                        Ok(frame) => frames.push(frame),
+                        Err(e) => errors.extend(e.into_iter().map(|e| (Some(index), e))),
+                    }
+                }
+                if !errors.is_empty() {
+                    return Err(PlaybackError::InvalidFrames(errors));
+                }
+                MotionClip::from_frames("uploaded", frames, fps)
+                    .map_err(PlaybackError::InvalidMotion)?
+            }
+            (None, None, Some(clip)) => {
+                clip.validate().map_err(PlaybackError::InvalidMotion)?;
+                clip.clone()
+            }
+            _ => return Err(PlaybackError::NoMotion),
+        };
+        if clip.frames.is_empty() {
+            return Err(PlaybackError::InvalidMotion(eyre::eyre!(
+                "Motion has no frames"
+            )));
+        }
+
+        let supported = state.frame_queue.supported_joints();
+        let errors: Vec<_> = clip
+            .frames()
+            .iter()
+            .enumerate()
+            .flat_map(|(index, frame)| {
+                check_frame(frame, supported, state.strict)
+                    .err()
+                    .into_iter()
+                    .flatten()
+                    .map(move |e| (Some(index), e))
+            })
+            .collect();
+        if !errors.is_empty() {
+            return Err(PlaybackError::InvalidFrames(errors));
+        }
+
+        clip.trajectory(Interpolation::Linear)
+            .map_err(PlaybackError::InvalidMotion)
+    }
+}
+
+impl AppState {
+    /// Play `request` in place of anything queued.
+    pub fn play(&self, request: &PlaybackRequest) -> Result<(), PlaybackError> {
+        let trajectory = request.trajectory(self)?;
+        println!(
+            "Playing {} for {:?} per loop, {:?}",
+            request.file.as_deref().unwrap_or("uploaded motion"),
+            trajectory.duration(),
+            request.options
+        );
+
+        self.frame_queue
+            .play_with(trajectory, request.options)
+            .map_err(|e| match e {
+                QueueError::Unsupported(e) => PlaybackError::InvalidFrames(
+                    e.joints
+                        .into_iter()
+                        .map(|joint| (None, FrameError::UnsupportedJoint { joint }))
+                        .collect(),
+                ),
+                QueueError::Halted(halt) => PlaybackError::Halted(halt),
+            })
+    }
+}
+
+type JsonResponse = (StatusCode, Json<serde_json::Value>);
+
+fn status_response(state: &AppState) -> Json<serde_json::Value> {
+    Json(serde_json::json!({ "playback": state.frame_queue.playback() }))
+}
+
+pub(crate) async fn play_handler(
+    State(state): State<AppState>,
+    request: Result<Json<PlaybackRequest>, JsonRejection>,
+) -> JsonResponse {
+    let request = match request {
+        Ok(Json(request)) => request,
+        Err(e) => return json_rejection_response(e),
+    };
+
+    let e = match state.play(&request) {
+        Ok(()) => return (StatusCode::CREATED, status_response(&state)),
+        Err(PlaybackError::InvalidFrames(errors)) => return errors_response(&errors),
+        Err(e) => e,
+    };
+
+    println!("Rejected playback: {}", e);
+    let status = match e {
+        PlaybackError::NotFound(_) => StatusCode::NOT_FOUND,
+        PlaybackError::Halted(_) => StatusCode::CONFLICT,
+        PlaybackError::InvalidMotion(_) => StatusCode::UNPROCESSABLE_ENTITY,
+        _ => StatusCode::BAD_REQUEST,
+    };
+    (status, Json(serde_json::json!({ "error": e.to_string() })))
+}
+
+pub(crate) async fn status_handler(State(state): State<AppState>) -> Json<serde_json::Value> {
+    status_response(&state)
+}
+
+pub(crate) async fn pause_handler(State(state): State<AppState>) -> JsonResponse {
+    control_response(&state, state.frame_queue.pause())
+}
+
+pub(crate) async fn resume_handler(State(state): State<AppState>) -> JsonResponse {
+    control_response(&state, state.frame_queue.resume())
+}
+
+pub(crate) async fn stop_handler(State(state): State<AppState>) -> JsonResponse {
+    let playing = state.frame_queue.is_playing();
+    state.frame_queue.stop_trajectory();
+    control_response(&state, playing)
+}
+
+fn control_response(state: &AppState, playing: bool) -> JsonResponse {
+    if playing {
+        (StatusCode::OK, status_response(state))
+    } else {
+        (
+            StatusCode::CONFLICT,
+            Json(serde_json::json!({ "error": "Nothing is playing" })),
+        )
+    }
+}
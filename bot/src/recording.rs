@@ -0,0 +1,164 @@
+use std::{
+    path::PathBuf,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use axum::{body::Bytes, extract::State, http::StatusCode, Json};
+use humanoid::{RecordingError, RecordingOptions, DEFAULT_FEEDBACK_INTERVAL};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    playback::{motion_path, save_motion},
+    AppState,
+};
+
+/// Slowest readback `POST /recording/start` accepts, once every 100 seconds.
+pub const MIN_FEEDBACK_HZ: f32 = 0.01;
+
+/// Body of `POST /recording/start`. Every field is optional.
+///
+/// ```json
+/// { "name": "wave", "feedback_hz": 10.0 }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RecordRequest {
+    /// Saved as `<name>.json` in the motions directory, defaults to `recording-<unix time>`. A
+    /// name ending in `.toml` or `.krec` is saved in that format instead.
+    #[serde(default)]
+    pub name: Option<String>,
+    /// How often joint positions are read back from the robot, at least [`MIN_FEEDBACK_HZ`], or
+    /// `0` to record commanded frames only.
+    #[serde(default)]
+    pub feedback_hz: Option<f32>,
+}
+
+/// File a recording called `name` is saved as.
+fn file_name(name: &str) -> String {
+    if name.ends_with(".json") || name.ends_with(".toml") || name.ends_with(".krec") {
+        name.to_string()
+    } else {
+        format!("{}.json", name)
+    }
+}
+
+impl RecordRequest {
+    /// Check the request, returning the recording options and where it will be saved. The clip
+    /// is tagged with the name of the active joint profile.
+    pub fn options(&self, state: &AppState) -> Result<(RecordingOptions, PathBuf), String> {
+        let name = match &self.name {
+            Some(name) => name.clone(),
+            None => format!(
+                "recording-{}",
+                SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map_or(0, |d| d.as_secs())
+            ),
+        };
+        let path = motion_path(&state.motions_dir, &file_name(&name)).map_err(|e| e.to_string())?;
+
+        let feedback_interval = match self.feedback_hz {
+            None => Some(DEFAULT_FEEDBACK_INTERVAL),
+            Some(0.0) => None,
+            Some(hz) if hz.is_finite() && hz >= MIN_FEEDBACK_HZ => {
+                Some(Duration::from_secs_f64(1.0 / hz as f64))
+            }
+            Some(hz) => {
+                return Err(format!(
+                    "feedback_hz must be 0 or at least {}, got {}",
+                    MIN_FEEDBACK_HZ, hz
+                ))
+            }
+        };
+
+        Ok((
+            RecordingOptions {
+                name,
+                robot: Some(state.profile.name.clone()).filter(|name| !name.is_empty()),
+                feedback_interval,
+            },
+            path,
+        ))
+    }
+}
+
+type JsonResponse = (StatusCode, Json<serde_json::Value>);
+
+fn error_response(status: StatusCode, error: impl ToString) -> JsonResponse {
+    (
+        status,
+        Json(serde_json::json!({ "error": error.to_string() })),
+    )
+}
+
+/// Start recording. An empty body records with the defaults of [`RecordRequest`].
+pub(crate) async fn start_handler(State(state): State<AppState>, body: Bytes) -> JsonResponse {
+    let request = if body.is_empty() {
+        RecordRequest::default()
+    } else {
+        match serde_json::from_slice::<RecordRequest>(&body) {
+            Ok(request) => request,
+            Err(e) => return error_response(StatusCode::BAD_REQUEST, e),
+        }
+    };
+
+    let (options, path) = match request.options(&state) {
+        Ok(options) => options,
+        Err(e) => return error_response(StatusCode::BAD_REQUEST, e),
+    };
+    // Never overwrite an earlier session
+    if path.exists() {
+        return error_response(
+            StatusCode::CONFLICT,
+            format!("{} already exists", path.display()),
+        );
+    }
+
+    match state.frame_queue.recorder().start(options) {
+        Ok(()) => {
+            println!("Recording to {}", path.display());
+            (StatusCode::CREATED, status_response(&state))
+        }
+        Err(e @ RecordingError::AlreadyRecording) => error_response(StatusCode::CONFLICT, e),
+    }
+}
+
+pub(crate) async fn status_handler(State(state): State<AppState>) -> Json<serde_json::Value> {
+    status_response(&state)
+}
+
+/// Stop recording and save the clip to the motions directory.
+pub(crate) async fn stop_handler(State(state): State<AppState>) -> JsonResponse {
+    let Some(clip) = state.frame_queue.recorder().stop() else {
+        return error_response(StatusCode::CONFLICT, "Nothing is being recorded");
+    };
+
+    let file = file_name(&clip.name);
+    let path = state.motions_dir.join(&file);
+    if let Err(e) = save_motion(&clip, &state.profile, &path) {
+        println!("Failed to save recording to {}: {:?}", path.display(), e);
+        return error_response(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to save recording: {}", e),
+        );
+    }
+
+    println!(
+        "Saved {} frames over {:?} to {}",
+        clip.frames.len(),
+        clip.duration(),
+        path.display()
+    );
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "file": file,
+            "frames": clip.frames.len(),
+            "feedback": clip.feedback.len(),
+            "duration": clip.duration().as_secs_f32(),
+        })),
+    )
+}
+
+fn status_response(state: &AppState) -> Json<serde_json::Value> {
+    Json(serde_json::json!({ "recording": state.frame_queue.recorder().status() }))
+}
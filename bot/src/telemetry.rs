@@ -0,0 +1,86 @@
+use std::convert::Infallible;
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::sse::{Event, KeepAlive, Sse},
+    Json,
+};
+use futures_util::{stream, Stream, StreamExt};
+use humanoid::TelemetrySnapshot;
+use serde::Deserialize;
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::AppState;
+
+/// How often the bot reads servo health by default.
+pub const DEFAULT_TELEMETRY_INTERVAL_MS: u64 = 1000;
+
+/// How much servo health history the bot keeps by default.
+pub const DEFAULT_TELEMETRY_HISTORY_SECS: u64 = 600;
+
+/// The latest servo health, or 404 before the first reading.
+pub(crate) async fn latest_handler(
+    State(state): State<AppState>,
+) -> (StatusCode, Json<serde_json::Value>) {
+    match state.telemetry.latest() {
+        Some(snapshot) => (StatusCode::OK, Json(serde_json::json!(snapshot))),
+        None => (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({ "error": "No telemetry yet" })),
+        ),
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub(crate) struct HistoryParams {
+    /// Only snapshots taken after this time, in milliseconds since the Unix epoch.
+    #[serde(default)]
+    since: Option<u64>,
+}
+
+/// Every snapshot kept, oldest first.
+pub(crate) async fn history_handler(
+    State(state): State<AppState>,
+    Query(params): Query<HistoryParams>,
+) -> Json<Vec<TelemetrySnapshot>> {
+    Json(match params.since {
+        Some(since) => state.telemetry.since(since),
+        None => state.telemetry.history(),
+    })
+}
+
+/// A `telemetry` event with each snapshot as JSON, starting with the latest one. Snapshots a
+/// slow client misses are skipped.
+pub(crate) async fn stream_handler(
+    State(state): State<AppState>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let updates = state.telemetry.subscribe();
+    let latest = stream::iter(state.telemetry.latest());
+    let updates = stream::unfold(updates, |mut updates| async move {
+        loop {
+            match updates.recv().await {
+                Ok(snapshot) => return Some((snapshot, updates)),
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    });
+
+    Sse::new(latest.chain(updates).map(|snapshot| Ok(event(&snapshot))))
+        .keep_alive(KeepAlive::default())
+}
+
+fn event(snapshot: &TelemetrySnapshot) -> Event {
+    Event::default()
+        .event("telemetry")
+        .data(serde_json::to_string(snapshot).expect("snapshots serialize"))
+}
+
+/// The latest thermal and overcurrent rule hits, oldest first.
+pub(crate) async fn protection_handler(State(state): State<AppState>) -> Json<serde_json::Value> {
+    Json(serde_json::json!({
+        "count": state.protection.count(),
+        "events": state.protection.events(),
+    }))
+}
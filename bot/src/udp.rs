@@ -0,0 +1,310 @@
+//! Lossy, low-latency frame ingest over UDP.
+//!
+//! Each datagram holds one frame, little-endian:
+//!
+//! | bytes | field                                                  |
+//! |-------|--------------------------------------------------------|
+//! | 1     | format version, [`UDP_FRAME_VERSION`]                  |
+//! | 4     | sequence number, `u32`, wrapping                       |
+//! | 8     | send time in microseconds since the Unix epoch, `u64`  |
+//! | 1     | joint count `n`                                        |
+//! | 5 × n | [`Joint`] id as `u8`, then its value as `f32`          |
+
+use std::{
+    collections::BTreeMap,
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use humanoid::{Frame, FrameQueue, Joint};
+use serde::{Deserialize, Serialize};
+use tokio::{
+    net::{ToSocketAddrs, UdpSocket},
+    time::Instant,
+};
+
+use crate::frame::check_frame;
+
+pub const UDP_FRAME_VERSION: u8 = 1;
+
+const HEADER_LEN: usize = 1 + 4 + 8 + 1;
+const JOINT_LEN: usize = 1 + 4;
+
+/// Largest datagram [`UdpFrame::encode`] can produce.
+pub const MAX_UDP_FRAME_LEN: usize = HEADER_LEN + u8::MAX as usize * JOINT_LEN;
+
+/// How far behind the last sequence number a frame may be and still count as reordered. A
+/// frame further back is taken to come from a restarted sender and starts a new sequence.
+pub const REORDER_WINDOW: u32 = 1024;
+
+/// A sender silent for this long starts a new sequence with its next frame.
+pub const SEQUENCE_TIMEOUT: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct UdpFrame {
+    pub seq: u32,
+    /// Microseconds since the Unix epoch when the frame was sent.
+    pub timestamp_us: u64,
+    pub joints: BTreeMap<Joint, f32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UdpFrameError {
+    UnsupportedVersion(u8),
+    /// The datagram is shorter than its header says.
+    Truncated {
+        expected: usize,
+        found: usize,
+    },
+    UnknownJoint(u8),
+    /// The value is NaN or infinite.
+    NotFinite(Joint),
+    TooManyJoints(usize),
+}
+
+impl std::fmt::Display for UdpFrameError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UdpFrameError::UnsupportedVersion(version) => write!(
+                f,
+                "UDP frame version {} is not supported, expected {}",
+                version, UDP_FRAME_VERSION
+            ),
+            UdpFrameError::Truncated { expected, found } => {
+                write!(f, "UDP frame is {} bytes, expected {}", found, expected)
+            }
+            UdpFrameError::UnknownJoint(id) => write!(f, "Unknown joint id {}", id),
+            UdpFrameError::NotFinite(joint) => write!(f, "{:?}: value is not finite", joint),
+            UdpFrameError::TooManyJoints(count) => write!(
+                f,
+                "{} joints do not fit in one UDP frame, at most {} do",
+                count,
+                u8::MAX
+            ),
+        }
+    }
+}
+
+impl std::error::Error for UdpFrameError {}
+
+impl UdpFrame {
+    pub fn encode(&self) -> Result<Vec<u8>, UdpFrameError> {
+        let count = u8::try_from(self.joints.len())
+            .map_err(|_| UdpFrameError::TooManyJoints(self.joints.len()))?;
+
+        let mut bytes = Vec::with_capacity(HEADER_LEN + self.joints.len() * JOINT_LEN);
+        bytes.push(UDP_FRAME_VERSION);
+        bytes.extend_from_slice(&self.seq.to_le_bytes());
+        bytes.extend_from_slice(&self.timestamp_us.to_le_bytes());
+        bytes.push(count);
+        for (joint, value) in &self.joints {
+            // Joint ids are 1..=28
+            bytes.push(i32::from(*joint) as u8);
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+
+        Ok(bytes)
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Self, UdpFrameError> {
+        let truncated = |expected| UdpFrameError::Truncated {
+            expected,
+            found: bytes.len(),
+        };
+
+        let version = *bytes.first().ok_or_else(|| truncated(HEADER_LEN))?;
+        if version != UDP_FRAME_VERSION {
+            return Err(UdpFrameError::UnsupportedVersion(version));
+        }
+        if bytes.len() < HEADER_LEN {
+            return Err(truncated(HEADER_LEN));
+        }
+
+        let seq = u32::from_le_bytes(bytes[1..5].try_into().expect("4 bytes"));
+        let timestamp_us = u64::from_le_bytes(bytes[5..13].try_into().expect("8 bytes"));
+        let count = bytes[13] as usize;
+
+        let expected = HEADER_LEN + count * JOINT_LEN;
+        if bytes.len() < expected {
+            return Err(truncated(expected));
+        }
+
+        let mut joints = BTreeMap::new();
+        for entry in bytes[HEADER_LEN..expected].chunks_exact(JOINT_LEN) {
+            let joint = Joint::try_from(entry[0] as i32)
+                .map_err(|_| UdpFrameError::UnknownJoint(entry[0]))?;
+            let value = f32::from_le_bytes(entry[1..].try_into().expect("4 bytes"));
+            if !value.is_finite() {
+                return Err(UdpFrameError::NotFinite(joint));
+            }
+            joints.insert(joint, value);
+        }
+
+        Ok(Self {
+            seq,
+            timestamp_us,
+            joints,
+        })
+    }
+}
+
+/// Counters kept by a [`UdpListener`].
+#[derive(Debug, Default)]
+pub struct UdpStats {
+    received: AtomicU64,
+    applied: AtomicU64,
+    lost: AtomicU64,
+    reordered: AtomicU64,
+    duplicates: AtomicU64,
+    malformed: AtomicU64,
+    rejected: AtomicU64,
+}
+
+/// A point-in-time copy of [`UdpStats`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UdpStatsSnapshot {
+    /// Datagrams received, including ones that were dropped.
+    pub received: u64,
+    /// Frames written to the queue.
+    pub applied: u64,
+    /// Sequence numbers skipped over that have not arrived late since.
+    pub lost: u64,
+    /// Frames dropped because a newer one had already been received.
+    pub reordered: u64,
+    /// Frames dropped because their sequence number was just received.
+    pub duplicates: u64,
+    /// Datagrams that could not be decoded.
+    pub malformed: u64,
+    /// Frames refused by the queue, e.g. for unsupported joints.
+    pub rejected: u64,
+}
+
+impl UdpStats {
+    pub fn snapshot(&self) -> UdpStatsSnapshot {
+        UdpStatsSnapshot {
+            received: self.received.load(Ordering::Relaxed),
+            applied: self.applied.load(Ordering::Relaxed),
+            lost: self.lost.load(Ordering::Relaxed),
+            reordered: self.reordered.load(Ordering::Relaxed),
+            duplicates: self.duplicates.load(Ordering::Relaxed),
+            malformed: self.malformed.load(Ordering::Relaxed),
+            rejected: self.rejected.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Decodes [`UdpFrame`]s and overwrites the [`FrameQueue`] with any newer than the last one
+/// received. Frames from a different sender, after [`SEQUENCE_TIMEOUT`] of silence or more
+/// than [`REORDER_WINDOW`] behind start a new sequence. Frames are checked like HTTP and
+/// WebSocket ones, see [`check_frame`].
+pub struct UdpListener {
+    socket: UdpSocket,
+    frame_queue: Arc<FrameQueue>,
+    strict: bool,
+    stats: Arc<UdpStats>,
+    // Sender, sequence number and arrival of the last frame received
+    last: Option<(SocketAddr, u32, Instant)>,
+}
+
+impl UdpListener {
+    pub async fn bind(
+        addr: impl ToSocketAddrs,
+        frame_queue: Arc<FrameQueue>,
+        strict: bool,
+    ) -> eyre::Result<Self> {
+        Ok(Self {
+            socket: UdpSocket::bind(addr).await?,
+            frame_queue,
+            strict,
+            stats: Arc::default(),
+            last: None,
+        })
+    }
+
+    pub fn local_addr(&self) -> eyre::Result<SocketAddr> {
+        Ok(self.socket.local_addr()?)
+    }
+
+    pub fn stats(&self) -> Arc<UdpStats> {
+        self.stats.clone()
+    }
+
+    /// Receive frames until the socket fails.
+    pub async fn run(mut self) -> eyre::Result<()> {
+        println!("Listening on udp://{}", self.socket.local_addr()?);
+
+        let mut buf = vec![0; MAX_UDP_FRAME_LEN];
+        loop {
+            let (len, from) = self.socket.recv_from(&mut buf).await?;
+            self.handle(&buf[..len], from);
+        }
+    }
+
+    fn handle(&mut self, bytes: &[u8], from: SocketAddr) {
+        self.stats.received.fetch_add(1, Ordering::Relaxed);
+
+        let frame = match UdpFrame::decode(bytes) {
+            Ok(frame) => frame,
+            Err(e) => {
+                println!("Dropping UDP frame from {}: {}", from, e);
+                self.stats.malformed.fetch_add(1, Ordering::Relaxed);
+                return;
+            }
+        };
+
+        // A new, restarted or long silent sender starts a new sequence
+        let now = Instant::now();
+        if let Some((addr, last_seq, at)) = self.last {
+            // Compare with wraparound, so that 0 follows u32::MAX
+            let ahead = frame.seq.wrapping_sub(last_seq) as i32;
+            let restarted = ahead < -(REORDER_WINDOW as i32) || now - at > SEQUENCE_TIMEOUT;
+            if addr == from && !restarted {
+                if ahead == 0 {
+                    self.stats.duplicates.fetch_add(1, Ordering::Relaxed);
+                    return;
+                }
+                if ahead < 0 {
+                    // This frame was counted as lost when a later one arrived
+                    self.stats.reordered.fetch_add(1, Ordering::Relaxed);
+                    let _ = self.stats.lost.fetch_update(
+                        Ordering::Relaxed,
+                        Ordering::Relaxed,
+                        |lost| lost.checked_sub(1),
+                    );
+                    return;
+                }
+                self.stats
+                    .lost
+                    .fetch_add(ahead as u64 - 1, Ordering::Relaxed);
+            }
+        }
+
+        self.last = Some((from, frame.seq, now));
+
+        let seq = frame.seq;
+        let frame = Frame {
+            joints: frame.joints,
+        };
+        if let Err(errors) = check_frame(&frame, self.frame_queue.supported_joints(), self.strict) {
+            for e in errors {
+                println!("Rejected UDP frame {}: {}", seq, e);
+            }
+            self.stats.rejected.fetch_add(1, Ordering::Relaxed);
+            return;
+        }
+
+        match self.frame_queue.overwrite(frame) {
+            Ok(()) => {
+                self.stats.applied.fetch_add(1, Ordering::Relaxed);
+            }
+            Err(e) => {
+                println!("Rejected UDP frame {}: {}", seq, e);
+                self.stats.rejected.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+}
@@ -0,0 +1,130 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use axum::{
+    extract::{
+        ws::{Message, WebSocket},
+        State, WebSocketUpgrade,
+    },
+    response::Response,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{frame::QueueMode, AppState};
+
+/// A frame sent by the client over `/ws`.
+///
+/// ```json
+/// { "seq": 12, "sent_at": 1734567890123.0, "joints": { "15": 0.0 } }
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FrameMessage {
+    /// Client-chosen sequence number, echoed back in the ack.
+    pub seq: u64,
+    /// Milliseconds since the Unix epoch when the client sent the frame.
+    #[serde(default)]
+    pub sent_at: Option<f64>,
+    #[serde(default)]
+    pub mode: QueueMode,
+    /// Joint ids or names to values, the same as the body of `POST /frame`.
+    pub joints: serde_json::Value,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AckStatus {
+    Queued,
+    Rejected,
+}
+
+/// Sent back for every message received on `/ws`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FrameAck {
+    /// `seq` of the acknowledged frame, or `None` if the message could not be parsed at all.
+    pub seq: Option<u64>,
+    pub status: AckStatus,
+    /// Milliseconds between `sent_at` and the frame being queued. Only meaningful if the clocks
+    /// of the client and the robot are in sync.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latency_ms: Option<f64>,
+    /// Frames waiting in the queue after this one was added.
+    pub queue_len: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+fn now_ms() -> f64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0.0, |d| d.as_secs_f64() * 1000.0)
+}
+
+pub(crate) async fn ws_handler(State(state): State<AppState>, ws: WebSocketUpgrade) -> Response {
+    ws.on_upgrade(move |socket| stream_frames(socket, state))
+}
+
+async fn stream_frames(mut socket: WebSocket, state: AppState) {
+    println!("WebSocket client connected");
+
+    while let Some(message) = socket.recv().await {
+        let text = match message {
+            Ok(Message::Text(text)) => text,
+            Ok(Message::Binary(bytes)) => match String::from_utf8(bytes) {
+                Ok(text) => text,
+                Err(e) => {
+                    println!("Ignoring binary WebSocket message: {}", e);
+                    continue;
+                }
+            },
+            Ok(Message::Close(_)) => break,
+            // Pings are answered by axum
+            Ok(_) => continue,
+            Err(e) => {
+                println!("WebSocket error: {}", e);
+                break;
+            }
+        };
+
+        let ack = handle_message(&state, &text);
+        let ack = serde_json::to_string(&ack).expect("ack serializes");
+        if socket.send(Message::Text(ack)).await.is_err() {
+            break;
+        }
+    }
+
+    println!("WebSocket client disconnected");
+}
+
+fn handle_message(state: &AppState, text: &str) -> FrameAck {
+    let frame_queue = &state.frame_queue;
+    let rejected = |seq, error: String| FrameAck {
+        seq,
+        status: AckStatus::Rejected,
+        latency_ms: None,
+        queue_len: frame_queue.len(),
+        error: Some(error),
+    };
+
+    let message: FrameMessage = match serde_json::from_str(text) {
+        Ok(message) => message,
+        Err(e) => return rejected(None, e.to_string()),
+    };
+    let frame = match state.parse_frame(&message.joints) {
+        Ok(frame) => frame,
+        Err(errors) => {
+            let errors: Vec<_> = errors.iter().map(ToString::to_string).collect();
+            return rejected(Some(message.seq), errors.join(", "));
+        }
+    };
+
+    if let Err(e) = state.enqueue(frame, message.mode) {
+        return rejected(Some(message.seq), e.to_string());
+    }
+
+    FrameAck {
+        seq: Some(message.seq),
+        status: AckStatus::Queued,
+        latency_ms: message.sent_at.map(|sent_at| now_ms() - sent_at),
+        queue_len: frame_queue.len(),
+        error: None,
+    }
+}
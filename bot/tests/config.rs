@@ -0,0 +1,92 @@
+use bot::config::{Backend, BotConfig, Cli};
+use clap::Parser;
+use humanoid::Joint;
+
+#[test]
+fn example_config_loads() {
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/bot.toml");
+    let config = BotConfig::load(path).unwrap();
+    config.validate().unwrap();
+
+    assert_eq!(config.backend, Backend::Zeroth);
+    assert_eq!(config.robot_address(), "grpc://192.168.42.1:50051");
+    // Paths are relative to the config file
+    assert!(config
+        .profile
+        .unwrap()
+        .ends_with("bot/profiles/zeroth.toml"));
+    assert!(config.safety.unwrap().exists());
+
+    // The example gains are the defaults
+    let balance = concat!(env!("CARGO_MANIFEST_DIR"), "/balance.toml");
+    assert_eq!(
+        humanoid::BalanceConfig::load(balance).unwrap(),
+        humanoid::BalanceConfig::default()
+    );
+    assert_eq!(
+        humanoid::FallConfig::load(config.fall.unwrap()).unwrap(),
+        humanoid::FallConfig::default()
+    );
+    assert_eq!(
+        humanoid::ProtectionConfig::load(config.protection.unwrap()).unwrap(),
+        humanoid::ProtectionConfig::default()
+    );
+}
+
+#[test]
+fn cli_overrides_config_file() {
+    let dir = std::env::temp_dir().join(format!("bot-config-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(
+        dir.join("bot.toml"),
+        "backend = \"kbot\"\nrobot_address = \"grpc://10.0.0.2:50051\"\n\n[initial_pose]\nLeftShoulderPitch = 20.0\n",
+    )
+    .unwrap();
+
+    let config = Cli::parse_from([
+        "bot",
+        "--config",
+        dir.join("bot.toml").to_str().unwrap(),
+        "--bind",
+        "127.0.0.1:9000",
+        "--control-rate-hz",
+        "100",
+    ])
+    .into_config()
+    .unwrap();
+
+    assert_eq!(config.backend, Backend::Kbot);
+    assert_eq!(config.robot_address(), "grpc://10.0.0.2:50051");
+    assert_eq!(config.bind, "127.0.0.1:9000");
+    assert_eq!(config.control_rate_hz, 100.0);
+    assert_eq!(config.calibration_file, dir.join("calibration.json"));
+    assert_eq!(
+        config.initial_pose.unwrap()[&Joint::LeftShoulderPitch],
+        20.0
+    );
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn kbot_needs_an_address() {
+    let err = Cli::parse_from(["bot", "--backend", "kbot"])
+        .into_config()
+        .unwrap_err();
+    assert!(err.to_string().contains("robot_address"));
+}
+
+#[test]
+fn control_rate_must_be_finite_and_bounded() {
+    for rate in ["inf", "NaN", "0", "-50", "5000"] {
+        let err = Cli::parse_from([
+            "bot",
+            "--backend",
+            "sim",
+            &format!("--control-rate-hz={}", rate),
+        ])
+        .into_config()
+        .unwrap_err();
+        assert!(err.to_string().contains("control_rate_hz"), "{}", rate);
+    }
+}
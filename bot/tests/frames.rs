@@ -0,0 +1,24 @@
+use bot::{frame_json_to_frame, initial_position};
+use humanoid::{Joint, Runtime, SimCall, SimulatedHumanoid};
+
+#[test]
+fn parses_frame_json() {
+    let frame = frame_json_to_frame(serde_json::json!({ "15": 12.5, "1": -3.0 })).unwrap();
+
+    assert_eq!(frame.joints.len(), 2);
+    assert_eq!(frame.joints[&Joint::LeftShoulderPitch], 12.5);
+    assert_eq!(frame.joints[&Joint::LeftHipPitch], -3.0);
+}
+
+#[tokio::test]
+async fn initial_position_sets_every_joint() {
+    let sim = SimulatedHumanoid::default();
+    let runtime = Runtime::new(sim.clone());
+
+    initial_position(&runtime).await.unwrap();
+
+    let calls = sim.calls();
+    assert_eq!(calls.len(), 16);
+    assert!(calls.contains(&SimCall::SetJoint(Joint::RightShoulderPitch, 90.0)));
+    assert_eq!(sim.target(Joint::LeftHipPitch), Some(65.0));
+}
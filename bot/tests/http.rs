@@ -0,0 +1,359 @@
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+};
+use bot::AppState;
+use http_body_util::BodyExt;
+use humanoid::{
+    EulerAngles, FallEvent, FallReason, Halt, Joint, JointInfo, Runtime, ServoHealth,
+    SimulatedHumanoid, TelemetrySnapshot,
+};
+use tower::ServiceExt;
+
+async fn send(
+    app: axum::Router,
+    method: &str,
+    uri: &str,
+    body: Option<serde_json::Value>,
+) -> (StatusCode, serde_json::Value) {
+    let request = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header("content-type", "application/json")
+        .body(body.map_or_else(Body::empty, |body| Body::from(body.to_string())))
+        .unwrap();
+
+    let response = app.oneshot(request).await.unwrap();
+    let status = response.status();
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    (status, serde_json::from_slice(&body).unwrap())
+}
+
+async fn post_frame(
+    app: axum::Router,
+    joints: serde_json::Value,
+) -> (StatusCode, serde_json::Value) {
+    send(
+        app,
+        "POST",
+        "/frame",
+        Some(serde_json::json!({ "joints": joints })),
+    )
+    .await
+}
+
+#[tokio::test]
+async fn frame_with_unsupported_joint_is_rejected() {
+    let sim =
+        SimulatedHumanoid::default().with_joints([JointInfo::new(Joint::LeftHipPitch, 0.0, 90.0)]);
+    let runtime = Runtime::new(sim);
+    let app = bot::router(AppState::new(runtime.queue()));
+
+    // Joint 28 is the neck yaw
+    let (status, body) = post_frame(app.clone(), serde_json::json!({ "1": 10.0, "28": 5.0 })).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(body["errors"][0]["reason"], "unsupported_joint");
+    assert_eq!(body["errors"][0]["joint"], "NeckYaw");
+    assert_eq!(runtime.current_frame(), None);
+
+    let (status, _) = post_frame(app, serde_json::json!({ "1": 10.0 })).await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(
+        runtime.current_frame().unwrap().joints[&Joint::LeftHipPitch],
+        10.0
+    );
+}
+
+#[tokio::test]
+async fn malformed_frames_name_the_joint_and_reason() {
+    let runtime = Runtime::new(SimulatedHumanoid::default());
+    let app = bot::router(AppState::new(runtime.queue()));
+
+    let (status, body) = post_frame(
+        app.clone(),
+        serde_json::json!({ "knee": 1.0, "99": 1.0, "15": "up" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let errors = body["errors"].as_array().unwrap();
+    assert_eq!(errors.len(), 3);
+    assert!(errors.contains(&serde_json::json!({
+        "reason": "not_a_number",
+        "joint": "LeftShoulderPitch",
+        "value": "up",
+        "message": "LeftShoulderPitch: expected a number, got \"up\"",
+    })));
+    assert!(errors
+        .iter()
+        .any(|e| e["reason"] == "unknown_joint" && e["joint"] == "99"));
+
+    // Values that overflow a 32-bit float never reach the robot
+    let (status, body) = post_frame(app.clone(), serde_json::json!({ "15": 1e39 })).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["errors"][0]["reason"], "not_finite");
+    assert_eq!(body["errors"][0]["joint"], "LeftShoulderPitch");
+
+    // Joints can be named instead of numbered
+    let (status, _) = post_frame(app, serde_json::json!({ "LeftShoulderPitch": 30.0 })).await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(
+        runtime.current_frame().unwrap().joints[&Joint::LeftShoulderPitch],
+        30.0
+    );
+}
+
+#[tokio::test]
+async fn strict_mode_rejects_out_of_range_values() {
+    let sim =
+        SimulatedHumanoid::default().with_joints([JointInfo::new(Joint::LeftHipPitch, 0.0, 90.0)]);
+    let runtime = Runtime::new(sim);
+    let lenient = bot::router(AppState::new(runtime.queue()));
+    let strict = bot::router(AppState {
+        strict: true,
+        ..AppState::new(runtime.queue())
+    });
+
+    let (status, _) = post_frame(lenient, serde_json::json!({ "LeftHipPitch": 120.0 })).await;
+    assert_eq!(status, StatusCode::CREATED);
+
+    let (status, body) = post_frame(strict, serde_json::json!({ "LeftHipPitch": 120.0 })).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(
+        body["errors"][0],
+        serde_json::json!({
+            "reason": "out_of_range",
+            "joint": "LeftHipPitch",
+            "value": 120.0,
+            "min": 0.0,
+            "max": 90.0,
+            "message": "LeftHipPitch: 120 is outside 0..=90",
+        })
+    );
+}
+
+#[tokio::test]
+async fn frames_can_be_queued_inspected_and_cleared() {
+    let runtime = Runtime::new(SimulatedHumanoid::default());
+    let app = bot::router(AppState::new(runtime.queue()));
+
+    let (status, body) = send(
+        app.clone(),
+        "POST",
+        "/frames",
+        Some(serde_json::json!({ "frames": [{ "1": 10.0 }, { "1": 20.0 }, { "1": 30.0 }] })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(body, serde_json::json!({ "queued": 3, "queue_len": 3 }));
+
+    // A batch with one bad frame queues nothing
+    let (status, body) = send(
+        app.clone(),
+        "POST",
+        "/frames",
+        Some(serde_json::json!({ "frames": [{ "1": 40.0 }, { "1": "up" }] })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["errors"][0]["frame"], 1);
+
+    let (status, _) = send(
+        app.clone(),
+        "POST",
+        "/frame?mode=push",
+        Some(serde_json::json!({ "joints": { "1": 50.0 } })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+
+    assert_eq!(
+        runtime.current_frame().unwrap().joints[&Joint::LeftHipPitch],
+        10.0
+    );
+    let (_, body) = send(app.clone(), "GET", "/queue", None).await;
+    assert_eq!(body["len"], 3);
+    assert_eq!(body["current"]["joints"]["LeftHipPitch"], 10.0);
+
+    let (_, body) = send(app.clone(), "DELETE", "/queue", None).await;
+    assert_eq!(body, serde_json::json!({ "cleared": 3 }));
+
+    // Overwriting replaces the current frame straight away
+    let (_, body) = post_frame(app.clone(), serde_json::json!({ "1": 60.0 })).await;
+    assert_eq!(body, serde_json::json!({ "queue_len": 0 }));
+    let (_, body) = send(app, "GET", "/queue", None).await;
+    assert_eq!(body["current"]["joints"]["LeftHipPitch"], 60.0);
+}
+
+#[tokio::test]
+async fn halt_is_reported_and_reset() {
+    let runtime = Runtime::new(SimulatedHumanoid::default());
+    let app = bot::router(AppState::new(runtime.queue()));
+
+    let (status, body) = send(app.clone(), "GET", "/halt", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["halt"], serde_json::Value::Null);
+    let (status, _) = send(app.clone(), "POST", "/halt/reset", None).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    runtime.queue().halt(Halt::Fall(FallEvent {
+        reason: FallReason::Tilt,
+        orientation: EulerAngles::new(0.0, 80.0, 0.0),
+        accel: 1.0,
+    }));
+    let (_, body) = send(app.clone(), "GET", "/halt", None).await;
+    assert_eq!(body["halt"]["cause"], "fall");
+    assert_eq!(body["halt"]["reason"], "tilt");
+
+    let (status, body) = send(app.clone(), "POST", "/halt/reset", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["reset"]["cause"], "fall");
+    assert_eq!(body["halt"], serde_json::Value::Null);
+    assert_eq!(runtime.queue().halted(), None);
+}
+
+#[tokio::test]
+async fn estop_is_latched_on_status() {
+    let sim = SimulatedHumanoid::default();
+    let runtime = Runtime::new(sim.clone());
+    let app = bot::router(AppState::new(runtime.queue()));
+
+    let (status, body) = send(app.clone(), "GET", "/status", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["status"], "ok");
+
+    let (status, body) = send(app.clone(), "POST", "/estop", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["halt"]["cause"], "emergency_stop");
+    assert!(!sim.torque_enabled());
+
+    let (_, body) = send(app.clone(), "GET", "/status", None).await;
+    assert_eq!(body["status"], "halted");
+    assert_eq!(body["halt"]["cause"], "emergency_stop");
+
+    // Nothing is accepted until the halt is reset
+    let frame = serde_json::json!({ "joints": { "LeftElbowYaw": 10.0 } });
+    let (status, body) = send(app.clone(), "POST", "/frame", Some(frame.clone())).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(body["halt"]["cause"], "emergency_stop");
+    let (status, _) = send(
+        app.clone(),
+        "POST",
+        "/frames",
+        Some(serde_json::json!({ "frames": [frame["joints"]] })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    let (status, _) = send(
+        app.clone(),
+        "POST",
+        "/playback",
+        Some(serde_json::json!({ "frames": [frame["joints"], frame["joints"]] })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(runtime.queue().frames_received(), 0);
+
+    send(app.clone(), "POST", "/halt/reset", None).await;
+    let (_, body) = send(app.clone(), "GET", "/status", None).await;
+    assert_eq!(body["status"], "ok");
+    let (status, _) = send(app, "POST", "/frame", Some(frame)).await;
+    assert_eq!(status, StatusCode::CREATED);
+}
+
+#[tokio::test]
+async fn telemetry_is_served_and_streamed() {
+    let runtime = Runtime::new(SimulatedHumanoid::default());
+    let state = AppState::new(runtime.queue());
+    let app = bot::router(state.clone());
+
+    let (status, _) = send(app.clone(), "GET", "/telemetry", None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let snapshot = |time| TelemetrySnapshot {
+        time,
+        servos: vec![ServoHealth {
+            joint: Joint::LeftElbowYaw,
+            id: 16,
+            temperature: Some(42.0),
+            current: Some(0.5),
+            voltage: Some(12.0),
+        }],
+    };
+    state.telemetry.push(snapshot(1000));
+    state.telemetry.push(snapshot(2000));
+
+    let (status, body) = send(app.clone(), "GET", "/telemetry", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["time"], 2000);
+    assert_eq!(body["servos"][0]["joint"], "LeftElbowYaw");
+    assert_eq!(body["servos"][0]["temperature"], 42.0);
+
+    let (_, body) = send(app.clone(), "GET", "/telemetry/history?since=1000", None).await;
+    assert_eq!(body.as_array().unwrap().len(), 1);
+
+    // The stream starts with the latest snapshot, then sends each new one
+    let request = Request::builder()
+        .uri("/telemetry/stream")
+        .body(Body::empty())
+        .unwrap();
+    let mut body = app.oneshot(request).await.unwrap().into_body();
+    let first = body.frame().await.unwrap().unwrap().into_data().unwrap();
+    assert!(String::from_utf8_lossy(&first).starts_with("event: telemetry\ndata: {\"time\":2000,"));
+
+    state.telemetry.push(snapshot(3000));
+    let next = body.frame().await.unwrap().unwrap().into_data().unwrap();
+    assert!(String::from_utf8_lossy(&next).contains("\"time\":3000"));
+}
+
+#[tokio::test]
+async fn metrics_are_exposed_for_prometheus() {
+    let runtime = Runtime::new(SimulatedHumanoid::default());
+    let state = AppState::new(runtime.queue());
+    let app = bot::router(state.clone());
+
+    post_frame(app.clone(), serde_json::json!({ "1": 10.0 })).await;
+    state.call_errors.record("set_joints");
+    state.telemetry.push(TelemetrySnapshot {
+        time: 1000,
+        servos: vec![ServoHealth {
+            joint: Joint::LeftElbowYaw,
+            id: 16,
+            temperature: Some(42.5),
+            current: None,
+            voltage: None,
+        }],
+    });
+    state.frame_queue.halt(Halt::EmergencyStop);
+
+    let request = Request::builder()
+        .uri("/metrics")
+        .body(Body::empty())
+        .unwrap();
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(response.headers()["content-type"]
+        .to_str()
+        .unwrap()
+        .starts_with("text/plain; version=0.0.4"));
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body = String::from_utf8(body.to_vec()).unwrap();
+
+    for line in [
+        "# TYPE bot_frames_received_total counter",
+        "bot_frames_received_total 1",
+        "bot_queue_depth 0",
+        "bot_robot_call_errors_total{method=\"set_joints\"} 1",
+        "bot_servo_temperature_celsius{joint=\"LeftElbowYaw\",id=\"16\"} 42.5",
+        "bot_halted 1",
+        "bot_halt_cause{cause=\"emergency_stop\"} 1",
+    ] {
+        assert!(
+            body.lines().any(|l| l == line),
+            "{} missing from\n{}",
+            line,
+            body
+        );
+    }
+    // No readings, no samples
+    assert!(!body.contains("bot_servo_current_amperes{"));
+}
@@ -0,0 +1,157 @@
+use bot::k_bot::KBot;
+use humanoid::{
+    load_or_calibrate, Halt, Humanoid, Imu, Instrumented, Joint, Runtime, ServoProtection,
+    ServoTelemetry, UnsupportedJoints,
+};
+use kbot::sim::{SimImu, SimKos};
+
+#[tokio::test]
+async fn k_bot_against_simulator() {
+    let sim = SimKos::new();
+    let (url, _handle) = sim.clone().spawn().await.unwrap();
+
+    let client = kbot::Client::connect(url).await.unwrap();
+    let mut robot = KBot::new(client);
+
+    // Joints are sent to the actuator named in the profile
+    robot
+        .set_joint(Joint::LeftShoulderPitch, 20.0)
+        .await
+        .unwrap();
+    assert_eq!(sim.actuator(14).unwrap().target, 20.0);
+
+    let joint = robot.get_joint(Joint::LeftShoulderPitch).await.unwrap();
+    assert_eq!(joint.joint, Joint::LeftShoulderPitch);
+
+    // Leg joints are not driven by the K-Bot backend
+    let err = robot
+        .set_joint(Joint::LeftHipPitch, 20.0)
+        .await
+        .unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<UnsupportedJoints>(),
+        Some(&UnsupportedJoints {
+            joints: vec![Joint::LeftHipPitch]
+        })
+    );
+}
+
+#[tokio::test(start_paused = true)]
+async fn k_bot_calibration_runs_once() {
+    let sim = SimKos::new();
+    let (url, _handle) = sim.clone().spawn().await.unwrap();
+    let path = std::env::temp_dir().join(format!("kbot-calibration-{}.toml", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+
+    let mut robot = KBot::new(kbot::Client::connect(&url).await.unwrap());
+    let started = tokio::time::Instant::now();
+    let calibration = load_or_calibrate(&mut robot, &path, false).await.unwrap();
+    assert!(started.elapsed() >= kbot::sim::CALIBRATION_DURATION);
+    assert_eq!(calibration.robot.model, "kbot");
+    assert_eq!(calibration.servos.len(), 6);
+
+    // The saved calibration is restored without running the routine again
+    let mut robot = KBot::new(kbot::Client::connect(&url).await.unwrap());
+    let started = tokio::time::Instant::now();
+    assert_eq!(
+        load_or_calibrate(&mut robot, &path, false).await.unwrap(),
+        calibration
+    );
+    assert!(started.elapsed() < kbot::sim::CALIBRATION_DURATION);
+
+    std::fs::remove_file(path).unwrap();
+}
+
+#[tokio::test]
+async fn k_bot_reads_imu_orientation_from_kos() {
+    let sim = SimKos::new();
+    let (url, _handle) = sim.clone().spawn().await.unwrap();
+    let mut robot = KBot::new(kbot::Client::connect(url).await.unwrap());
+
+    sim.set_imu(SimImu {
+        gyro: [1.0, -2.0, 3.0],
+        roll: 10.0,
+        pitch: -5.0,
+        yaw: 30.0,
+        ..Default::default()
+    });
+    let reading = robot.read_imu().await.unwrap();
+
+    assert_eq!(reading.gyro, [1.0, -2.0, 3.0]);
+    assert_eq!(reading.mag, None);
+    let euler = reading.euler();
+    for (actual, expected) in [(euler.roll, 10.0), (euler.pitch, -5.0), (euler.yaw, 30.0)] {
+        assert!((actual - expected).abs() < 1e-3, "{:?}", euler);
+    }
+}
+
+#[tokio::test]
+async fn k_bot_cuts_torque_on_every_mapped_actuator() {
+    let sim = SimKos::new();
+    let (url, _handle) = sim.clone().spawn().await.unwrap();
+    let robot = KBot::new(kbot::Client::connect(url).await.unwrap());
+
+    robot.torque_cutoff().unwrap().cut().await.unwrap();
+    for mapping in robot.profile().joints.values() {
+        assert!(!sim.actuator(mapping.id as u32).unwrap().torque_enabled);
+    }
+}
+
+#[tokio::test]
+async fn k_bot_reports_servo_health_by_joint() {
+    let sim = SimKos::new();
+    let (url, _handle) = sim.clone().spawn().await.unwrap();
+    let robot = KBot::new(kbot::Client::connect(url).await.unwrap());
+
+    sim.update_actuator(14, |actuator| {
+        actuator.temperature = 61.5;
+        actuator.voltage = 23.8;
+    });
+
+    let health = robot.servo_health().await.unwrap();
+    assert_eq!(health.len(), robot.profile().joints.len());
+    let shoulder = health
+        .iter()
+        .find(|servo| servo.joint == Joint::LeftShoulderPitch)
+        .unwrap();
+    assert_eq!(shoulder.id, 14);
+    assert_eq!(shoulder.temperature, Some(61.5));
+    // Follows the load, which is none at rest
+    assert_eq!(shoulder.current, Some(0.0));
+    assert_eq!(shoulder.voltage, Some(23.8));
+}
+
+#[tokio::test]
+async fn failed_robot_calls_are_counted() {
+    // The left elbow's actuator is missing, so the robot refuses any request for it
+    let sim = SimKos::with_actuators([11, 12, 13, 14, 15]);
+    let (url, _handle) = sim.spawn().await.unwrap();
+    let robot = KBot::new(kbot::Client::connect(url).await.unwrap());
+    let runtime = Runtime::new(Instrumented::new(robot, bot::metrics::is_robot_call_error));
+    let errors = runtime.lock().await.errors();
+
+    let mut robot = runtime.lock().await;
+    robot
+        .set_torque_limit(Joint::LeftShoulderPitch, 20.0)
+        .await
+        .unwrap();
+    assert!(robot
+        .set_torque_limit(Joint::LeftElbowYaw, 20.0)
+        .await
+        .is_err());
+    // Never sent, since the profile has no leg joints
+    assert!(robot.set_joint(Joint::LeftHipPitch, 10.0).await.is_err());
+    drop(robot);
+
+    // The emergency stop used by /estop and the watchdog
+    assert!(runtime
+        .queue()
+        .emergency_stop(Halt::EmergencyStop)
+        .await
+        .is_err());
+
+    assert_eq!(errors.get("set_torque_limit"), 1);
+    assert_eq!(errors.get("set_joint"), 0);
+    assert_eq!(errors.get("torque_cutoff"), 1);
+    assert_eq!(errors.counts().len(), 2);
+}
@@ -0,0 +1,107 @@
+use std::time::Duration;
+
+use bot::{
+    k_bot::{KBot, K_BOT_PROFILE},
+    playback::{load_motion, save_motion},
+};
+use humanoid::{ImuSample, Interpolation, Joint, JointProfile, MotionClip, Runtime};
+use kbot::{
+    krec::{ActuatorCommand, KRec, KRecFrame, KRecHeader},
+    sim::SimKos,
+};
+
+fn temp_path(name: &str) -> std::path::PathBuf {
+    std::env::temp_dir().join(format!("{}-{}.krec", name, std::process::id()))
+}
+
+#[test]
+fn clips_round_trip_through_krec() {
+    let profile = JointProfile::from_toml(K_BOT_PROFILE).unwrap();
+
+    let mut clip = MotionClip::new("wave");
+    clip.robot = Some("kbot".to_string());
+    clip.push(
+        Duration::ZERO,
+        [
+            (Joint::LeftShoulderPitch, 0.0),
+            (Joint::RightElbowYaw, 10.0),
+        ]
+        .into_iter()
+        .collect(),
+    );
+    clip.push(
+        Duration::from_millis(500),
+        [(Joint::LeftShoulderPitch, 45.0)].into_iter().collect(),
+    );
+    clip.feedback.push(humanoid::ClipFrame {
+        time: 0.25,
+        joints: [(Joint::LeftShoulderPitch, 20.0)].into_iter().collect(),
+    });
+    clip.imu.push(ImuSample {
+        time: 0.5,
+        gyro: Some([0.0, 1.0, 2.0]),
+        quaternion: Some([0.0, 0.0, 0.0, 1.0]),
+        ..Default::default()
+    });
+
+    let path = temp_path("wave");
+    save_motion(&clip, &profile, &path).unwrap();
+
+    let krec = KRec::load(path.to_str().unwrap()).unwrap();
+    assert_eq!(krec.frames.len(), 3);
+    let ids: Vec<_> = krec.frames[0]
+        .actuator_commands
+        .iter()
+        .map(|command| command.actuator_id)
+        .collect();
+    assert_eq!(ids, [14, 11]);
+
+    assert_eq!(load_motion(&path, 50.0, &profile).unwrap(), clip);
+    std::fs::remove_file(path).unwrap();
+}
+
+#[tokio::test(start_paused = true)]
+async fn krec_episodes_replay_through_k_bot() {
+    let command = |actuator_id, position| ActuatorCommand {
+        actuator_id,
+        position,
+        ..Default::default()
+    };
+    let mut krec = KRec::new(KRecHeader {
+        task: "reach".to_string(),
+        start_timestamp: 1_000_000_000,
+        ..Default::default()
+    });
+    for (i, position) in [0.0, 15.0, 30.0].into_iter().enumerate() {
+        krec.add_frame(KRecFrame {
+            real_timestamp: 1_000_000_000 + i as u64 * 100_000_000,
+            // Actuator 40 is not driven by the K-Bot profile and is left out
+            actuator_commands: vec![command(14, position), command(40, 1.0)],
+            ..Default::default()
+        });
+    }
+    let path = temp_path("reach");
+    krec.save(path.to_str().unwrap()).unwrap();
+
+    let profile = JointProfile::from_toml(K_BOT_PROFILE).unwrap();
+    let clip = load_motion(&path, 50.0, &profile).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(clip.name, "reach");
+    let times: Vec<_> = clip.frames.iter().map(|frame| frame.time).collect();
+    assert_eq!(times, [0.0, 0.1, 0.2]);
+
+    let sim = SimKos::new();
+    let (url, _handle) = sim.clone().spawn().await.unwrap();
+    let runtime = Runtime::new(KBot::new(kbot::Client::connect(url).await.unwrap()));
+    let handle = runtime.run(50.0).unwrap();
+
+    runtime
+        .play(clip.trajectory(Interpolation::Linear).unwrap())
+        .unwrap();
+    while runtime.is_playing() {
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    handle.stop().await.unwrap();
+
+    assert_eq!(sim.actuator(14).unwrap().target, 30.0);
+}
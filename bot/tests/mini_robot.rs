@@ -0,0 +1,239 @@
+use std::{collections::BTreeMap, time::Duration};
+
+use bot::mini_robot::MiniRobot;
+use humanoid::{
+    load_or_calibrate, Calibrate, Humanoid, Imu, Joint, ServoProtection, ServoTelemetry,
+};
+use zeroth::sim::SimServoControl;
+
+#[tokio::test]
+async fn mini_robot_against_simulator() {
+    let sim = SimServoControl::new();
+    let (url, _handle) = sim.clone().spawn().await.unwrap();
+
+    let mut client = zeroth::Client::connect(url).await.unwrap();
+    client.enable_movement().await.unwrap();
+
+    let mut robot = MiniRobot::new(client);
+    robot.calibrate().await.unwrap();
+
+    let servo = sim.servo(zeroth::ServoId::LeftShoulderYaw.into()).unwrap();
+    assert!(servo.torque_enabled);
+    assert_eq!(servo.torque, 50.0);
+
+    robot
+        .set_joints(BTreeMap::from([
+            (Joint::LeftShoulderYaw, 45.0),
+            (Joint::RightShoulderYaw, 0.0),
+        ]))
+        .await
+        .unwrap();
+
+    // Each joint goes to the servo named in the profile, mapped onto its calibrated range
+    let servo = sim.servo(zeroth::ServoId::LeftShoulderYaw.into()).unwrap();
+    assert_eq!(servo.target, 0.0);
+    assert_eq!(servo.speed, 30.0);
+
+    // The right shoulder is inverted, so 0 is the top of its range
+    let servo = sim.servo(zeroth::ServoId::RightShoulderYaw.into()).unwrap();
+    assert_eq!(servo.target, 90.0);
+
+    // The Zeroth has no neck, which used to panic while translating
+    let err = robot.set_joint(Joint::NeckYaw, 10.0).await.unwrap_err();
+    assert!(err.downcast_ref::<humanoid::UnsupportedJoints>().is_some());
+}
+
+#[tokio::test]
+async fn failed_commands_are_errors() {
+    // A robot missing the left shoulder servo refuses commands to it
+    let left_shoulder: i32 = zeroth::ServoId::LeftShoulderYaw.into();
+    let sim = SimServoControl::with_servos((1..=16).filter(|&id| id != left_shoulder));
+    let (url, _handle) = sim.spawn().await.unwrap();
+
+    let mut robot = MiniRobot::new(zeroth::Client::connect(url).await.unwrap());
+    robot.enable_movement().await.unwrap();
+    assert!(robot.set_joint(Joint::LeftShoulderYaw, 45.0).await.is_err());
+    robot
+        .set_joint(Joint::RightShoulderYaw, 45.0)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn calibration_is_saved_and_restored() {
+    let sim = SimServoControl::new();
+    let (url, _handle) = sim.clone().spawn().await.unwrap();
+    let path = std::env::temp_dir().join(format!("zeroth-calibration-{}.json", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+
+    let servo_id: i32 = zeroth::ServoId::LeftShoulderYaw.into();
+    let limits = |calibration: &humanoid::Calibration| {
+        let servo = calibration.servo(servo_id).unwrap();
+        (servo.min.unwrap(), servo.max.unwrap())
+    };
+
+    let mut robot = MiniRobot::new(zeroth::Client::connect(&url).await.unwrap());
+    let calibration = load_or_calibrate(&mut robot, &path, false).await.unwrap();
+    assert_eq!(limits(&calibration), (-90.0, 90.0));
+    assert!(path.exists());
+
+    // The servo now reports different limits, but its position still agrees with the saved
+    // ones, so they are reused without probing
+    sim.update_servo(servo_id, |servo| {
+        servo.min_position = -45.0;
+        servo.max_position = 45.0;
+    });
+    let mut robot = MiniRobot::new(zeroth::Client::connect(&url).await.unwrap());
+    let restored = load_or_calibrate(&mut robot, &path, false).await.unwrap();
+    assert_eq!(restored, calibration);
+    assert_eq!(robot.calibration(), Some(calibration));
+
+    // Asking for it, or a servo reading outside the saved range, probes again
+    let mut robot = MiniRobot::new(zeroth::Client::connect(&url).await.unwrap());
+    let forced = load_or_calibrate(&mut robot, &path, true).await.unwrap();
+    assert_eq!(limits(&forced), (-45.0, 45.0));
+
+    sim.update_servo(servo_id, |servo| {
+        servo.min_position = -30.0;
+        servo.max_position = 120.0;
+        servo.position = 100.0;
+    });
+    let mut robot = MiniRobot::new(zeroth::Client::connect(&url).await.unwrap());
+    let reprobed = load_or_calibrate(&mut robot, &path, false).await.unwrap();
+    assert_eq!(limits(&reprobed), (-30.0, 120.0));
+
+    std::fs::remove_file(path).unwrap();
+}
+
+#[tokio::test]
+async fn mini_robot_balances_against_simulated_imu() {
+    let sim = SimServoControl::new();
+    let (url, _handle) = sim.clone().spawn().await.unwrap();
+    // Proportional only, as the readings below change faster than any real IMU
+    let config = humanoid::BalanceConfig {
+        pitch: humanoid::AxisGains {
+            kd: 0.0,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    // Follow the accelerometer straight away rather than over the filter's time constant
+    let mut robot = MiniRobot::new(zeroth::Client::connect(url).await.unwrap())
+        .with_balance(config)
+        .unwrap()
+        .with_imu_filter(humanoid::ComplementaryFilter::new(Duration::ZERO));
+
+    robot
+        .set_joints(BTreeMap::from([(Joint::LeftAnklePitch, 0.0)]))
+        .await
+        .unwrap();
+    let mapping = *robot.profile().mapping(Joint::LeftAnklePitch).unwrap();
+    let servo_id = mapping.id;
+    assert_eq!(sim.servo(servo_id).unwrap().target, mapping.to_servo(0.0));
+
+    // Upright, nothing to correct
+    robot.stabilize().await.unwrap();
+    assert_eq!(sim.servo(servo_id).unwrap().target, mapping.to_servo(0.0));
+
+    // Gravity pulling backwards in the IMU frame means the torso leans forward
+    sim.set_imu(zeroth::ImuData {
+        gyro: Some(zeroth::proto::Vector3 {
+            x: 0.0,
+            y: 0.0,
+            z: 0.0,
+        }),
+        accel: Some(zeroth::proto::Vector3 {
+            x: -174.0,
+            y: 0.0,
+            z: 985.0,
+        }),
+    });
+    robot.stabilize().await.unwrap();
+    let pitch = humanoid::EulerAngles::from_accel([-174.0, 0.0, 985.0]).pitch;
+    assert!(pitch > 9.0);
+    let offset = 0.5 * -0.5 * (pitch - 1.0);
+    // Equal up to rounding, as the filter goes through a quaternion
+    let target = sim.servo(servo_id).unwrap().target;
+    assert!(
+        (target - mapping.to_servo(offset)).abs() < 1e-4,
+        "{}",
+        target
+    );
+}
+
+#[tokio::test]
+async fn mini_robot_reads_imu_in_common_units() {
+    let sim = SimServoControl::new();
+    let (url, _handle) = sim.clone().spawn().await.unwrap();
+    let mut robot = MiniRobot::new(zeroth::Client::connect(url).await.unwrap());
+
+    sim.set_imu(zeroth::ImuData {
+        gyro: Some(zeroth::proto::Vector3 {
+            x: 1.5,
+            y: 0.0,
+            z: -2.0,
+        }),
+        accel: Some(zeroth::proto::Vector3 {
+            x: 0.0,
+            y: 500.0,
+            z: 866.0,
+        }),
+    });
+    let reading = robot.read_imu().await.unwrap();
+
+    assert_eq!(reading.gyro, [1.5, 0.0, -2.0]);
+    assert!((reading.accel[1] - 0.5 * humanoid::STANDARD_GRAVITY).abs() < 1e-3);
+    // The first reading is taken as at rest, so the estimate starts from gravity alone
+    let euler = reading.euler();
+    assert!((euler.roll - 30.0).abs() < 0.1, "{:?}", euler);
+    assert!(euler.pitch.abs() < 0.1, "{:?}", euler);
+}
+
+#[tokio::test]
+async fn mini_robot_cuts_torque_without_its_lock() {
+    let sim = SimServoControl::new();
+    let (url, _handle) = sim.clone().spawn().await.unwrap();
+    let mut robot = MiniRobot::new(zeroth::Client::connect(url).await.unwrap());
+
+    robot.set_torque_enabled(true).await.unwrap();
+    assert!(sim.movement_enabled());
+    assert!(sim.servo(1).unwrap().torque_enabled);
+
+    robot.torque_cutoff().unwrap().cut().await.unwrap();
+    assert!(!sim.movement_enabled());
+    assert!((1..=16).all(|id| !sim.servo(id).unwrap().torque_enabled));
+}
+
+#[tokio::test]
+async fn mini_robot_throttles_single_servos() {
+    let sim = SimServoControl::new();
+    let (url, _handle) = sim.clone().spawn().await.unwrap();
+    let mut robot = MiniRobot::new(zeroth::Client::connect(url).await.unwrap());
+    robot.set_torque_enabled(true).await.unwrap();
+
+    let id = zeroth::ServoId::LeftShoulderYaw.into();
+    sim.update_servo(id, |servo| servo.temperature = 64.0);
+    let health = robot.servo_health().await.unwrap();
+    let shoulder = health
+        .iter()
+        .find(|servo| servo.joint == Joint::LeftShoulderYaw)
+        .unwrap();
+    assert_eq!(shoulder.temperature, Some(64.0));
+
+    robot
+        .set_torque_limit(Joint::LeftShoulderYaw, 25.0)
+        .await
+        .unwrap();
+    robot
+        .set_joint_torque_enabled(Joint::LeftShoulderYaw, false)
+        .await
+        .unwrap();
+    assert_eq!(sim.servo(id).unwrap().torque, 25.0);
+    assert!(!sim.servo(id).unwrap().torque_enabled);
+    // The rest keep going
+    assert!(
+        sim.servo(zeroth::ServoId::RightShoulderYaw.into())
+            .unwrap()
+            .torque_enabled
+    );
+}
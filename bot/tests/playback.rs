@@ -0,0 +1,121 @@
+use std::path::PathBuf;
+
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+};
+use bot::AppState;
+use http_body_util::BodyExt;
+use humanoid::{Runtime, SimulatedHumanoid};
+use tower::ServiceExt;
+
+fn motions_dir() -> PathBuf {
+    PathBuf::from(concat!(env!("CARGO_MANIFEST_DIR"), "/../pose_mappings"))
+}
+
+async fn post(
+    app: axum::Router,
+    uri: &str,
+    body: serde_json::Value,
+) -> (StatusCode, serde_json::Value) {
+    let request = Request::post(uri)
+        .header("content-type", "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap();
+
+    let response = app.oneshot(request).await.unwrap();
+    let status = response.status();
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    (status, serde_json::from_slice(&body).unwrap())
+}
+
+#[test]
+fn file_to_frames_reports_missing_files() {
+    let frames = bot::file_to_frames(motions_dir().join("flapping_motion.json")).unwrap();
+    assert!(frames.len() > 10);
+
+    assert!(bot::file_to_frames(motions_dir().join("missing.json")).is_err());
+}
+
+#[tokio::test]
+async fn motion_files_play_pause_resume_and_stop() {
+    let runtime = Runtime::new(SimulatedHumanoid::default());
+    let app = bot::router(AppState {
+        motions_dir: motions_dir(),
+        ..AppState::new(runtime.queue())
+    });
+
+    let (status, body) = post(
+        app.clone(),
+        "/playback",
+        serde_json::json!({ "file": "flapping_motion.json", "loops": 0, "speed": 2.0 }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(body["playback"]["options"]["loops"], 0);
+    assert_eq!(body["playback"]["options"]["speed"], 2.0);
+    assert!(runtime.is_playing());
+
+    let (status, body) = post(app.clone(), "/playback/pause", serde_json::json!({})).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["playback"]["paused"], true);
+
+    let (_, body) = post(app.clone(), "/playback/resume", serde_json::json!({})).await;
+    assert_eq!(body["playback"]["paused"], false);
+
+    let (status, _) = post(app.clone(), "/playback/stop", serde_json::json!({})).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(!runtime.is_playing());
+
+    let (status, _) = post(app.clone(), "/playback/pause", serde_json::json!({})).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    // Only files inside the motions directory can be played
+    let (status, _) = post(
+        app.clone(),
+        "/playback",
+        serde_json::json!({ "file": "../bot/Cargo.toml" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, _) = post(
+        app.clone(),
+        "/playback",
+        serde_json::json!({ "file": "missing.json" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    // Bodies that don't parse get the same JSON errors as /frame
+    let (status, body) = post(
+        app.clone(),
+        "/playback",
+        serde_json::json!({ "file": "flapping_motion.json", "loops": "forever" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(body["errors"][0]["reason"], "invalid_json");
+
+    // Uploaded clips too, including frame times too far out to play
+    let (status, _) = post(
+        app.clone(),
+        "/playback",
+        serde_json::json!({ "clip": {
+            "version": 1,
+            "name": "forever",
+            "frames": [{ "time": 0.0, "joints": {} }, { "time": 1e30, "joints": {} }],
+        } }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+
+    // Uploaded frames are validated like any other
+    let (status, body) = post(
+        app,
+        "/playback",
+        serde_json::json!({ "frames": [{ "15": 10.0 }, { "15": "up" }] }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["errors"][0]["frame"], 1);
+}
@@ -0,0 +1,115 @@
+use std::time::Duration;
+
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+};
+use bot::AppState;
+use http_body_util::BodyExt;
+use humanoid::{Joint, MotionClip, Runtime, SimulatedHumanoid};
+use tower::ServiceExt;
+
+async fn post(
+    app: axum::Router,
+    uri: &str,
+    body: serde_json::Value,
+) -> (StatusCode, serde_json::Value) {
+    let request = Request::post(uri)
+        .header("content-type", "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap();
+
+    let response = app.oneshot(request).await.unwrap();
+    let status = response.status();
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    (status, serde_json::from_slice(&body).unwrap())
+}
+
+#[tokio::test(start_paused = true)]
+async fn records_a_session_that_plays_back() {
+    let motions_dir = std::env::temp_dir().join(format!("recordings-{}", std::process::id()));
+    std::fs::create_dir_all(&motions_dir).unwrap();
+
+    let runtime = Runtime::new(SimulatedHumanoid::default());
+    let app = bot::router(AppState {
+        motions_dir: motions_dir.clone(),
+        ..AppState::new(runtime.queue())
+    });
+    let handle = runtime.run(50.0).unwrap();
+
+    let (status, body) = post(
+        app.clone(),
+        "/recording/start",
+        serde_json::json!({ "name": "session", "feedback_hz": 10.0 }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(body["recording"]["name"], "session");
+
+    let (status, _) = post(app.clone(), "/recording/start", serde_json::json!({})).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    let (status, _) = post(
+        app.clone(),
+        "/frames",
+        serde_json::json!({ "frames": [{ "15": 10.0 }, { "15": 20.0 }, { "15": 30.0 }] }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    tokio::time::sleep(Duration::from_secs(1)).await;
+
+    let (status, body) = post(app.clone(), "/recording/stop", serde_json::json!({})).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["file"], "session.json");
+    handle.stop().await.unwrap();
+
+    let clip = MotionClip::load(motions_dir.join("session.json")).unwrap();
+    let commanded: Vec<_> = clip
+        .frames
+        .iter()
+        .map(|frame| frame.joints[&Joint::LeftShoulderPitch])
+        .collect();
+    assert_eq!(commanded[..3], [10.0, 20.0, 30.0]);
+    assert!(clip.frames.len() > 40);
+    assert!((9..=11).contains(&clip.feedback.len()));
+    assert_eq!(clip.robot.as_deref(), Some("kbot"));
+
+    let (status, _) = post(app.clone(), "/recording/stop", serde_json::json!({})).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    // Sessions are never overwritten, and play back by name
+    let (status, _) = post(
+        app.clone(),
+        "/recording/start",
+        serde_json::json!({ "name": "session" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    let (status, _) = post(
+        app,
+        "/playback",
+        serde_json::json!({ "file": "session.json" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+
+    std::fs::remove_dir_all(&motions_dir).unwrap();
+}
+
+#[tokio::test]
+async fn rejects_feedback_rates_out_of_range() {
+    let runtime = Runtime::new(SimulatedHumanoid::default());
+    let app = bot::router(AppState::new(runtime.queue()));
+
+    for feedback_hz in [-1.0, 1e-30, 0.001] {
+        let (status, body) = post(
+            app.clone(),
+            "/recording/start",
+            serde_json::json!({ "name": "too-slow", "feedback_hz": feedback_hz }),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{feedback_hz}");
+        assert!(body["error"].as_str().unwrap().contains("feedback_hz"));
+    }
+    assert!(runtime.queue().recorder().status().is_none());
+}
@@ -0,0 +1,161 @@
+use std::{collections::BTreeMap, time::Duration};
+
+use bot::udp::{UdpFrame, UdpFrameError, UdpListener, UdpStatsSnapshot};
+use humanoid::{Joint, JointInfo, Runtime, SimulatedHumanoid};
+
+fn frame(seq: u32, value: f32) -> Vec<u8> {
+    UdpFrame {
+        seq,
+        timestamp_us: 1_700_000_000_000_000,
+        joints: BTreeMap::from([(Joint::LeftShoulderPitch, value)]),
+    }
+    .encode()
+    .unwrap()
+}
+
+#[test]
+fn udp_frame_round_trips() {
+    let frame = UdpFrame {
+        seq: u32::MAX,
+        timestamp_us: 42,
+        joints: BTreeMap::from([(Joint::LeftHipPitch, -3.5), (Joint::NeckYaw, 12.25)]),
+    };
+    let bytes = frame.encode().unwrap();
+    assert_eq!(bytes.len(), 14 + 2 * 5);
+    assert_eq!(UdpFrame::decode(&bytes).unwrap(), frame);
+
+    let nan = UdpFrame {
+        seq: 1,
+        timestamp_us: 42,
+        joints: BTreeMap::from([(Joint::NeckYaw, f32::NAN)]),
+    };
+    assert_eq!(
+        UdpFrame::decode(&nan.encode().unwrap()),
+        Err(UdpFrameError::NotFinite(Joint::NeckYaw))
+    );
+
+    assert_eq!(
+        UdpFrame::decode(&bytes[..20]),
+        Err(UdpFrameError::Truncated {
+            expected: 24,
+            found: 20
+        })
+    );
+}
+
+#[tokio::test]
+async fn stale_and_out_of_order_frames_are_dropped() {
+    let runtime = Runtime::new(SimulatedHumanoid::default());
+    let listener = UdpListener::bind("127.0.0.1:0", runtime.queue(), false)
+        .await
+        .unwrap();
+    let addr = listener.local_addr().unwrap();
+    let stats = listener.stats();
+    tokio::spawn(listener.run());
+
+    let sender = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    // 3 and 4 are lost, 2 arrives after 5 and is dropped along with the repeated 5
+    for (seq, value) in [(1, 10.0), (5, 50.0), (2, 20.0), (5, 55.0), (6, 60.0)] {
+        sender.send_to(&frame(seq, value), addr).await.unwrap();
+    }
+    sender.send_to(&[1, 2, 3], addr).await.unwrap();
+
+    while stats.snapshot().received < 6 {
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+
+    assert_eq!(
+        stats.snapshot(),
+        UdpStatsSnapshot {
+            received: 6,
+            applied: 3,
+            lost: 2,
+            reordered: 1,
+            duplicates: 1,
+            malformed: 1,
+            rejected: 0,
+        }
+    );
+    assert_eq!(
+        runtime.current_frame().unwrap().joints[&Joint::LeftShoulderPitch],
+        60.0
+    );
+}
+
+#[tokio::test]
+async fn restarted_senders_start_a_new_sequence() {
+    let runtime = Runtime::new(SimulatedHumanoid::default());
+    let listener = UdpListener::bind("127.0.0.1:0", runtime.queue(), false)
+        .await
+        .unwrap();
+    let addr = listener.local_addr().unwrap();
+    let stats = listener.stats();
+    tokio::spawn(listener.run());
+
+    let sender = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    // Far behind the last frame, as from a sender that restarted on the same port
+    for (seq, value) in [(5000, 10.0), (0, 20.0), (1, 30.0)] {
+        sender.send_to(&frame(seq, value), addr).await.unwrap();
+    }
+    while stats.snapshot().received < 3 {
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+    assert_eq!(stats.snapshot().applied, 3);
+    assert_eq!(stats.snapshot().reordered, 0);
+
+    // Slightly behind, but after a silence
+    tokio::time::sleep(bot::udp::SEQUENCE_TIMEOUT + Duration::from_millis(100)).await;
+    sender.send_to(&frame(0, 40.0), addr).await.unwrap();
+    while stats.snapshot().received < 4 {
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+    assert_eq!(stats.snapshot().applied, 4);
+    assert_eq!(
+        runtime.current_frame().unwrap().joints[&Joint::LeftShoulderPitch],
+        40.0
+    );
+}
+
+#[tokio::test]
+async fn invalid_frames_are_rejected() {
+    let runtime = Runtime::new(SimulatedHumanoid::default().with_joints([JointInfo::new(
+        Joint::LeftShoulderPitch,
+        -90.0,
+        90.0,
+    )]));
+    let listener = UdpListener::bind("127.0.0.1:0", runtime.queue(), true)
+        .await
+        .unwrap();
+    let addr = listener.local_addr().unwrap();
+    let stats = listener.stats();
+    tokio::spawn(listener.run());
+
+    let unsupported = UdpFrame {
+        seq: 3,
+        timestamp_us: 0,
+        joints: BTreeMap::from([(Joint::NeckYaw, 0.0)]),
+    }
+    .encode()
+    .unwrap();
+    let sender = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    sender.send_to(&frame(1, 10.0), addr).await.unwrap();
+    sender.send_to(&frame(2, 120.0), addr).await.unwrap();
+    sender.send_to(&unsupported, addr).await.unwrap();
+    sender
+        .send_to(&frame(4, f32::INFINITY), addr)
+        .await
+        .unwrap();
+
+    while stats.snapshot().received < 4 {
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+
+    let stats = stats.snapshot();
+    assert_eq!(stats.applied, 1);
+    assert_eq!(stats.rejected, 2);
+    assert_eq!(stats.malformed, 1);
+    assert_eq!(
+        runtime.current_frame().unwrap().joints[&Joint::LeftShoulderPitch],
+        10.0
+    );
+}
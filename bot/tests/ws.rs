@@ -0,0 +1,75 @@
+use bot::ws::{AckStatus, FrameAck};
+use bot::AppState;
+use futures_util::{SinkExt, StreamExt};
+use humanoid::{Joint, JointInfo, Runtime, SimulatedHumanoid};
+use tokio::net::TcpStream;
+use tokio_tungstenite::{tungstenite::Message, MaybeTlsStream, WebSocketStream};
+
+async fn send(
+    socket: &mut WebSocketStream<MaybeTlsStream<TcpStream>>,
+    message: serde_json::Value,
+) -> FrameAck {
+    socket
+        .send(Message::Text(message.to_string()))
+        .await
+        .unwrap();
+    let reply = socket.next().await.unwrap().unwrap();
+    serde_json::from_str(reply.to_text().unwrap()).unwrap()
+}
+
+#[tokio::test]
+async fn frames_are_streamed_and_acked() {
+    let sim =
+        SimulatedHumanoid::default().with_joints([JointInfo::new(Joint::LeftHipPitch, 0.0, 90.0)]);
+    let runtime = Runtime::new(sim);
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let app = bot::router(AppState::new(runtime.queue()));
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+    let (mut socket, _) = tokio_tungstenite::connect_async(format!("ws://{}/ws", addr))
+        .await
+        .unwrap();
+
+    let sent_at = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs_f64()
+        * 1000.0;
+    let first = send(
+        &mut socket,
+        serde_json::json!({ "seq": 1, "sent_at": sent_at, "joints": { "1": 10.0 } }),
+    )
+    .await;
+    assert_eq!(first.seq, Some(1));
+    assert_eq!(first.status, AckStatus::Queued);
+    assert!(first.latency_ms.unwrap() >= 0.0);
+    assert_eq!(
+        runtime.current_frame().unwrap().joints[&Joint::LeftHipPitch],
+        10.0
+    );
+
+    let pushed = send(
+        &mut socket,
+        serde_json::json!({ "seq": 2, "mode": "push", "joints": { "1": 20.0 } }),
+    )
+    .await;
+    assert_eq!(pushed.status, AckStatus::Queued);
+    assert_eq!(pushed.latency_ms, None);
+    assert_eq!(pushed.queue_len, 1);
+
+    // Joint 28 is the neck yaw
+    let rejected = send(
+        &mut socket,
+        serde_json::json!({ "seq": 3, "joints": { "28": 5.0 } }),
+    )
+    .await;
+    assert_eq!(rejected.seq, Some(3));
+    assert_eq!(rejected.status, AckStatus::Rejected);
+    assert!(rejected.error.is_some());
+
+    let malformed = send(&mut socket, serde_json::json!({ "joints": {} })).await;
+    assert_eq!(malformed.seq, None);
+    assert_eq!(malformed.status, AckStatus::Rejected);
+}
@@ -0,0 +1,253 @@
+use std::{collections::BTreeMap, path::Path, time::Duration};
+
+use serde::{Deserialize, Serialize};
+use tokio::time::Instant;
+
+use crate::{read_config, EulerAngles, Joint, JointInfo};
+
+/// PID gains for one axis of a [`BalanceController`], in degrees of correction per degree of
+/// tilt.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AxisGains {
+    pub kp: f32,
+    pub ki: f32,
+    pub kd: f32,
+}
+
+impl Default for AxisGains {
+    fn default() -> Self {
+        Self {
+            kp: 0.5,
+            ki: 0.0,
+            kd: 0.05,
+        }
+    }
+}
+
+/// Tuning for [`BalanceController`].
+///
+/// ```toml
+/// deadband = 1.0
+/// max_correction = 15.0
+///
+/// [pitch]
+/// kp = 0.5
+/// kd = 0.05
+///
+/// [pitch_joints]
+/// LeftHipPitch = 0.5
+/// LeftAnklePitch = 0.5
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BalanceConfig {
+    pub pitch: AxisGains,
+    pub roll: AxisGains,
+    /// Tilt in degrees that is left uncorrected, so that sensor noise does not move the legs.
+    pub deadband: f32,
+    /// Largest offset added to any joint, in degrees.
+    pub max_correction: f32,
+    /// Joints that correct pitch, with the share of the correction each one takes. A negative
+    /// share moves the joint the other way, for joints mounted in reverse.
+    pub pitch_joints: BTreeMap<Joint, f32>,
+    pub roll_joints: BTreeMap<Joint, f32>,
+}
+
+impl Default for BalanceConfig {
+    fn default() -> Self {
+        Self {
+            pitch: AxisGains::default(),
+            roll: AxisGains::default(),
+            deadband: 1.0,
+            max_correction: 15.0,
+            pitch_joints: [
+                (Joint::LeftHipPitch, 0.5),
+                (Joint::RightHipPitch, 0.5),
+                (Joint::LeftAnklePitch, 0.5),
+                (Joint::RightAnklePitch, 0.5),
+            ]
+            .into_iter()
+            .collect(),
+            // The Zeroth's hip roll servos
+            roll_joints: [(Joint::LeftKneeYaw, 1.0), (Joint::RightKneeYaw, 1.0)]
+                .into_iter()
+                .collect(),
+        }
+    }
+}
+
+impl BalanceConfig {
+    /// Load gains from a TOML or JSON file.
+    pub fn load(path: impl AsRef<Path>) -> eyre::Result<Self> {
+        let config: Self = read_config(path)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Parse gains in TOML, e.g. ones embedded with `include_str!`.
+    pub fn from_toml(contents: &str) -> eyre::Result<Self> {
+        let config: Self = toml::from_str(contents)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn validate(&self) -> eyre::Result<()> {
+        for (axis, gains) in [("pitch", self.pitch), ("roll", self.roll)] {
+            for (name, gain) in [("kp", gains.kp), ("ki", gains.ki), ("kd", gains.kd)] {
+                eyre::ensure!(
+                    gain.is_finite() && gain >= 0.0,
+                    "{}.{} must not be negative, got {}",
+                    axis,
+                    name,
+                    gain
+                );
+            }
+        }
+        eyre::ensure!(self.deadband >= 0.0, "deadband must not be negative");
+        eyre::ensure!(self.max_correction > 0.0, "max_correction must be positive");
+        Ok(())
+    }
+
+    /// Fail unless every joint the controller moves is one of `supported`.
+    pub fn check_joints(&self, supported: &[JointInfo]) -> eyre::Result<()> {
+        let joints: Vec<_> = self.joints().collect();
+        crate::check_joints(supported, &joints)
+            .map_err(|e| eyre::eyre!("Balance joints are not supported by this robot: {}", e))
+    }
+
+    /// Every joint the controller moves.
+    pub fn joints(&self) -> impl Iterator<Item = Joint> + '_ {
+        self.pitch_joints
+            .keys()
+            .chain(self.roll_joints.keys())
+            .copied()
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Axis {
+    integral: f32,
+    last_error: Option<f32>,
+}
+
+impl Axis {
+    /// The correction for a measured `tilt`, where the target is upright.
+    fn update(&mut self, gains: AxisGains, tilt: f32, deadband: f32, limit: f32, dt: f32) -> f32 {
+        let error = if tilt.abs() <= deadband {
+            0.0
+        } else {
+            -(tilt - deadband.copysign(tilt))
+        };
+
+        let mut derivative = 0.0;
+        if dt > 0.0 {
+            if gains.ki > 0.0 {
+                // Never let the integral term alone exceed the correction limit
+                let max_integral = limit / gains.ki;
+                self.integral = (self.integral + error * dt).clamp(-max_integral, max_integral);
+            }
+            if let Some(last_error) = self.last_error {
+                derivative = (error - last_error) / dt;
+            }
+        }
+        self.last_error = Some(error);
+
+        gains.kp * error + gains.ki * self.integral + gains.kd * derivative
+    }
+}
+
+/// Keeps the torso upright while standing by offsetting hip and ankle joints against the tilt
+/// reported by the IMU.
+///
+/// Frames sent to the robot go through [`BalanceController::apply`], which remembers the pose
+/// they ask for and adds the current corrections. Each [`BalanceController::update`] computes new
+/// corrections from the latest orientation and returns the corrected pose to send.
+#[derive(Debug, Clone)]
+pub struct BalanceController {
+    config: BalanceConfig,
+    pitch: Axis,
+    roll: Axis,
+    pose: BTreeMap<Joint, f32>,
+    offsets: BTreeMap<Joint, f32>,
+    last_update: Option<Instant>,
+}
+
+impl BalanceController {
+    pub fn new(config: BalanceConfig) -> Self {
+        Self {
+            config,
+            pitch: Axis::default(),
+            roll: Axis::default(),
+            pose: BTreeMap::new(),
+            offsets: BTreeMap::new(),
+            last_update: None,
+        }
+    }
+
+    pub fn config(&self) -> &BalanceConfig {
+        &self.config
+    }
+
+    /// Offsets currently added to each balance joint.
+    pub fn offsets(&self) -> &BTreeMap<Joint, f32> {
+        &self.offsets
+    }
+
+    /// Remember the commanded values of the balance joints in `joints` and add the current
+    /// corrections to them.
+    pub fn apply(&mut self, mut joints: BTreeMap<Joint, f32>) -> BTreeMap<Joint, f32> {
+        for (joint, value) in joints.iter_mut() {
+            if self.config.joints().any(|j| j == *joint) {
+                self.pose.insert(*joint, *value);
+                *value += self.offsets.get(joint).copied().unwrap_or_default();
+            }
+        }
+        joints
+    }
+
+    /// Correct for `orientation`, returning the balance joints to send. Joints that have never
+    /// been commanded are left alone, as there is no pose to correct.
+    pub fn update(&mut self, orientation: EulerAngles) -> BTreeMap<Joint, f32> {
+        let now = Instant::now();
+        let dt = self
+            .last_update
+            .map_or(Duration::ZERO, |last| now - last)
+            .as_secs_f32();
+        self.last_update = Some(now);
+
+        let config = &self.config;
+        let limit = config.max_correction;
+        let pitch = self
+            .pitch
+            .update(config.pitch, orientation.pitch, config.deadband, limit, dt);
+        let roll = self
+            .roll
+            .update(config.roll, orientation.roll, config.deadband, limit, dt);
+
+        let mut offsets: BTreeMap<Joint, f32> = BTreeMap::new();
+        for (joint, share) in &config.pitch_joints {
+            *offsets.entry(*joint).or_default() += share * pitch;
+        }
+        for (joint, share) in &config.roll_joints {
+            *offsets.entry(*joint).or_default() += share * roll;
+        }
+        for offset in offsets.values_mut() {
+            *offset = offset.clamp(-limit, limit);
+        }
+        self.offsets = offsets;
+
+        self.pose
+            .iter()
+            .map(|(joint, value)| (*joint, value + self.offsets[joint]))
+            .collect()
+    }
+
+    /// Forget the corrections and controller state, e.g. after the robot was picked up.
+    pub fn reset(&mut self) {
+        self.pitch = Axis::default();
+        self.roll = Axis::default();
+        self.offsets.clear();
+        self.last_update = None;
+    }
+}
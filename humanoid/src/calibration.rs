@@ -0,0 +1,196 @@
+use std::{
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{read_config, write_config, Humanoid};
+
+/// Format version written by [`Calibration::save`]. Files with any other version are rejected.
+pub const CALIBRATION_VERSION: u32 = 1;
+
+/// What a saved calibration was measured on.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RobotIdentity {
+    /// Robot model, usually the name of its joint profile.
+    pub model: String,
+    /// Every servo or actuator id found on the robot, in increasing order.
+    pub servo_ids: Vec<i32>,
+}
+
+impl std::fmt::Display for RobotIdentity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} with servos {:?}", self.model, self.servo_ids)
+    }
+}
+
+/// Calibration result for a single servo. Limits are only present for robots that report them.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ServoCalibration {
+    pub id: i32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max: Option<f32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Calibration {
+    pub version: u32,
+    /// Seconds since the Unix epoch.
+    pub created_at: u64,
+    pub robot: RobotIdentity,
+    pub servos: Vec<ServoCalibration>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CalibrationError {
+    UnsupportedVersion {
+        found: u32,
+    },
+    WrongRobot {
+        expected: RobotIdentity,
+        found: RobotIdentity,
+    },
+}
+
+impl std::fmt::Display for CalibrationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CalibrationError::UnsupportedVersion { found } => write!(
+                f,
+                "Calibration version {} is not supported, expected {}",
+                found, CALIBRATION_VERSION
+            ),
+            CalibrationError::WrongRobot { expected, found } => write!(
+                f,
+                "Calibration was taken on {}, but connected to {}",
+                found, expected
+            ),
+        }
+    }
+}
+
+impl std::error::Error for CalibrationError {}
+
+#[derive(Deserialize)]
+struct CalibrationHeader {
+    version: u32,
+}
+
+impl Calibration {
+    pub fn new(robot: RobotIdentity, servos: Vec<ServoCalibration>) -> Self {
+        Self {
+            version: CALIBRATION_VERSION,
+            created_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs()),
+            robot,
+            servos,
+        }
+    }
+
+    /// Load a calibration saved with [`Calibration::save`], rejecting other format versions
+    /// with [`CalibrationError::UnsupportedVersion`].
+    pub fn load(path: impl AsRef<Path>) -> eyre::Result<Self> {
+        let path = path.as_ref();
+
+        // Check the version first so that a newer layout is reported as such
+        let header: CalibrationHeader = read_config(path)?;
+        if header.version != CALIBRATION_VERSION {
+            return Err(CalibrationError::UnsupportedVersion {
+                found: header.version,
+            }
+            .into());
+        }
+
+        read_config(path)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> eyre::Result<()> {
+        write_config(path, self)
+    }
+
+    pub fn servo(&self, id: i32) -> Option<&ServoCalibration> {
+        self.servos.iter().find(|servo| servo.id == id)
+    }
+
+    /// Check that this calibration was taken on `robot`.
+    pub fn check_robot(&self, robot: &RobotIdentity) -> Result<(), CalibrationError> {
+        if &self.robot == robot {
+            Ok(())
+        } else {
+            Err(CalibrationError::WrongRobot {
+                expected: robot.clone(),
+                found: self.robot.clone(),
+            })
+        }
+    }
+}
+
+/// A [`Humanoid`] whose calibration can be saved and restored instead of probed on every start.
+pub trait Calibrate: Humanoid {
+    /// Identify the connected robot, to check that a saved calibration belongs to it.
+    fn identity(&self) -> impl std::future::Future<Output = eyre::Result<RobotIdentity>> + Send;
+
+    /// The calibration found by the last [`Humanoid::calibrate`], or the one restored.
+    fn calibration(&self) -> Option<Calibration>;
+
+    /// Use `calibration` in place of [`Humanoid::calibrate`].
+    fn restore_calibration(
+        &mut self,
+        calibration: Calibration,
+    ) -> impl std::future::Future<Output = eyre::Result<()>> + Send;
+
+    /// Whether live readings from the robot still agree with `calibration`.
+    fn verify_calibration(
+        &self,
+        calibration: &Calibration,
+    ) -> impl std::future::Future<Output = eyre::Result<bool>> + Send;
+}
+
+/// Restore the calibration saved at `path` if it belongs to this robot and still agrees with
+/// it, otherwise calibrate and save the result. `force` always calibrates.
+pub async fn load_or_calibrate<H: Calibrate>(
+    robot: &mut H,
+    path: impl AsRef<Path>,
+    force: bool,
+) -> eyre::Result<Calibration> {
+    let path = path.as_ref();
+
+    if !force && path.exists() {
+        match restore_saved(robot, path).await {
+            Ok(Some(calibration)) => {
+                println!("Restored calibration from {}", path.display());
+                return Ok(calibration);
+            }
+            Ok(None) => println!("Saved calibration no longer matches the robot"),
+            Err(e) => println!("Ignoring saved calibration: {}", e),
+        }
+    }
+
+    robot.calibrate().await?;
+    let calibration = robot
+        .calibration()
+        .ok_or_else(|| eyre::eyre!("Robot did not produce a calibration"))?;
+    calibration.save(path)?;
+    println!("Saved calibration to {}", path.display());
+
+    Ok(calibration)
+}
+
+async fn restore_saved<H: Calibrate>(
+    robot: &mut H,
+    path: &Path,
+) -> eyre::Result<Option<Calibration>> {
+    let calibration = Calibration::load(path)?;
+    calibration.check_robot(&robot.identity().await?)?;
+
+    if !robot.verify_calibration(&calibration).await? {
+        return Ok(None);
+    }
+
+    robot.restore_calibration(calibration.clone()).await?;
+    Ok(Some(calibration))
+}
@@ -0,0 +1,320 @@
+use std::{
+    collections::BTreeMap,
+    path::Path,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    parse_joint, read_config, write_config, Frame, Interpolation, Joint, Keyframe, Trajectory,
+};
+
+/// Format version written by [`MotionClip::save`]. Clips with any other version are rejected.
+pub const CLIP_VERSION: u32 = 1;
+
+/// Latest frame time [`MotionClip::validate`] accepts, in seconds, a day.
+pub const MAX_CLIP_SECS: f32 = 24.0 * 60.0 * 60.0;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AngleUnit {
+    #[default]
+    Degrees,
+    Radians,
+}
+
+impl AngleUnit {
+    pub fn to_degrees(self, value: f32) -> f32 {
+        match self {
+            AngleUnit::Degrees => value,
+            AngleUnit::Radians => value.to_degrees(),
+        }
+    }
+}
+
+/// One frame of a [`MotionClip`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ClipFrame {
+    /// Seconds since the start of the clip.
+    pub time: f32,
+    pub joints: BTreeMap<Joint, f32>,
+}
+
+/// One IMU reading in a [`MotionClip`]. Each field is `None` if the IMU did not report it.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ImuSample {
+    /// Seconds since the start of the clip.
+    pub time: f32,
+    /// Acceleration in m/s², `[x, y, z]`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub accel: Option<[f32; 3]>,
+    /// Angular velocity in degrees per second, `[x, y, z]`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gyro: Option<[f32; 3]>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mag: Option<[f32; 3]>,
+    /// Orientation, `[x, y, z, w]`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quaternion: Option<[f32; 4]>,
+}
+
+/// A recorded or authored motion, with timing and the metadata needed to play it back.
+///
+/// ```json
+/// {
+///   "version": 1,
+///   "name": "wave",
+///   "robot": "zeroth-01",
+///   "units": "degrees",
+///   "fps": 30.0,
+///   "created_at": 1734567890,
+///   "frames": [
+///     { "time": 0.0, "joints": { "LeftShoulderPitch": 0.0 } },
+///     { "time": 0.5, "joints": { "LeftShoulderPitch": 45.0 } }
+///   ]
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MotionClip {
+    pub version: u32,
+    #[serde(default)]
+    pub name: String,
+    /// Robot the clip was made for, usually the name of its joint profile.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub robot: Option<String>,
+    #[serde(default)]
+    pub units: AngleUnit,
+    /// Rate the clip was recorded at. Informational, playback follows the frame times.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fps: Option<f32>,
+    /// Seconds since the Unix epoch.
+    #[serde(default)]
+    pub created_at: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub frames: Vec<ClipFrame>,
+    /// Positions read back from the robot with [`crate::Humanoid::get_joint`] while recording,
+    /// in the robot's own units. Kept for review, only `frames` are played back.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub feedback: Vec<ClipFrame>,
+    /// IMU readings, e.g. from an imported krec episode. Not played back.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub imu: Vec<ImuSample>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ClipError {
+    UnsupportedVersion { found: u32 },
+}
+
+impl std::fmt::Display for ClipError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ClipError::UnsupportedVersion { found } => write!(
+                f,
+                "Motion clip version {} is not supported, expected {}",
+                found, CLIP_VERSION
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ClipError {}
+
+#[derive(Deserialize)]
+struct ClipHeader {
+    version: u32,
+}
+
+fn check_version(header: ClipHeader) -> Result<(), ClipError> {
+    if header.version == CLIP_VERSION {
+        Ok(())
+    } else {
+        Err(ClipError::UnsupportedVersion {
+            found: header.version,
+        })
+    }
+}
+
+impl MotionClip {
+    /// An empty clip in degrees, created now.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            version: CLIP_VERSION,
+            name: name.into(),
+            robot: None,
+            units: AngleUnit::Degrees,
+            fps: None,
+            created_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs()),
+            description: None,
+            frames: Vec::new(),
+            feedback: Vec::new(),
+            imu: Vec::new(),
+        }
+    }
+
+    /// A clip of `frames` spaced `1 / fps` seconds apart.
+    pub fn from_frames(
+        name: impl Into<String>,
+        frames: Vec<Frame>,
+        fps: f32,
+    ) -> eyre::Result<Self> {
+        eyre::ensure!(
+            fps.is_finite() && fps > 0.0,
+            "fps must be positive, got {}",
+            fps
+        );
+
+        let mut clip = Self::new(name);
+        clip.fps = Some(fps);
+        clip.frames = frames
+            .into_iter()
+            .enumerate()
+            .map(|(i, frame)| ClipFrame {
+                time: i as f32 / fps,
+                joints: frame.joints,
+            })
+            .collect();
+        Ok(clip)
+    }
+
+    /// Upgrade a legacy motion, a JSON array of objects of [`Joint`] ids (or names) to degrees,
+    /// assuming it was recorded at `fps`.
+    pub fn from_legacy(
+        name: impl Into<String>,
+        legacy: &serde_json::Value,
+        fps: f32,
+    ) -> eyre::Result<Self> {
+        let legacy: Vec<BTreeMap<String, f32>> = serde_json::from_value(legacy.clone())?;
+
+        let frames = legacy
+            .into_iter()
+            .enumerate()
+            .map(|(i, joints)| {
+                let joints = joints
+                    .into_iter()
+                    .map(|(key, value)| match parse_joint(&key) {
+                        Some(joint) => Ok((joint, value)),
+                        None => Err(eyre::eyre!("Frame {}: unknown joint {:?}", i, key)),
+                    })
+                    .collect::<eyre::Result<_>>()?;
+                Ok(Frame { joints })
+            })
+            .collect::<eyre::Result<_>>()?;
+
+        Self::from_frames(name, frames, fps)
+    }
+
+    /// Parse a clip from JSON, rejecting other format versions with
+    /// [`ClipError::UnsupportedVersion`].
+    pub fn from_json(contents: &str) -> eyre::Result<Self> {
+        check_version(serde_json::from_str(contents)?)?;
+        let clip: Self = serde_json::from_str(contents)?;
+        clip.validate()?;
+        Ok(clip)
+    }
+
+    /// Load a clip from a JSON or TOML file.
+    pub fn load(path: impl AsRef<Path>) -> eyre::Result<Self> {
+        let path = path.as_ref();
+
+        // Check the version first so that a newer layout is reported as such
+        check_version(read_config(path)?)?;
+        let clip: Self = read_config(path)?;
+        clip.validate()?;
+        Ok(clip)
+    }
+
+    /// Load a clip, or upgrade a legacy JSON array of frames recorded at `legacy_fps`.
+    pub fn load_any(path: impl AsRef<Path>, legacy_fps: f32) -> eyre::Result<Self> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| eyre::eyre!("Failed to read {}: {}", path.display(), e))?;
+
+        let json: serde_json::Value = match serde_json::from_str(&contents) {
+            Ok(json) => json,
+            // Not JSON, so not a legacy array either
+            Err(_) => return Self::load(path),
+        };
+        if json.is_array() {
+            let name = path.file_stem().unwrap_or_default().to_string_lossy();
+            Self::from_legacy(name, &json, legacy_fps)
+        } else {
+            Self::from_json(&contents)
+        }
+    }
+
+    /// Save as TOML for a `.toml` path, or JSON otherwise.
+    pub fn save(&self, path: impl AsRef<Path>) -> eyre::Result<()> {
+        write_config(path, self)
+    }
+
+    pub fn validate(&self) -> eyre::Result<()> {
+        check_version(ClipHeader {
+            version: self.version,
+        })?;
+        for (i, frame) in self.frames.iter().enumerate() {
+            eyre::ensure!(
+                (0.0..=MAX_CLIP_SECS).contains(&frame.time),
+                "Frame {}: time {} is not within 0..={} seconds",
+                i,
+                frame.time,
+                MAX_CLIP_SECS
+            );
+        }
+        eyre::ensure!(
+            self.frames.windows(2).all(|w| w[0].time < w[1].time),
+            "Frame times must be strictly increasing"
+        );
+        Ok(())
+    }
+
+    /// Time of the last frame, saturating for a clip that fails [`MotionClip::validate`].
+    pub fn duration(&self) -> Duration {
+        self.frames.last().map_or(Duration::ZERO, |frame| {
+            Duration::try_from_secs_f32(frame.time).unwrap_or(Duration::MAX)
+        })
+    }
+
+    /// Append a frame `time` after the start of the clip.
+    pub fn push(&mut self, time: Duration, joints: BTreeMap<Joint, f32>) {
+        self.frames.push(ClipFrame {
+            time: time.as_secs_f32(),
+            joints,
+        });
+    }
+
+    /// The frames in degrees, as sent to the robot.
+    pub fn frames(&self) -> Vec<Frame> {
+        self.frames
+            .iter()
+            .map(|frame| Frame {
+                joints: frame
+                    .joints
+                    .iter()
+                    .map(|(joint, value)| (*joint, self.units.to_degrees(*value)))
+                    .collect(),
+            })
+            .collect()
+    }
+
+    /// A trajectory through every frame at its recorded time.
+    pub fn trajectory(&self, interpolation: Interpolation) -> eyre::Result<Trajectory> {
+        let keyframes = self
+            .frames
+            .iter()
+            .zip(self.frames())
+            .map(|(clip_frame, frame)| {
+                let time = Duration::try_from_secs_f32(clip_frame.time).map_err(|e| {
+                    eyre::eyre!("Frame time {} is out of range: {}", clip_frame.time, e)
+                })?;
+                Ok(Keyframe { time, frame })
+            })
+            .collect::<eyre::Result<_>>()?;
+
+        Trajectory::new(keyframes, interpolation)
+    }
+}
@@ -0,0 +1,37 @@
+use std::path::Path;
+
+use serde::{de::DeserializeOwned, Serialize};
+
+fn is_toml(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "toml")
+}
+
+/// Read a `.toml` file, or JSON for any other extension.
+pub fn read_config<T: DeserializeOwned>(path: impl AsRef<Path>) -> eyre::Result<T> {
+    let path = path.as_ref();
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| eyre::eyre!("Failed to read {}: {}", path.display(), e))?;
+
+    let value = if is_toml(path) {
+        toml::from_str(&contents)?
+    } else {
+        serde_json::from_str(&contents)?
+    };
+
+    Ok(value)
+}
+
+/// Write `value` as TOML for a `.toml` path, or pretty-printed JSON otherwise.
+pub fn write_config<T: Serialize>(path: impl AsRef<Path>, value: &T) -> eyre::Result<()> {
+    let path = path.as_ref();
+
+    let contents = if is_toml(path) {
+        toml::to_string_pretty(value)?
+    } else {
+        serde_json::to_string_pretty(value)?
+    };
+
+    std::fs::write(path, contents)
+        .map_err(|e| eyre::eyre!("Failed to write {}: {}", path.display(), e))?;
+    Ok(())
+}
@@ -0,0 +1,305 @@
+use std::{
+    collections::BTreeMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use tokio::{
+    sync::watch,
+    task::JoinHandle,
+    time::{Instant, MissedTickBehavior},
+};
+
+use crate::{Frame, Humanoid, Runtime};
+
+/// How often an unchanged frame is re-sent so the robot knows the controller is still alive.
+pub const DEFAULT_KEEPALIVE: Duration = Duration::from_secs(1);
+
+/// Upper bounds of the [`ControlLoopStats::step_latency`] buckets, in seconds.
+pub const STEP_LATENCY_BUCKETS: [f64; 10] = [
+    0.0005, 0.001, 0.0025, 0.005, 0.01, 0.02, 0.05, 0.1, 0.25, 1.0,
+];
+
+/// Settings for [`Runtime::run_with`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ControlLoop {
+    /// Ticks per second.
+    pub rate_hz: f32,
+    /// Re-send the current frame after this long even if it has not changed.
+    pub keepalive: Duration,
+}
+
+impl ControlLoop {
+    pub fn new(rate_hz: f32) -> Self {
+        Self {
+            rate_hz,
+            keepalive: DEFAULT_KEEPALIVE,
+        }
+    }
+
+    /// Time between ticks. Fails unless `rate_hz` is positive and finite, with a period of at
+    /// least a nanosecond.
+    pub fn period(&self) -> eyre::Result<Duration> {
+        eyre::ensure!(
+            self.rate_hz.is_finite() && self.rate_hz > 0.0,
+            "Control rate must be positive, got {} Hz",
+            self.rate_hz
+        );
+        match Duration::try_from_secs_f32(1.0 / self.rate_hz) {
+            Ok(period) if !period.is_zero() => Ok(period),
+            _ => eyre::bail!("Control rate of {} Hz is out of range", self.rate_hz),
+        }
+    }
+}
+
+/// Durations counted into [`STEP_LATENCY_BUCKETS`].
+#[derive(Debug, Default)]
+pub struct LatencyHistogram {
+    buckets: [AtomicU64; STEP_LATENCY_BUCKETS.len()],
+    count: AtomicU64,
+    sum_micros: AtomicU64,
+}
+
+impl LatencyHistogram {
+    pub fn observe(&self, latency: Duration) {
+        let secs = latency.as_secs_f64();
+        if let Some(i) = STEP_LATENCY_BUCKETS.iter().position(|&le| secs <= le) {
+            self.buckets[i].fetch_add(1, Ordering::Relaxed);
+        }
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.sum_micros
+            .fetch_add(latency.as_micros() as u64, Ordering::Relaxed);
+    }
+
+    /// Each bucket's upper bound with the number of durations at or under it.
+    pub fn buckets(&self) -> Vec<(f64, u64)> {
+        let mut total = 0;
+        STEP_LATENCY_BUCKETS
+            .iter()
+            .zip(&self.buckets)
+            .map(|(&le, count)| {
+                total += count.load(Ordering::Relaxed);
+                (le, total)
+            })
+            .collect()
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count.load(Ordering::Relaxed)
+    }
+
+    pub fn sum(&self) -> Duration {
+        Duration::from_micros(self.sum_micros.load(Ordering::Relaxed))
+    }
+}
+
+/// Counters kept by a running control loop.
+#[derive(Debug, Default)]
+pub struct ControlLoopStats {
+    ticks: AtomicU64,
+    commands: AtomicU64,
+    missed_deadlines: AtomicU64,
+    errors: AtomicU64,
+    step_latency: LatencyHistogram,
+}
+
+impl ControlLoopStats {
+    pub fn ticks(&self) -> u64 {
+        self.ticks.load(Ordering::Relaxed)
+    }
+
+    /// Number of frames actually sent to the robot.
+    pub fn commands(&self) -> u64 {
+        self.commands.load(Ordering::Relaxed)
+    }
+
+    /// Number of ticks that finished after the next tick was due.
+    pub fn missed_deadlines(&self) -> u64 {
+        self.missed_deadlines.load(Ordering::Relaxed)
+    }
+
+    /// Number of frames the robot failed to apply, ticks it failed to stabilize, or failures to
+    /// switch torque around a halt.
+    pub fn errors(&self) -> u64 {
+        self.errors.load(Ordering::Relaxed)
+    }
+
+    /// Time from each tick to the end of its work, for ticks that weren't halted.
+    pub fn step_latency(&self) -> &LatencyHistogram {
+        &self.step_latency
+    }
+}
+
+/// Handle to a control loop started with [`Runtime::run`].
+///
+/// Dropping the handle stops the loop after its current tick.
+pub struct RunHandle {
+    stop: watch::Sender<bool>,
+    task: JoinHandle<()>,
+    stats: Arc<ControlLoopStats>,
+}
+
+impl RunHandle {
+    /// The loop's counters, which outlive it and can be shared, e.g. with `GET /metrics`.
+    pub fn stats(&self) -> &Arc<ControlLoopStats> {
+        &self.stats
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.task.is_finished()
+    }
+
+    /// Stop the loop and wait for the tick in progress to finish.
+    pub async fn stop(self) -> eyre::Result<()> {
+        // The loop may already have exited, in which case there is no receiver
+        let _ = self.stop.send(true);
+        self.task.await?;
+        Ok(())
+    }
+}
+
+impl<H: Humanoid> Runtime<H> {
+    /// Drive the robot at a fixed `rate_hz`, see [`Runtime::run_with`].
+    pub fn run(&self, rate_hz: f32) -> eyre::Result<RunHandle> {
+        self.run_with(ControlLoop::new(rate_hz))
+    }
+
+    /// Spawn a task that executes queued frames at a fixed rate.
+    ///
+    /// Each tick runs the current frame and advances the queue like [`Runtime::step`], but a
+    /// frame is only sent to the robot when it differs from the last one sent, the keepalive
+    /// interval has elapsed or the robot is still [`Humanoid::short_of_target`]. While a [`crate::Trajectory`] is playing, every tick sends a new
+    /// setpoint sampled from it instead.
+    ///
+    /// Every tick also calls [`Humanoid::stabilize`], which balances robots that have a
+    /// [`crate::BalanceController`] and does nothing otherwise.
+    ///
+    /// While the queue's [`crate::Recorder`] is recording, every tick's frame is recorded along
+    /// with a periodic readback of the commanded joints.
+    ///
+    /// While the queue is halted, see [`crate::FrameQueue::halt`], the robot's torque is
+    /// disabled and nothing is sent. Torque is enabled again once the halt is reset, see
+    /// [`crate::FrameQueue::subscribe_resumes`].
+    ///
+    /// Fails without starting the loop if `config` has no valid [`ControlLoop::period`].
+    pub fn run_with(&self, config: ControlLoop) -> eyre::Result<RunHandle> {
+        let period = config.period()?;
+        let (stop, mut stopped) = watch::channel(false);
+        let stats = Arc::new(ControlLoopStats::default());
+
+        let mut runtime = self.clone();
+        let task_stats = stats.clone();
+        let task = tokio::spawn(async move {
+            let mut interval = tokio::time::interval(period);
+            interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+            let mut last_sent: Option<(Frame, Instant)> = None;
+            let mut limp = false;
+
+            loop {
+                let deadline = tokio::select! {
+                    _ = stopped.changed() => break,
+                    tick = interval.tick() => tick + period,
+                };
+                task_stats.ticks.fetch_add(1, Ordering::Relaxed);
+
+                let queue = runtime.queue();
+                if queue.halted().is_some() {
+                    // Frames sent while halted must not run after the reset
+                    queue.drop_frames();
+                    if !limp {
+                        match runtime.lock().await.set_torque_enabled(false).await {
+                            Ok(()) => limp = true,
+                            Err(e) => {
+                                task_stats.errors.fetch_add(1, Ordering::Relaxed);
+                                println!("Failed to disable torque: {:?}", e);
+                            }
+                        }
+                    }
+                    continue;
+                }
+                if limp {
+                    match runtime.lock().await.set_torque_enabled(true).await {
+                        Ok(()) => {
+                            limp = false;
+                            last_sent = None;
+                            queue.resumed();
+                        }
+                        Err(e) => {
+                            task_stats.errors.fetch_add(1, Ordering::Relaxed);
+                            println!("Failed to enable torque: {:?}", e);
+                            continue;
+                        }
+                    }
+                }
+
+                // An active trajectory supplies a new setpoint every tick and holds the queue
+                let (setpoint, interpolated) = match runtime.queue().trajectory_setpoint() {
+                    Some(frame) => (Some(frame), true),
+                    None => (runtime.current_frame(), false),
+                };
+
+                if let Some(frame) = setpoint {
+                    let queue = runtime.queue();
+                    let recorder = queue.recorder();
+                    recorder.record_command(&frame);
+                    if let Some(time) = recorder.feedback_due() {
+                        let robot = runtime.lock().await;
+                        let mut joints = BTreeMap::new();
+                        for &joint in frame.joints.keys() {
+                            // Joints that cannot be read back are left out of the sample
+                            if let Ok(position) = robot.get_joint(joint).await {
+                                joints.insert(joint, position.position);
+                            }
+                        }
+                        recorder.record_feedback(time, joints);
+                    }
+
+                    let due = match &last_sent {
+                        Some((sent, at)) => {
+                            sent != &frame
+                                || at.elapsed() >= config.keepalive
+                                || runtime.lock().await.short_of_target()
+                        }
+                        None => true,
+                    };
+
+                    if due {
+                        let result = runtime.lock().await.set_joints(frame.joints.clone()).await;
+                        match result {
+                            Ok(()) => {
+                                task_stats.commands.fetch_add(1, Ordering::Relaxed);
+                                last_sent = Some((frame, Instant::now()));
+                            }
+                            Err(e) => {
+                                task_stats.errors.fetch_add(1, Ordering::Relaxed);
+                                println!("Failed to send frame: {:?}", e);
+                            }
+                        }
+                    }
+
+                    if !interpolated {
+                        runtime.advance();
+                    }
+                }
+
+                if let Err(e) = runtime.lock().await.stabilize().await {
+                    task_stats.errors.fetch_add(1, Ordering::Relaxed);
+                    println!("Failed to stabilize: {:?}", e);
+                }
+
+                let now = Instant::now();
+                task_stats.step_latency.observe(now - (deadline - period));
+                if now > deadline {
+                    task_stats.missed_deadlines.fetch_add(1, Ordering::Relaxed);
+                    println!("Control loop missed its deadline by {:?}", now - deadline);
+                }
+            }
+        });
+
+        Ok(RunHandle { stop, task, stats })
+    }
+}
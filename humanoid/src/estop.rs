@@ -0,0 +1,161 @@
+use std::{future::Future, pin::Pin, sync::Arc, time::Duration};
+
+use tokio::{
+    sync::{
+        broadcast::{self, error::RecvError},
+        watch,
+    },
+    task::JoinHandle,
+    time::{Instant, MissedTickBehavior},
+};
+
+use crate::{Halt, Humanoid, Runtime};
+
+/// Shortest interval at which [`Runtime::watch_frames`] checks for frames.
+const WATCHDOG_MIN_PERIOD: Duration = Duration::from_millis(10);
+
+type CutoffFuture = Pin<Box<dyn Future<Output = eyre::Result<()>> + Send>>;
+
+/// Disables torque on a robot over a client of its own, so that it works even while a command
+/// holds the [`Runtime`]'s lock on the robot. See [`Humanoid::torque_cutoff`].
+#[derive(Clone)]
+pub struct TorqueCutoff(Arc<dyn Fn() -> CutoffFuture + Send + Sync>);
+
+impl TorqueCutoff {
+    pub fn new<F, Fut>(cut: F) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = eyre::Result<()>> + Send + 'static,
+    {
+        Self(Arc::new(move || Box::pin(cut())))
+    }
+
+    pub async fn cut(&self) -> eyre::Result<()> {
+        (self.0)().await
+    }
+}
+
+impl std::fmt::Debug for TorqueCutoff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("TorqueCutoff")
+    }
+}
+
+/// Handle to a background task on a [`Runtime`], such as [`Runtime::detect_falls`] or
+/// [`Runtime::watch_frames`].
+///
+/// Dropping the handle stops the task after its current check.
+pub struct Monitor {
+    stop: watch::Sender<bool>,
+    task: JoinHandle<()>,
+}
+
+impl Monitor {
+    /// Run `check` every `period` until stopped.
+    pub(crate) fn spawn<F, Fut>(period: Duration, mut check: F) -> Self
+    where
+        F: FnMut() -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send,
+    {
+        let (stop, mut stopped) = watch::channel(false);
+        let task = tokio::spawn(async move {
+            let mut interval = tokio::time::interval(period);
+            interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+            loop {
+                tokio::select! {
+                    _ = stopped.changed() => break,
+                    _ = interval.tick() => check().await,
+                }
+            }
+        });
+
+        Self { stop, task }
+    }
+
+    /// Run `handle` on every value sent to `updates`, and `handle_other` on every one sent to
+    /// `others`, until stopped, or until either sender is gone. Values missed by falling behind
+    /// are skipped.
+    pub(crate) fn listen_both<T, U, F, G, Fut, Gut>(
+        mut updates: broadcast::Receiver<T>,
+        mut handle: F,
+        mut others: broadcast::Receiver<U>,
+        mut handle_other: G,
+    ) -> Self
+    where
+        T: Clone + Send + 'static,
+        U: Clone + Send + 'static,
+        F: FnMut(T) -> Fut + Send + 'static,
+        G: FnMut(U) -> Gut + Send + 'static,
+        Fut: Future<Output = ()> + Send,
+        Gut: Future<Output = ()> + Send,
+    {
+        let (stop, mut stopped) = watch::channel(false);
+        let task = tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    _ = stopped.changed() => break,
+                    update = updates.recv() => match update {
+                        Ok(update) => handle(update).await,
+                        Err(RecvError::Lagged(_)) => continue,
+                        Err(RecvError::Closed) => break,
+                    },
+                    other = others.recv() => match other {
+                        Ok(other) => handle_other(other).await,
+                        Err(RecvError::Lagged(_)) => continue,
+                        Err(RecvError::Closed) => break,
+                    },
+                }
+            }
+        });
+
+        Self { stop, task }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.task.is_finished()
+    }
+
+    /// Stop the task and wait for the check in progress to finish.
+    pub async fn stop(self) -> eyre::Result<()> {
+        // The task may already have exited, in which case there is no receiver
+        let _ = self.stop.send(true);
+        self.task.await?;
+        Ok(())
+    }
+}
+
+impl<H: Humanoid> Runtime<H> {
+    /// Spawn a watchdog that stops the robot with [`Halt::Watchdog`] when streamed frames stop
+    /// arriving for longer than `timeout`, see [`crate::FrameQueue::emergency_stop`].
+    ///
+    /// The watchdog is armed by the first frame pushed or overwritten after startup or a reset,
+    /// and is idle while a trajectory plays, since that needs no frames. Queued frames count as
+    /// they are taken from the queue, so a long batch of frames doesn't trip it as it drains.
+    pub fn watch_frames(&self, timeout: Duration) -> Monitor {
+        let queue = self.queue();
+        let period = (timeout / 4).max(WATCHDOG_MIN_PERIOD);
+
+        Monitor::spawn(period, move || {
+            let queue = queue.clone();
+            async move {
+                if queue.halted().is_some() || queue.is_playing() {
+                    return;
+                }
+                let Some(last_frame) = queue.last_frame() else {
+                    return;
+                };
+
+                let silence = Instant::now().duration_since(last_frame);
+                if silence > timeout {
+                    let halt = Halt::Watchdog {
+                        last_frame_secs: silence.as_secs_f32(),
+                    };
+                    if let Err(e) = queue.emergency_stop(halt).await {
+                        println!("Failed to cut torque: {:?}", e);
+                    }
+                }
+            }
+        })
+    }
+}
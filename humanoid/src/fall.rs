@@ -0,0 +1,162 @@
+use std::{path::Path, time::Duration};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{read_config, EulerAngles, Halt, Imu, ImuReading, Monitor, Runtime, STANDARD_GRAVITY};
+
+/// Thresholds for [`FallDetector`].
+///
+/// ```toml
+/// max_tilt = 45.0
+/// max_accel = 3.0
+/// rate_hz = 50.0
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FallConfig {
+    /// Roll or pitch in degrees past which the robot has tipped over.
+    pub max_tilt: f32,
+    /// Acceleration in g past which the robot has hit something, usually the floor.
+    pub max_accel: f32,
+    /// How often [`Runtime::detect_falls`] reads the IMU.
+    pub rate_hz: f32,
+}
+
+impl Default for FallConfig {
+    fn default() -> Self {
+        Self {
+            max_tilt: 45.0,
+            max_accel: 3.0,
+            rate_hz: 50.0,
+        }
+    }
+}
+
+impl FallConfig {
+    /// Load thresholds from a TOML or JSON file.
+    pub fn load(path: impl AsRef<Path>) -> eyre::Result<Self> {
+        let config: Self = read_config(path)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Parse thresholds in TOML, e.g. ones embedded with `include_str!`.
+    pub fn from_toml(contents: &str) -> eyre::Result<Self> {
+        let config: Self = toml::from_str(contents)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn validate(&self) -> eyre::Result<()> {
+        eyre::ensure!(
+            self.max_tilt > 0.0 && self.max_tilt <= 180.0,
+            "max_tilt must be within 0..=180 degrees, got {}",
+            self.max_tilt
+        );
+        eyre::ensure!(
+            self.max_accel > 1.0,
+            "max_accel must be more than 1 g, got {}",
+            self.max_accel
+        );
+        eyre::ensure!(
+            self.rate_hz.is_finite() && self.rate_hz > 0.0,
+            "rate_hz must be positive, got {}",
+            self.rate_hz
+        );
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FallReason {
+    /// The torso tilted past [`FallConfig::max_tilt`].
+    Tilt,
+    /// An acceleration spike past [`FallConfig::max_accel`].
+    Impact,
+}
+
+/// A fall seen by a [`FallDetector`], with the reading that gave it away.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct FallEvent {
+    pub reason: FallReason,
+    pub orientation: EulerAngles,
+    /// Magnitude of the acceleration, in g.
+    pub accel: f32,
+}
+
+/// Tells from IMU readings whether the robot has fallen.
+#[derive(Debug, Clone)]
+pub struct FallDetector {
+    config: FallConfig,
+}
+
+impl FallDetector {
+    pub fn new(config: FallConfig) -> Self {
+        Self { config }
+    }
+
+    pub fn config(&self) -> &FallConfig {
+        &self.config
+    }
+
+    /// The fall `reading` shows, if any. An impact is reported ahead of the tilt it causes.
+    pub fn check(&self, reading: &ImuReading) -> Option<FallEvent> {
+        let orientation = reading.euler();
+        let accel = reading.accel.iter().map(|a| a * a).sum::<f32>().sqrt() / STANDARD_GRAVITY;
+
+        let reason = if accel > self.config.max_accel {
+            FallReason::Impact
+        } else if orientation.roll.abs() > self.config.max_tilt
+            || orientation.pitch.abs() > self.config.max_tilt
+        {
+            FallReason::Tilt
+        } else {
+            return None;
+        };
+
+        Some(FallEvent {
+            reason,
+            orientation,
+            accel,
+        })
+    }
+}
+
+impl<H: Imu> Runtime<H> {
+    /// Spawn a task that reads the IMU at `config.rate_hz` and stops the robot with
+    /// [`Halt::Fall`] as soon as it falls, see [`crate::FrameQueue::emergency_stop`].
+    ///
+    /// Nothing is checked while the runtime is halted, and the robot stays limp until the halt
+    /// is reset. A robot still lying down when it is reset is caught again on the next reading.
+    pub fn detect_falls(&self, config: FallConfig) -> Monitor {
+        let runtime = self.clone();
+        let detector = FallDetector::new(config);
+        let period = Duration::from_secs_f32(1.0 / config.rate_hz);
+
+        Monitor::spawn(period, move || {
+            let runtime = runtime.clone();
+            let detector = detector.clone();
+            async move {
+                let queue = runtime.queue();
+                if queue.halted().is_some() {
+                    return;
+                }
+
+                let reading = runtime.lock().await.read_imu().await;
+                let event = match reading {
+                    Ok(reading) => detector.check(&reading),
+                    Err(e) => {
+                        println!("Failed to read IMU: {:?}", e);
+                        return;
+                    }
+                };
+                if let Some(event) = event {
+                    if let Err(e) = queue.emergency_stop(Halt::Fall(event)).await {
+                        println!("Failed to cut torque: {:?}", e);
+                    }
+                }
+            }
+        })
+    }
+}
@@ -0,0 +1,247 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tokio::time::Instant;
+
+use crate::Humanoid;
+
+/// Orientation of the torso in degrees. Positive pitch leans forward, positive roll leans
+/// right.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct EulerAngles {
+    pub roll: f32,
+    pub pitch: f32,
+    pub yaw: f32,
+}
+
+impl EulerAngles {
+    pub fn new(roll: f32, pitch: f32, yaw: f32) -> Self {
+        Self { roll, pitch, yaw }
+    }
+
+    /// Roll and pitch of a robot at rest, from the direction of gravity in an accelerometer
+    /// reading `[x, y, z]` in any unit. Yaw cannot be observed and is zero.
+    pub fn from_accel([x, y, z]: [f32; 3]) -> Self {
+        Self {
+            roll: y.atan2(z).to_degrees(),
+            pitch: (-x).atan2((y * y + z * z).sqrt()).to_degrees(),
+            yaw: 0.0,
+        }
+    }
+}
+
+/// Standard gravity in m/s², the unit of [`ImuReading::accel`].
+pub const STANDARD_GRAVITY: f32 = 9.80665;
+
+/// A unit quaternion for an orientation, rotating the body frame into the world frame.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Quaternion {
+    pub w: f32,
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+}
+
+impl Default for Quaternion {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl Quaternion {
+    pub const IDENTITY: Self = Self {
+        w: 1.0,
+        x: 0.0,
+        y: 0.0,
+        z: 0.0,
+    };
+
+    pub fn new(w: f32, x: f32, y: f32, z: f32) -> Self {
+        Self { w, x, y, z }
+    }
+
+    /// Yaw, then pitch, then roll, as reported by KOS.
+    pub fn from_euler(euler: EulerAngles) -> Self {
+        let (sr, cr) = (euler.roll.to_radians() / 2.0).sin_cos();
+        let (sp, cp) = (euler.pitch.to_radians() / 2.0).sin_cos();
+        let (sy, cy) = (euler.yaw.to_radians() / 2.0).sin_cos();
+
+        Self {
+            w: cr * cp * cy + sr * sp * sy,
+            x: sr * cp * cy - cr * sp * sy,
+            y: cr * sp * cy + sr * cp * sy,
+            z: cr * cp * sy - sr * sp * cy,
+        }
+    }
+
+    pub fn to_euler(self) -> EulerAngles {
+        let Self { w, x, y, z } = self;
+        let roll = (2.0 * (w * x + y * z)).atan2(1.0 - 2.0 * (x * x + y * y));
+        // Clamped so that rounding at ±90° pitch doesn't give NaN
+        let pitch = (2.0 * (w * y - z * x)).clamp(-1.0, 1.0).asin();
+        let yaw = (2.0 * (w * z + x * y)).atan2(1.0 - 2.0 * (y * y + z * z));
+
+        EulerAngles::new(roll.to_degrees(), pitch.to_degrees(), yaw.to_degrees())
+    }
+
+    /// `[x, y, z, w]`, as in [`crate::ImuSample::quaternion`].
+    pub fn to_array(self) -> [f32; 4] {
+        [self.x, self.y, self.z, self.w]
+    }
+
+    /// Scaled to unit length, or the identity if it has none.
+    pub fn normalize(self) -> Self {
+        let norm = (self.w * self.w + self.x * self.x + self.y * self.y + self.z * self.z).sqrt();
+        if norm == 0.0 || !norm.is_finite() {
+            return Self::IDENTITY;
+        }
+
+        Self::new(self.w / norm, self.x / norm, self.y / norm, self.z / norm)
+    }
+
+    /// The orientation after turning at `gyro`, in degrees per second about the body axes,
+    /// for `dt`.
+    pub fn integrate(self, gyro: [f32; 3], dt: Duration) -> Self {
+        let [x, y, z] = gyro.map(f32::to_radians);
+        let rate = (x * x + y * y + z * z).sqrt();
+        let angle = rate * dt.as_secs_f32();
+        if rate == 0.0 || angle == 0.0 {
+            return self;
+        }
+
+        let (s, c) = (angle / 2.0).sin_cos();
+        let step = Self::new(c, x / rate * s, y / rate * s, z / rate * s);
+        (self * step).normalize()
+    }
+}
+
+impl std::ops::Mul for Quaternion {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self {
+        Self {
+            w: self.w * rhs.w - self.x * rhs.x - self.y * rhs.y - self.z * rhs.z,
+            x: self.w * rhs.x + self.x * rhs.w + self.y * rhs.z - self.z * rhs.y,
+            y: self.w * rhs.y - self.x * rhs.z + self.y * rhs.w + self.z * rhs.x,
+            z: self.w * rhs.z + self.x * rhs.y - self.y * rhs.x + self.z * rhs.w,
+        }
+    }
+}
+
+/// One IMU reading in common units, whatever the robot reports natively.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct ImuReading {
+    /// Acceleration in m/s², `[x, y, z]`. Reads `[0, 0, g]` upright at rest.
+    pub accel: [f32; 3],
+    /// Angular velocity in degrees per second, `[x, y, z]`.
+    pub gyro: [f32; 3],
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mag: Option<[f32; 3]>,
+    /// Reported by the robot, or estimated from `accel` and `gyro`.
+    pub orientation: Quaternion,
+}
+
+impl ImuReading {
+    pub fn euler(&self) -> EulerAngles {
+        self.orientation.to_euler()
+    }
+}
+
+/// A [`Humanoid`] with an IMU in its torso.
+pub trait Imu: Humanoid {
+    /// Read the IMU. Takes `&mut self` so that robots without an orientation sensor can keep
+    /// a filter running across readings.
+    fn read_imu(&mut self) -> impl std::future::Future<Output = eyre::Result<ImuReading>> + Send;
+}
+
+/// Estimates orientation from raw gyro and accelerometer readings.
+///
+/// The gyro is integrated for quick changes and roll and pitch are pulled towards the
+/// accelerometer's direction of gravity over `time_constant`, which cancels gyro drift. Readings
+/// far from 1 g are left out, since the robot is then accelerating and gravity can't be told
+/// apart. Yaw has nothing to correct it and drifts with the gyro.
+#[derive(Debug, Clone)]
+pub struct ComplementaryFilter {
+    time_constant: Duration,
+    orientation: Option<Quaternion>,
+    last_update: Option<Instant>,
+}
+
+impl Default for ComplementaryFilter {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_TIME_CONSTANT)
+    }
+}
+
+impl ComplementaryFilter {
+    pub const DEFAULT_TIME_CONSTANT: Duration = Duration::from_millis(500);
+
+    pub fn new(time_constant: Duration) -> Self {
+        Self {
+            time_constant,
+            orientation: None,
+            last_update: None,
+        }
+    }
+
+    /// The last estimate, `None` before the first reading.
+    pub fn orientation(&self) -> Option<Quaternion> {
+        self.orientation
+    }
+
+    /// Forget the estimate, so the next reading starts again from the accelerometer.
+    pub fn reset(&mut self) {
+        self.orientation = None;
+        self.last_update = None;
+    }
+
+    /// Update with a reading taken now, see [`ComplementaryFilter::update_with_dt`].
+    pub fn update(&mut self, accel: [f32; 3], gyro: [f32; 3]) -> Quaternion {
+        let now = Instant::now();
+        let dt = self
+            .last_update
+            .map_or(Duration::ZERO, |last| now.duration_since(last));
+        self.last_update = Some(now);
+
+        self.update_with_dt(accel, gyro, dt)
+    }
+
+    /// Update with `accel` in m/s² and `gyro` in degrees per second, read `dt` after the last
+    /// reading. The first reading is taken as at rest.
+    pub fn update_with_dt(&mut self, accel: [f32; 3], gyro: [f32; 3], dt: Duration) -> Quaternion {
+        let gravity = EulerAngles::from_accel(accel);
+        let Some(previous) = self.orientation else {
+            let orientation = Quaternion::from_euler(gravity);
+            self.orientation = Some(orientation);
+            return orientation;
+        };
+
+        let predicted = previous.integrate(gyro, dt);
+        let norm = accel.iter().map(|a| a * a).sum::<f32>().sqrt();
+        let orientation = if (0.5..1.5).contains(&(norm / STANDARD_GRAVITY)) {
+            let tc = self.time_constant.as_secs_f32();
+            let weight = if tc > 0.0 {
+                dt.as_secs_f32() / (tc + dt.as_secs_f32())
+            } else {
+                1.0
+            };
+            let euler = predicted.to_euler();
+            Quaternion::from_euler(EulerAngles::new(
+                blend(euler.roll, gravity.roll, weight),
+                blend(euler.pitch, gravity.pitch, weight),
+                euler.yaw,
+            ))
+        } else {
+            predicted
+        };
+
+        self.orientation = Some(orientation);
+        orientation
+    }
+}
+
+/// Move `weight` of the way from `from` to `to` in degrees, the short way round.
+fn blend(from: f32, to: f32, weight: f32) -> f32 {
+    let diff = (to - from + 180.0).rem_euclid(360.0) - 180.0;
+    from + diff * weight
+}
@@ -0,0 +1,196 @@
+use std::{collections::BTreeMap, sync::Arc};
+
+use crate::{
+    Calibrate, Calibration, Humanoid, Imu, ImuReading, Joint, JointInfo, JointPosition,
+    RobotIdentity, ServoHealth, ServoProtection, ServoTelemetry, TorqueCutoff,
+};
+
+/// Failed calls to a robot, counted by method name, see [`Instrumented`].
+#[derive(Debug, Default)]
+pub struct CallErrors {
+    counts: std::sync::Mutex<BTreeMap<&'static str, u64>>,
+}
+
+impl CallErrors {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn counts_mut(&self) -> std::sync::MutexGuard<'_, BTreeMap<&'static str, u64>> {
+        self.counts.lock().expect("call errors poisoned")
+    }
+
+    pub fn record(&self, method: &'static str) {
+        *self.counts_mut().entry(method).or_default() += 1;
+    }
+
+    /// Number of times `method` has failed.
+    pub fn get(&self, method: &str) -> u64 {
+        self.counts_mut().get(method).copied().unwrap_or_default()
+    }
+
+    /// Every method that has failed, with how many times.
+    pub fn counts(&self) -> BTreeMap<&'static str, u64> {
+        self.counts_mut().clone()
+    }
+}
+
+/// Wraps a robot and counts calls that fail in [`CallErrors`], by method name. Only errors
+/// `counted` accepts are counted, so that calls the robot refuses before sending anything, e.g.
+/// for [`crate::UnsupportedJoints`], can be told apart from failures to reach it.
+///
+/// The [`TorqueCutoff`] is wrapped as well, so failed emergency stops count as `torque_cutoff`.
+#[derive(Clone)]
+pub struct Instrumented<H: Humanoid> {
+    robot: H,
+    errors: Arc<CallErrors>,
+    counted: fn(&eyre::Report) -> bool,
+}
+
+impl<H: Humanoid> Instrumented<H> {
+    pub fn new(robot: H, counted: fn(&eyre::Report) -> bool) -> Self {
+        Self {
+            robot,
+            errors: Default::default(),
+            counted,
+        }
+    }
+
+    /// The counts, shared with every clone of the wrapper.
+    pub fn errors(&self) -> Arc<CallErrors> {
+        self.errors.clone()
+    }
+
+    pub fn inner(&self) -> &H {
+        &self.robot
+    }
+
+    pub fn inner_mut(&mut self) -> &mut H {
+        &mut self.robot
+    }
+
+    fn count<T>(&self, method: &'static str, result: eyre::Result<T>) -> eyre::Result<T> {
+        if let Err(e) = &result {
+            if (self.counted)(e) {
+                self.errors.record(method);
+            }
+        }
+        result
+    }
+}
+
+impl<H: Humanoid> Humanoid for Instrumented<H> {
+    type JointId = H::JointId;
+
+    fn supported_joints(&self) -> Vec<JointInfo> {
+        self.robot.supported_joints()
+    }
+
+    async fn calibrate(&mut self) -> eyre::Result<()> {
+        let result = self.robot.calibrate().await;
+        self.count("calibrate", result)
+    }
+
+    fn translate(&self, joint: Joint, value: f32) -> f32 {
+        self.robot.translate(joint, value)
+    }
+
+    async fn stabilize(&mut self) -> eyre::Result<()> {
+        let result = self.robot.stabilize().await;
+        self.count("stabilize", result)
+    }
+
+    async fn set_torque_enabled(&mut self, enabled: bool) -> eyre::Result<()> {
+        let result = self.robot.set_torque_enabled(enabled).await;
+        self.count("set_torque_enabled", result)
+    }
+
+    fn torque_cutoff(&self) -> Option<TorqueCutoff> {
+        let cutoff = self.robot.torque_cutoff()?;
+        let errors = self.errors.clone();
+        let counted = self.counted;
+        Some(TorqueCutoff::new(move || {
+            let cutoff = cutoff.clone();
+            let errors = errors.clone();
+            async move {
+                let result = cutoff.cut().await;
+                if let Err(e) = &result {
+                    if counted(e) {
+                        errors.record("torque_cutoff");
+                    }
+                }
+                result
+            }
+        }))
+    }
+
+    fn short_of_target(&self) -> bool {
+        self.robot.short_of_target()
+    }
+
+    async fn get_joint(&self, joint: Joint) -> eyre::Result<JointPosition> {
+        let result = self.robot.get_joint(joint).await;
+        self.count("get_joint", result)
+    }
+
+    async fn set_joints(&mut self, joints: BTreeMap<Joint, f32>) -> eyre::Result<()> {
+        let result = self.robot.set_joints(joints).await;
+        self.count("set_joints", result)
+    }
+
+    async fn set_joint(&mut self, joint: Joint, position: f32) -> eyre::Result<()> {
+        let result = self.robot.set_joint(joint, position).await;
+        self.count("set_joint", result)
+    }
+}
+
+impl<H: Calibrate> Calibrate for Instrumented<H> {
+    async fn identity(&self) -> eyre::Result<RobotIdentity> {
+        let result = self.robot.identity().await;
+        self.count("identity", result)
+    }
+
+    fn calibration(&self) -> Option<Calibration> {
+        self.robot.calibration()
+    }
+
+    async fn restore_calibration(&mut self, calibration: Calibration) -> eyre::Result<()> {
+        let result = self.robot.restore_calibration(calibration).await;
+        self.count("restore_calibration", result)
+    }
+
+    async fn verify_calibration(&self, calibration: &Calibration) -> eyre::Result<bool> {
+        let result = self.robot.verify_calibration(calibration).await;
+        self.count("verify_calibration", result)
+    }
+}
+
+impl<H: Imu> Imu for Instrumented<H> {
+    async fn read_imu(&mut self) -> eyre::Result<ImuReading> {
+        let result = self.robot.read_imu().await;
+        self.count("read_imu", result)
+    }
+}
+
+impl<H: ServoTelemetry> ServoTelemetry for Instrumented<H> {
+    async fn servo_health(&self) -> eyre::Result<Vec<ServoHealth>> {
+        let result = self.robot.servo_health().await;
+        self.count("servo_health", result)
+    }
+}
+
+impl<H: ServoProtection> ServoProtection for Instrumented<H> {
+    fn nominal_torque(&self) -> f32 {
+        self.robot.nominal_torque()
+    }
+
+    async fn set_torque_limit(&mut self, joint: Joint, torque: f32) -> eyre::Result<()> {
+        let result = self.robot.set_torque_limit(joint, torque).await;
+        self.count("set_torque_limit", result)
+    }
+
+    async fn set_joint_torque_enabled(&mut self, joint: Joint, enabled: bool) -> eyre::Result<()> {
+        let result = self.robot.set_joint_torque_enabled(joint, enabled).await;
+        self.count("set_joint_torque_enabled", result)
+    }
+}
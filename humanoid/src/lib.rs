@@ -1,8 +1,40 @@
 use serde::{Deserialize, Serialize};
 
+mod balance;
+mod calibration;
+mod clip;
+mod config;
+mod control;
+mod estop;
+mod fall;
+mod imu;
+mod instrumented;
+mod mapping;
+mod protection;
+mod recording;
 mod runtime;
-
+mod safety;
+mod sim;
+mod telemetry;
+mod trajectory;
+
+pub use balance::*;
+pub use calibration::*;
+pub use clip::*;
+pub use config::*;
+pub use control::*;
+pub use estop::*;
+pub use fall::*;
+pub use imu::*;
+pub use instrumented::*;
+pub use mapping::*;
+pub use protection::*;
+pub use recording::*;
 pub use runtime::*;
+pub use safety::*;
+pub use sim::*;
+pub use telemetry::*;
+pub use trajectory::*;
 
 #[derive(
     Debug,
@@ -16,6 +48,8 @@ pub use runtime::*;
     Ord,
     num_enum::TryFromPrimitive,
     num_enum::IntoPrimitive,
+    strum::EnumIter,
+    strum::EnumString,
 )]
 #[repr(i32)]
 pub enum Joint {
@@ -68,15 +102,104 @@ pub struct JointPosition {
     pub speed: f32,
 }
 
+/// Parse a joint given either by id, e.g. `"15"`, or by name, e.g. `"LeftShoulderPitch"`.
+pub fn parse_joint(key: &str) -> Option<Joint> {
+    match key.parse::<i32>() {
+        Ok(id) => Joint::try_from(id).ok(),
+        Err(_) => key.parse().ok(),
+    }
+}
+
+/// A joint a [`Humanoid`] can drive, with the range of frame values it accepts.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct JointInfo {
+    pub joint: Joint,
+    pub min: f32,
+    pub max: f32,
+}
+
+impl JointInfo {
+    pub const fn new(joint: Joint, min: f32, max: f32) -> Self {
+        Self { joint, min, max }
+    }
+}
+
+/// Returned when a frame mentions joints the robot does not have.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnsupportedJoints {
+    pub joints: Vec<Joint>,
+}
+
+impl std::fmt::Display for UnsupportedJoints {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Unsupported joints: {:?}", self.joints)
+    }
+}
+
+impl std::error::Error for UnsupportedJoints {}
+
+/// Check that every joint in `joints` appears in `supported`.
+pub fn check_joints<'a>(
+    supported: &[JointInfo],
+    joints: impl IntoIterator<Item = &'a Joint>,
+) -> Result<(), UnsupportedJoints> {
+    let unsupported: Vec<Joint> = joints
+        .into_iter()
+        .filter(|joint| !supported.iter().any(|info| info.joint == **joint))
+        .copied()
+        .collect();
+
+    if unsupported.is_empty() {
+        Ok(())
+    } else {
+        Err(UnsupportedJoints {
+            joints: unsupported,
+        })
+    }
+}
+
 pub trait Humanoid: Clone + Sync + Send + 'static {
     type JointId: TryFrom<i32> + Into<i32>;
 
+    /// Every joint this robot can drive. Commands for any other joint are rejected with
+    /// [`UnsupportedJoints`].
+    fn supported_joints(&self) -> Vec<JointInfo>;
+
+    fn supports(&self, joint: Joint) -> bool {
+        self.supported_joints()
+            .iter()
+            .any(|info| info.joint == joint)
+    }
+
     fn calibrate(&mut self) -> impl std::future::Future<Output = eyre::Result<()>> + Send;
 
     fn translate(&self, joint: Joint, value: f32) -> f32;
 
+    /// Correct the pose for the torso's tilt once, see [`BalanceController`]. Called on every
+    /// tick of the control loop, so robots that do not balance should return straight away.
     fn stabilize(&mut self) -> impl std::future::Future<Output = eyre::Result<()>> + Send;
 
+    /// Enable or disable torque on every joint. Without torque the joints go limp and ignore
+    /// commands until it is enabled again.
+    fn set_torque_enabled(
+        &mut self,
+        enabled: bool,
+    ) -> impl std::future::Future<Output = eyre::Result<()>> + Send;
+
+    /// A way to disable torque that doesn't need `&mut self`, for emergency stops while a
+    /// command is in flight. Robots without one are only made limp by the control loop, once
+    /// its current command finishes.
+    fn torque_cutoff(&self) -> Option<TorqueCutoff> {
+        None
+    }
+
+    /// Whether the last command was held back short of its target, e.g. by [`SafetyLimits`], so
+    /// that sending it again moves the robot further. The control loop re-sends an unchanged
+    /// frame on every tick while this holds, rather than once per keepalive.
+    fn short_of_target(&self) -> bool {
+        false
+    }
+
     fn get_joint(
         &self,
         joint: Joint,
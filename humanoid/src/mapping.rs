@@ -0,0 +1,161 @@
+use std::{collections::BTreeMap, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{read_config, Joint, JointInfo, UnsupportedJoints};
+
+fn default_scale() -> f32 {
+    1.0
+}
+
+/// How a frame value for one joint becomes a position command for its servo.
+///
+/// The frame value is clamped to `min..=max`, negated if `inverted`, then mapped to
+/// `value * scale + offset`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct JointMapping {
+    /// Servo or actuator id on the robot.
+    pub id: i32,
+    #[serde(default)]
+    pub offset: f32,
+    #[serde(default = "default_scale")]
+    pub scale: f32,
+    #[serde(default)]
+    pub inverted: bool,
+    /// Lowest frame value accepted for this joint.
+    pub min: f32,
+    /// Highest frame value accepted for this joint.
+    pub max: f32,
+}
+
+impl JointMapping {
+    pub fn new(id: i32, min: f32, max: f32) -> Self {
+        Self {
+            id,
+            offset: 0.0,
+            scale: 1.0,
+            inverted: false,
+            min,
+            max,
+        }
+    }
+
+    pub fn to_servo(&self, value: f32) -> f32 {
+        let value = value.clamp(self.min, self.max);
+        let value = if self.inverted { -value } else { value };
+        value * self.scale + self.offset
+    }
+
+    pub fn from_servo(&self, position: f32) -> f32 {
+        let value = (position - self.offset) / self.scale;
+        if self.inverted {
+            -value
+        } else {
+            value
+        }
+    }
+
+    /// Set `offset` and `scale` so that `min..=max` spans the servo's `servo_min..=servo_max`,
+    /// with `min` at `servo_min` unless the joint is inverted.
+    pub fn fit(&mut self, servo_min: f32, servo_max: f32) {
+        let span = self.max - self.min;
+        if span.abs() < f32::EPSILON {
+            self.scale = 0.0;
+            self.offset = servo_min;
+            return;
+        }
+
+        self.scale = (servo_max - servo_min) / span;
+        self.offset = if self.inverted {
+            servo_min + self.max * self.scale
+        } else {
+            servo_min - self.min * self.scale
+        };
+    }
+}
+
+/// The joint mappings of one robot, usually loaded from a profile file.
+///
+/// ```toml
+/// name = "zeroth-01"
+/// fit_to_servo_limits = true
+///
+/// [joints.LeftShoulderPitch]
+/// id = 14
+/// min = -45.0
+/// max = 45.0
+/// inverted = false
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct JointProfile {
+    #[serde(default)]
+    pub name: String,
+    /// Refit every mapping to the limits the servos report when the robot is calibrated,
+    /// instead of using the `offset` and `scale` from the file.
+    #[serde(default)]
+    pub fit_to_servo_limits: bool,
+    pub joints: BTreeMap<Joint, JointMapping>,
+}
+
+impl JointProfile {
+    /// Load a profile from a TOML or JSON file.
+    pub fn load(path: impl AsRef<Path>) -> eyre::Result<Self> {
+        let profile: Self = read_config(path)?;
+        profile.validate()?;
+        Ok(profile)
+    }
+
+    /// Parse a profile in TOML, e.g. one embedded with `include_str!`.
+    pub fn from_toml(contents: &str) -> eyre::Result<Self> {
+        let profile: Self = toml::from_str(contents)?;
+        profile.validate()?;
+        Ok(profile)
+    }
+
+    pub fn validate(&self) -> eyre::Result<()> {
+        for (joint, mapping) in &self.joints {
+            eyre::ensure!(
+                mapping.min <= mapping.max,
+                "{:?}: min {} is above max {}",
+                joint,
+                mapping.min,
+                mapping.max
+            );
+        }
+
+        let mut ids = BTreeMap::new();
+        for (joint, mapping) in &self.joints {
+            if let Some(other) = ids.insert(mapping.id, joint) {
+                eyre::bail!(
+                    "{:?} and {:?} are both mapped to id {}",
+                    other,
+                    joint,
+                    mapping.id
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn mapping(&self, joint: Joint) -> Result<&JointMapping, UnsupportedJoints> {
+        self.joints.get(&joint).ok_or_else(|| UnsupportedJoints {
+            joints: vec![joint],
+        })
+    }
+
+    /// The joint driven by servo `id`, if any.
+    pub fn joint_for_id(&self, id: i32) -> Option<Joint> {
+        self.joints
+            .iter()
+            .find(|(_, mapping)| mapping.id == id)
+            .map(|(joint, _)| *joint)
+    }
+
+    pub fn supported_joints(&self) -> Vec<JointInfo> {
+        self.joints
+            .iter()
+            .map(|(joint, mapping)| JointInfo::new(*joint, mapping.min, mapping.max))
+            .collect()
+    }
+}
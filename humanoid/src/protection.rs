@@ -0,0 +1,439 @@
+use std::{
+    collections::{BTreeMap, VecDeque},
+    path::Path,
+    sync::Arc,
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+use tokio::time::Instant;
+
+use crate::{
+    read_config, Halt, Joint, Monitor, Runtime, ServoTelemetry, TelemetryLog, TelemetrySnapshot,
+};
+
+/// Number of events kept for [`ProtectionEvents::events`].
+pub const PROTECTION_EVENT_HISTORY: usize = 256;
+
+/// Thermal and overcurrent rules applied by [`Runtime::protect_servos`]. Rules left out are
+/// off.
+///
+/// ```toml
+/// cooldown = 5.0
+///
+/// [reduce_torque]
+/// temperature = 60.0
+/// torque = 25.0
+///
+/// [disable]
+/// temperature = 70.0
+///
+/// [overcurrent]
+/// current = 2.0
+/// duration_secs = 3.0
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProtectionConfig {
+    /// Degrees Celsius a servo must cool below a temperature rule before the rule is lifted.
+    pub cooldown: f32,
+    pub reduce_torque: Option<TorqueRule>,
+    pub disable: Option<DisableRule>,
+    pub overcurrent: Option<OvercurrentRule>,
+}
+
+/// Limit a servo's torque while it is hotter than `temperature`. A limit at or above the
+/// robot's [`ServoProtection::nominal_torque`] leaves the servo at nominal torque.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct TorqueRule {
+    /// Degrees Celsius.
+    pub temperature: f32,
+    /// Percent of full torque.
+    pub torque: f32,
+}
+
+/// Switch a servo off while it is hotter than `temperature`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct DisableRule {
+    /// Degrees Celsius.
+    pub temperature: f32,
+}
+
+/// Halt the runtime when a servo draws more than `current` for `duration_secs` in a row.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct OvercurrentRule {
+    /// Amperes.
+    pub current: f32,
+    pub duration_secs: f32,
+}
+
+impl Default for ProtectionConfig {
+    fn default() -> Self {
+        Self {
+            cooldown: 5.0,
+            reduce_torque: Some(TorqueRule {
+                temperature: 60.0,
+                torque: 25.0,
+            }),
+            disable: Some(DisableRule { temperature: 70.0 }),
+            overcurrent: None,
+        }
+    }
+}
+
+impl ProtectionConfig {
+    /// Load rules from a TOML or JSON file.
+    pub fn load(path: impl AsRef<Path>) -> eyre::Result<Self> {
+        let config: Self = read_config(path)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Parse rules in TOML, e.g. ones embedded with `include_str!`.
+    pub fn from_toml(contents: &str) -> eyre::Result<Self> {
+        let config: Self = toml::from_str(contents)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn validate(&self) -> eyre::Result<()> {
+        eyre::ensure!(
+            self.cooldown.is_finite() && self.cooldown >= 0.0,
+            "cooldown must not be negative, got {}",
+            self.cooldown
+        );
+        if let Some(rule) = self.reduce_torque {
+            eyre::ensure!(
+                (0.0..=100.0).contains(&rule.torque),
+                "reduce_torque.torque must be within 0..=100 percent, got {}",
+                rule.torque
+            );
+        }
+        if let (Some(reduce), Some(disable)) = (self.reduce_torque, self.disable) {
+            eyre::ensure!(
+                disable.temperature > reduce.temperature,
+                "disable.temperature must be above reduce_torque.temperature"
+            );
+        }
+        if let Some(rule) = self.overcurrent {
+            eyre::ensure!(
+                rule.current > 0.0,
+                "overcurrent.current must be positive, got {}",
+                rule.current
+            );
+            eyre::ensure!(
+                Duration::try_from_secs_f32(rule.duration_secs).is_ok(),
+                "overcurrent.duration_secs must be a non-negative number of seconds, got {}",
+                rule.duration_secs
+            );
+        }
+        Ok(())
+    }
+}
+
+/// A [`ServoTelemetry`] robot whose servos can be throttled or switched off one at a time.
+pub trait ServoProtection: ServoTelemetry {
+    /// Percent of full torque the servos run at when not throttled, restored once a
+    /// [`TorqueRule`] is lifted.
+    fn nominal_torque(&self) -> f32;
+
+    /// Limit the servo driving `joint` to `torque` percent of its full torque.
+    fn set_torque_limit(
+        &mut self,
+        joint: Joint,
+        torque: f32,
+    ) -> impl std::future::Future<Output = eyre::Result<()>> + Send;
+
+    /// Enable or disable torque on the servo driving `joint` alone. A disabled joint goes limp.
+    fn set_joint_torque_enabled(
+        &mut self,
+        joint: Joint,
+        enabled: bool,
+    ) -> impl std::future::Future<Output = eyre::Result<()>> + Send;
+}
+
+/// What a [`ServoProtector`] did about a servo.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum ProtectionAction {
+    /// Hotter than [`TorqueRule::temperature`], limited to `torque` percent, at most
+    /// [`ServoProtection::nominal_torque`].
+    ReduceTorque { torque: f32 },
+    /// Cooled down, back to [`ServoProtection::nominal_torque`].
+    RestoreTorque,
+    /// Hotter than [`DisableRule::temperature`].
+    DisableJoint,
+    /// Cooled down, driven again.
+    EnableJoint,
+    /// Over [`OvercurrentRule::current`] for too long, see [`Halt::Overcurrent`].
+    Halt,
+}
+
+/// A rule hit, with the reading that caused it.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ProtectionEvent {
+    /// Time of the snapshot, in milliseconds since the Unix epoch.
+    pub time: u64,
+    pub joint: Joint,
+    pub id: i32,
+    #[serde(flatten)]
+    pub action: ProtectionAction,
+    pub temperature: Option<f32>,
+    pub current: Option<f32>,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct ServoState {
+    derated: bool,
+    disabled: bool,
+    overcurrent_since: Option<Instant>,
+}
+
+/// Applies [`ProtectionConfig`] to telemetry snapshots. Each rule fires once when crossed and
+/// is lifted once the servo cools [`ProtectionConfig::cooldown`] below it.
+#[derive(Debug, Clone)]
+pub struct ServoProtector {
+    config: ProtectionConfig,
+    servos: BTreeMap<Joint, ServoState>,
+}
+
+impl ServoProtector {
+    pub fn new(config: ProtectionConfig) -> Self {
+        Self {
+            config,
+            servos: BTreeMap::new(),
+        }
+    }
+
+    pub fn config(&self) -> &ProtectionConfig {
+        &self.config
+    }
+
+    /// Joints throttled by the [`TorqueRule`].
+    pub fn derated(&self) -> Vec<Joint> {
+        self.servos
+            .iter()
+            .filter(|(_, state)| state.derated)
+            .map(|(joint, _)| *joint)
+            .collect()
+    }
+
+    /// Joints switched off by the [`DisableRule`].
+    pub fn disabled(&self) -> Vec<Joint> {
+        self.servos
+            .iter()
+            .filter(|(_, state)| state.disabled)
+            .map(|(joint, _)| *joint)
+            .collect()
+    }
+
+    /// The rules `snapshot`, read at `now`, crosses or lifts.
+    pub fn check(&mut self, snapshot: &TelemetrySnapshot, now: Instant) -> Vec<ProtectionEvent> {
+        let mut events = Vec::new();
+
+        for servo in &snapshot.servos {
+            let state = self.servos.entry(servo.joint).or_default();
+            let mut report = |action| {
+                events.push(ProtectionEvent {
+                    time: snapshot.time,
+                    joint: servo.joint,
+                    id: servo.id,
+                    action,
+                    temperature: servo.temperature,
+                    current: servo.current,
+                })
+            };
+
+            if let (Some(temperature), Some(rule)) = (servo.temperature, self.config.reduce_torque)
+            {
+                if !state.derated && temperature > rule.temperature {
+                    state.derated = true;
+                    report(ProtectionAction::ReduceTorque {
+                        torque: rule.torque,
+                    });
+                } else if state.derated && temperature < rule.temperature - self.config.cooldown {
+                    state.derated = false;
+                    report(ProtectionAction::RestoreTorque);
+                }
+            }
+
+            if let (Some(temperature), Some(rule)) = (servo.temperature, self.config.disable) {
+                if !state.disabled && temperature > rule.temperature {
+                    state.disabled = true;
+                    report(ProtectionAction::DisableJoint);
+                } else if state.disabled && temperature < rule.temperature - self.config.cooldown {
+                    state.disabled = false;
+                    report(ProtectionAction::EnableJoint);
+                }
+            }
+
+            if let Some(rule) = self.config.overcurrent {
+                match servo.current {
+                    Some(current) if current > rule.current => {
+                        let since = *state.overcurrent_since.get_or_insert(now);
+                        if now - since >= Duration::from_secs_f32(rule.duration_secs) {
+                            state.overcurrent_since = None;
+                            report(ProtectionAction::Halt);
+                        }
+                    }
+                    _ => state.overcurrent_since = None,
+                }
+            }
+        }
+
+        events
+    }
+}
+
+/// The latest [`ProtectionEvent`]s, oldest first.
+#[derive(Default)]
+pub struct ProtectionEvents {
+    events: std::sync::Mutex<(VecDeque<ProtectionEvent>, u64)>,
+}
+
+impl ProtectionEvents {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, (VecDeque<ProtectionEvent>, u64)> {
+        self.events.lock().expect("protection events poisoned")
+    }
+
+    pub fn push(&self, event: ProtectionEvent) {
+        println!("Servo protection: {:?}", event);
+
+        let (events, count) = &mut *self.state();
+        if events.len() == PROTECTION_EVENT_HISTORY {
+            events.pop_front();
+        }
+        events.push_back(event);
+        *count += 1;
+    }
+
+    /// The most recent events, oldest first.
+    pub fn events(&self) -> Vec<ProtectionEvent> {
+        self.state().0.iter().copied().collect()
+    }
+
+    /// Total number of events since startup.
+    pub fn count(&self) -> u64 {
+        self.state().1
+    }
+}
+
+impl<H: ServoProtection> Runtime<H> {
+    /// Spawn a task that applies `config` to every snapshot pushed to `telemetry`, see
+    /// [`ServoProtector`], and adds each rule hit to `events`.
+    ///
+    /// Sustained overcurrent halts the runtime with [`Halt::Overcurrent`], so the robot goes
+    /// limp until the halt is reset. Enabling torque after a reset restores every servo, so
+    /// throttled and disabled joints are limited again as soon as the control loop resumes.
+    /// Disabled joints are also switched off again with every snapshot.
+    pub fn protect_servos(
+        &self,
+        config: ProtectionConfig,
+        telemetry: &TelemetryLog,
+        events: Arc<ProtectionEvents>,
+    ) -> Monitor {
+        let protector = Arc::new(tokio::sync::Mutex::new(ServoProtector::new(config)));
+
+        let runtime = self.clone();
+        let snapshot_protector = protector.clone();
+        let on_snapshot = move |snapshot: TelemetrySnapshot| {
+            let runtime = runtime.clone();
+            let protector = snapshot_protector.clone();
+            let events = events.clone();
+            async move {
+                let mut protector = protector.lock().await;
+                let hits = protector.check(&snapshot, Instant::now());
+                let disabled = protector.disabled();
+                drop(protector);
+
+                for mut event in hits {
+                    if let Err(e) = runtime.protect(&mut event).await {
+                        println!("Failed to apply servo protection: {:?}", e);
+                    }
+                    events.push(event);
+                }
+
+                let mut robot = runtime.lock().await;
+                for joint in disabled {
+                    if let Err(e) = robot.set_joint_torque_enabled(joint, false).await {
+                        println!("Failed to disable {:?}: {:?}", joint, e);
+                    }
+                }
+            }
+        };
+
+        let runtime = self.clone();
+        let on_resume = move |()| {
+            let runtime = runtime.clone();
+            let protector = protector.clone();
+            async move {
+                let protector = protector.lock().await;
+                let torque = protector.config().reduce_torque.map(|rule| rule.torque);
+                let derated = protector.derated();
+                let disabled = protector.disabled();
+                drop(protector);
+
+                let mut robot = runtime.lock().await;
+                if let Some(torque) = torque {
+                    let torque = torque.min(robot.nominal_torque());
+                    for joint in derated {
+                        if let Err(e) = robot.set_torque_limit(joint, torque).await {
+                            println!("Failed to throttle {:?}: {:?}", joint, e);
+                        }
+                    }
+                }
+                for joint in disabled {
+                    if let Err(e) = robot.set_joint_torque_enabled(joint, false).await {
+                        println!("Failed to disable {:?}: {:?}", joint, e);
+                    }
+                }
+            }
+        };
+
+        Monitor::listen_both(
+            telemetry.subscribe(),
+            on_snapshot,
+            self.queue().subscribe_resumes(),
+            on_resume,
+        )
+    }
+
+    /// Act on `event`, recording the torque actually applied by a [`TorqueRule`].
+    async fn protect(&self, event: &mut ProtectionEvent) -> eyre::Result<()> {
+        match &mut event.action {
+            ProtectionAction::ReduceTorque { torque } => {
+                let mut robot = self.lock().await;
+                *torque = torque.min(robot.nominal_torque());
+                robot.set_torque_limit(event.joint, *torque).await
+            }
+            ProtectionAction::RestoreTorque => {
+                let mut robot = self.lock().await;
+                let torque = robot.nominal_torque();
+                robot.set_torque_limit(event.joint, torque).await
+            }
+            ProtectionAction::DisableJoint => {
+                self.lock()
+                    .await
+                    .set_joint_torque_enabled(event.joint, false)
+                    .await
+            }
+            ProtectionAction::EnableJoint => {
+                self.lock()
+                    .await
+                    .set_joint_torque_enabled(event.joint, true)
+                    .await
+            }
+            ProtectionAction::Halt => {
+                self.queue().halt(Halt::Overcurrent {
+                    joint: event.joint,
+                    current: event.current.unwrap_or_default(),
+                });
+                Ok(())
+            }
+        }
+    }
+}
@@ -0,0 +1,157 @@
+use std::{collections::BTreeMap, time::Duration};
+
+use serde::{Deserialize, Serialize};
+use tokio::time::Instant;
+
+use crate::{Frame, Joint, MotionClip};
+
+/// How often joint positions are read back while recording, unless
+/// [`RecordingOptions::feedback_interval`] says otherwise.
+pub const DEFAULT_FEEDBACK_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Settings for [`Recorder::start`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordingOptions {
+    pub name: String,
+    /// Robot being recorded, stored in the clip.
+    pub robot: Option<String>,
+    /// How often to read every commanded joint back with [`crate::Humanoid::get_joint`].
+    /// `None` records commanded frames only.
+    pub feedback_interval: Option<Duration>,
+}
+
+impl RecordingOptions {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            robot: None,
+            feedback_interval: Some(DEFAULT_FEEDBACK_INTERVAL),
+        }
+    }
+}
+
+/// Progress of the recording in progress.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordingStatus {
+    pub name: String,
+    /// Seconds since the recording started.
+    pub elapsed: f32,
+    pub frames: usize,
+    pub feedback: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RecordingError {
+    AlreadyRecording,
+}
+
+impl std::fmt::Display for RecordingError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RecordingError::AlreadyRecording => write!(f, "A recording is already in progress"),
+        }
+    }
+}
+
+impl std::error::Error for RecordingError {}
+
+struct Recording {
+    clip: MotionClip,
+    started: Instant,
+    feedback_interval: Option<Duration>,
+    last_feedback: Option<Instant>,
+}
+
+/// Captures the frames sent by the control loop, and periodic readback of where the robot
+/// actually is, into a [`MotionClip`].
+#[derive(Default)]
+pub struct Recorder {
+    active: std::sync::Mutex<Option<Recording>>,
+}
+
+impl Recorder {
+    pub fn start(&self, options: RecordingOptions) -> Result<(), RecordingError> {
+        let mut active = self.active.lock().expect("recorder lock poisoned");
+        if active.is_some() {
+            return Err(RecordingError::AlreadyRecording);
+        }
+
+        let mut clip = MotionClip::new(options.name);
+        clip.robot = options.robot;
+        *active = Some(Recording {
+            clip,
+            started: Instant::now(),
+            feedback_interval: options.feedback_interval,
+            last_feedback: None,
+        });
+        Ok(())
+    }
+
+    /// Finish the recording, returning the clip if one was in progress.
+    pub fn stop(&self) -> Option<MotionClip> {
+        let recording = self.active.lock().expect("recorder lock poisoned").take()?;
+        Some(recording.clip)
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.active
+            .lock()
+            .expect("recorder lock poisoned")
+            .is_some()
+    }
+
+    pub fn status(&self) -> Option<RecordingStatus> {
+        let active = self.active.lock().expect("recorder lock poisoned");
+        active.as_ref().map(|recording| RecordingStatus {
+            name: recording.clip.name.clone(),
+            elapsed: recording.started.elapsed().as_secs_f32(),
+            frames: recording.clip.frames.len(),
+            feedback: recording.clip.feedback.len(),
+        })
+    }
+
+    /// Record `frame` as sent to the robot now.
+    pub(crate) fn record_command(&self, frame: &Frame) {
+        let mut active = self.active.lock().expect("recorder lock poisoned");
+        if let Some(recording) = active.as_mut() {
+            let time = recording.started.elapsed();
+            // Clip frame times must be strictly increasing
+            if recording
+                .clip
+                .frames
+                .last()
+                .is_some_and(|last| last.time >= time.as_secs_f32())
+            {
+                return;
+            }
+            recording.clip.push(time, frame.joints.clone());
+        }
+    }
+
+    /// Whether joints should be read back now, returning the time to record them at.
+    pub(crate) fn feedback_due(&self) -> Option<Duration> {
+        let mut active = self.active.lock().expect("recorder lock poisoned");
+        let recording = active.as_mut()?;
+        let interval = recording.feedback_interval?;
+
+        let now = Instant::now();
+        if recording
+            .last_feedback
+            .is_some_and(|last| now - last < interval)
+        {
+            return None;
+        }
+        recording.last_feedback = Some(now);
+        Some(now - recording.started)
+    }
+
+    pub(crate) fn record_feedback(&self, time: Duration, joints: BTreeMap<Joint, f32>) {
+        let mut active = self.active.lock().expect("recorder lock poisoned");
+        if let (Some(recording), false) = (active.as_mut(), joints.is_empty()) {
+            recording.clip.feedback.push(crate::ClipFrame {
+                time: time.as_secs_f32(),
+                joints,
+            });
+        }
+    }
+}
@@ -1,11 +1,30 @@
-use std::{ops::Deref, sync::Arc, time::Duration};
+use std::{
+    ops::Deref,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
 
 use crossbeam::atomic::AtomicCell;
-use tokio::sync::Mutex;
+use tokio::{
+    sync::{broadcast, Mutex},
+    time::Instant,
+};
 
-use crate::{Humanoid, Joint};
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, PartialEq)]
+use crate::{
+    check_joints, FallEvent, Humanoid, Joint, JointInfo, MotionClip, Recorder, RecordingError,
+    RecordingOptions, TorqueCutoff, Trajectory, UnsupportedJoints,
+};
+
+/// Halts and resumes kept for [`FrameQueue::subscribe_halts`] and
+/// [`FrameQueue::subscribe_resumes`] receivers that fall behind.
+const HALT_CHANNEL_CAPACITY: usize = 16;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Frame {
     pub joints: std::collections::BTreeMap<Joint, f32>,
 }
@@ -15,18 +34,389 @@ pub struct FrameQueue {
     // unfortunately need arc here due to axum constraints needing H: Send if we clone the whole
     // runtime
     pub queue: crossbeam::queue::SegQueue<Frame>,
+    trajectory: std::sync::Mutex<Option<ActiveTrajectory>>,
+    supported: Vec<JointInfo>,
+    recorder: Recorder,
+    halt: std::sync::Mutex<Option<Halt>>,
+    halts: broadcast::Sender<Halt>,
+    resumes: broadcast::Sender<()>,
+    cutoff: Option<TorqueCutoff>,
+    // When a frame was last pushed, overwritten or taken from the queue, for the watchdog
+    last_frame: AtomicCell<Option<Instant>>,
+    received: AtomicU64,
+}
+
+/// Why the control loop stopped driving the robot, see [`FrameQueue::halt`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "cause", rename_all = "snake_case")]
+pub enum Halt {
+    Fall(FallEvent),
+    /// Requested with [`FrameQueue::emergency_stop`], e.g. from `POST /estop`.
+    EmergencyStop,
+    /// No frame arrived in time, see [`Runtime::watch_frames`].
+    Watchdog {
+        last_frame_secs: f32,
+    },
+    /// A servo drew too much current for too long, see [`Runtime::protect_servos`].
+    Overcurrent {
+        joint: Joint,
+        /// Amperes.
+        current: f32,
+    },
+}
+
+/// Why [`FrameQueue`] refused a frame or trajectory.
+#[derive(Debug, Clone, PartialEq)]
+pub enum QueueError {
+    Unsupported(UnsupportedJoints),
+    /// Nothing is accepted until the halt is reset, see [`FrameQueue::reset_halt`].
+    Halted(Halt),
+}
+
+impl std::fmt::Display for QueueError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            QueueError::Unsupported(e) => e.fmt(f),
+            QueueError::Halted(halt) => write!(f, "Robot is halted: {:?}", halt),
+        }
+    }
+}
+
+impl std::error::Error for QueueError {}
+
+impl From<UnsupportedJoints> for QueueError {
+    fn from(e: UnsupportedJoints) -> Self {
+        QueueError::Unsupported(e)
+    }
+}
+
+/// How [`FrameQueue::play_with`] plays a trajectory.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PlaybackOptions {
+    /// Times to play the trajectory through, `0` repeats it until stopped.
+    pub loops: u32,
+    /// Playback rate, e.g. `2.0` plays twice as fast.
+    pub speed: f32,
+}
+
+impl Default for PlaybackOptions {
+    fn default() -> Self {
+        Self {
+            loops: 1,
+            speed: 1.0,
+        }
+    }
+}
+
+/// Progress of the trajectory being played.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PlaybackStatus {
+    pub options: PlaybackOptions,
+    /// Seconds into the current loop.
+    pub position: f32,
+    /// Seconds in one loop.
+    pub duration: f32,
+    pub completed_loops: u32,
+    pub paused: bool,
+}
+
+struct ActiveTrajectory {
+    trajectory: Trajectory,
+    options: PlaybackOptions,
+    position: Duration,
+    // Set on the first sample so that timing starts when the control loop picks it up
+    last_sample: Option<Instant>,
+    completed_loops: u32,
+    paused: bool,
+}
+
+impl ActiveTrajectory {
+    /// Move the playback position to `now`, returning false once the last loop is finished.
+    fn advance(&mut self, now: Instant) -> bool {
+        if let (Some(last), false) = (self.last_sample, self.paused) {
+            self.position += (now - last).mul_f32(self.options.speed.max(0.0));
+        }
+        self.last_sample = Some(now);
+
+        let duration = self.trajectory.duration();
+        while self.position >= duration {
+            self.completed_loops += 1;
+            if self.options.loops != 0 && self.completed_loops >= self.options.loops {
+                self.position = duration;
+                return false;
+            }
+            if duration.is_zero() {
+                break;
+            }
+            self.position -= duration;
+        }
+
+        true
+    }
 }
 
 impl FrameQueue {
-    pub fn push(&self, frame: Frame) {
-        self.queue.push(frame);
+    /// Joints of the robot this queue feeds, taken from [`Humanoid::supported_joints`].
+    pub fn supported_joints(&self) -> &[JointInfo] {
+        &self.supported
+    }
+
+    /// Records frames as the control loop sends them.
+    pub fn recorder(&self) -> &Recorder {
+        &self.recorder
+    }
+
+    /// Number of frames waiting to be sent.
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    /// The frame being sent to the robot, without taking the next one from the queue.
+    pub fn current(&self) -> Option<Frame> {
+        let current = self.current.take();
+        self.current.store(current.clone());
+        current
+    }
+
+    pub fn check(&self, frame: &Frame) -> Result<(), UnsupportedJoints> {
+        check_joints(&self.supported, frame.joints.keys())
+    }
+
+    /// Fail while halted, so that frames sent during a fall or emergency stop are refused
+    /// rather than dropped later. Holding the guard keeps a halt from landing meanwhile.
+    fn accepting(&self) -> Result<std::sync::MutexGuard<'_, Option<Halt>>, QueueError> {
+        let halt = self.halt.lock().expect("halt lock poisoned");
+        match &*halt {
+            Some(halt) => Err(QueueError::Halted(halt.clone())),
+            None => Ok(halt),
+        }
+    }
+
+    /// Append `frame` to the queue. Fails while halted.
+    pub fn push(&self, frame: Frame) -> Result<(), QueueError> {
+        self.push_all(vec![frame])
     }
 
-    pub fn overwrite(&self, frame: Frame) {
-        // Clear the queue
-        while let Some(_) = self.queue.pop() {}
+    /// Append every frame in `frames` to the queue, or none of them if any names a joint the
+    /// robot lacks or the queue is halted.
+    pub fn push_all(&self, frames: Vec<Frame>) -> Result<(), QueueError> {
+        let _accepting = self.accepting()?;
+        for frame in &frames {
+            self.check(frame)?;
+        }
+        self.received
+            .fetch_add(frames.len() as u64, Ordering::Relaxed);
+        self.last_frame.store(Some(Instant::now()));
+        for frame in frames {
+            self.queue.push(frame);
+        }
+        Ok(())
+    }
+
+    /// Replace anything queued with `frame`. Fails while halted.
+    pub fn overwrite(&self, frame: Frame) -> Result<(), QueueError> {
+        let _accepting = self.accepting()?;
+        self.check(&frame)?;
+        self.received.fetch_add(1, Ordering::Relaxed);
+        self.last_frame.store(Some(Instant::now()));
+        self.clear();
 
         self.current.swap(Some(frame));
+        Ok(())
+    }
+
+    /// Play `trajectory` once in place of any queued frames. Frames pushed afterwards run once
+    /// it finishes.
+    pub fn play(&self, trajectory: Trajectory) -> Result<(), QueueError> {
+        self.play_with(trajectory, PlaybackOptions::default())
+    }
+
+    /// Play `trajectory` in place of any queued frames, looped and sped up as in `options`.
+    /// Fails while halted.
+    pub fn play_with(
+        &self,
+        trajectory: Trajectory,
+        options: PlaybackOptions,
+    ) -> Result<(), QueueError> {
+        let _accepting = self.accepting()?;
+        check_joints(&self.supported, trajectory.joints())?;
+        while self.queue.pop().is_some() {}
+        // Not streaming any more, so the watchdog has nothing to wait for
+        self.last_frame.store(None);
+
+        *self.trajectory.lock().expect("trajectory lock poisoned") = Some(ActiveTrajectory {
+            trajectory,
+            options,
+            position: Duration::ZERO,
+            last_sample: None,
+            completed_loops: 0,
+            paused: false,
+        });
+        Ok(())
+    }
+
+    /// Hold the trajectory being played at its current position. Returns false if nothing is
+    /// playing.
+    pub fn pause(&self) -> bool {
+        self.set_paused(true)
+    }
+
+    /// Continue a paused trajectory from where it was paused. Returns false if nothing is
+    /// playing.
+    pub fn resume(&self) -> bool {
+        self.set_paused(false)
+    }
+
+    fn set_paused(&self, paused: bool) -> bool {
+        let mut trajectory = self.trajectory.lock().expect("trajectory lock poisoned");
+        match trajectory.as_mut() {
+            Some(active) => {
+                active.paused = paused;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn playback(&self) -> Option<PlaybackStatus> {
+        let trajectory = self.trajectory.lock().expect("trajectory lock poisoned");
+        trajectory.as_ref().map(|active| PlaybackStatus {
+            options: active.options,
+            position: active.position.as_secs_f32(),
+            duration: active.trajectory.duration().as_secs_f32(),
+            completed_loops: active.completed_loops,
+            paused: active.paused,
+        })
+    }
+
+    /// Drop every queued frame and any trajectory being played, returning how many frames were
+    /// dropped. The robot holds the current frame.
+    pub fn clear(&self) -> usize {
+        self.stop_trajectory();
+
+        let mut cleared = 0;
+        while self.queue.pop().is_some() {
+            cleared += 1;
+        }
+        cleared
+    }
+
+    pub fn stop_trajectory(&self) {
+        self.trajectory
+            .lock()
+            .expect("trajectory lock poisoned")
+            .take();
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.trajectory
+            .lock()
+            .expect("trajectory lock poisoned")
+            .is_some()
+    }
+
+    /// Stop driving the robot: drop every queued frame, the current frame and any trajectory,
+    /// and have the control loop disable torque. Frames and trajectories sent while halted are
+    /// refused with [`QueueError::Halted`].
+    ///
+    /// The halt is latched until [`FrameQueue::reset_halt`], and only the first cause is kept.
+    /// Returns false if already halted.
+    pub fn halt(&self, halt: Halt) -> bool {
+        {
+            let mut latched = self.halt.lock().expect("halt lock poisoned");
+            if latched.is_some() {
+                return false;
+            }
+            *latched = Some(halt.clone());
+            // Under the lock, so that no frame is accepted between the halt and the drop
+            self.drop_frames();
+        }
+
+        println!("Halted: {:?}", halt);
+        // Nobody may be listening
+        let _ = self.halts.send(halt);
+        true
+    }
+
+    /// Halt, see [`FrameQueue::halt`], and disable torque straight away with the robot's
+    /// [`TorqueCutoff`] rather than waiting for the control loop. Torque is cut even if already
+    /// halted. Returns false if already halted.
+    pub async fn emergency_stop(&self, halt: Halt) -> eyre::Result<bool> {
+        let halted = self.halt(halt);
+        if let Some(cutoff) = &self.cutoff {
+            cutoff.cut().await?;
+        }
+        Ok(halted)
+    }
+
+    /// Why the robot is halted, or `None` while it is driven.
+    pub fn halted(&self) -> Option<Halt> {
+        self.halt.lock().expect("halt lock poisoned").clone()
+    }
+
+    /// Let the control loop enable torque and drive the robot again, returning the halt that
+    /// was cleared. The robot holds where it is until the next frame.
+    pub fn reset_halt(&self) -> Option<Halt> {
+        let halt = self.halt.lock().expect("halt lock poisoned").take();
+        self.last_frame.store(None);
+        if let Some(halt) = &halt {
+            println!("Reset halt: {:?}", halt);
+        }
+        halt
+    }
+
+    /// Receive every halt from now on.
+    pub fn subscribe_halts(&self) -> broadcast::Receiver<Halt> {
+        self.halts.subscribe()
+    }
+
+    /// Receive a notification each time the control loop enables torque again after a halt is
+    /// reset.
+    pub fn subscribe_resumes(&self) -> broadcast::Receiver<()> {
+        self.resumes.subscribe()
+    }
+
+    pub(crate) fn resumed(&self) {
+        // Nobody may be listening
+        let _ = self.resumes.send(());
+    }
+
+    /// Number of frames pushed or overwritten since startup, whatever they came over.
+    pub fn frames_received(&self) -> u64 {
+        self.received.load(Ordering::Relaxed)
+    }
+
+    /// When a frame was last pushed, overwritten or taken from the queue, `None` if none has
+    /// been since startup, a reset or the last trajectory started.
+    pub fn last_frame(&self) -> Option<Instant> {
+        self.last_frame.load()
+    }
+
+    pub(crate) fn drop_frames(&self) {
+        self.clear();
+        self.current.take();
+    }
+
+    /// Sample the active trajectory at the current time and make it the current frame.
+    ///
+    /// The trajectory is finished once its last keyframe has been sampled in the last loop.
+    pub(crate) fn trajectory_setpoint(&self) -> Option<Frame> {
+        let mut trajectory = self.trajectory.lock().expect("trajectory lock poisoned");
+        let active = trajectory.as_mut()?;
+
+        let playing = active.advance(Instant::now());
+        let frame = active.trajectory.sample(active.position);
+        if !playing {
+            *trajectory = None;
+        }
+
+        self.current.swap(Some(frame.clone()));
+        Some(frame)
     }
 }
 
@@ -42,12 +432,24 @@ pub struct Runtime<H: Humanoid> {
 
 impl<H: Humanoid> Runtime<H> {
     pub fn new(robot: H) -> Self {
+        let supported = robot.supported_joints();
+        let cutoff = robot.torque_cutoff();
+
         Self {
             inner: Arc::new(RuntimeInner {
                 robot: Mutex::new(robot),
                 queue: Arc::new(FrameQueue {
                     current: AtomicCell::new(None),
                     queue: crossbeam::queue::SegQueue::new(),
+                    trajectory: std::sync::Mutex::new(None),
+                    supported,
+                    recorder: Recorder::default(),
+                    halt: std::sync::Mutex::new(None),
+                    halts: broadcast::channel(HALT_CHANNEL_CAPACITY).0,
+                    resumes: broadcast::channel(HALT_CHANNEL_CAPACITY).0,
+                    cutoff,
+                    last_frame: AtomicCell::new(None),
+                    received: AtomicU64::new(0),
                 }),
             }),
         }
@@ -58,23 +460,50 @@ impl<H: Humanoid> Runtime<H> {
     }
 
     pub fn queue_len(&self) -> usize {
-        self.inner.queue.queue.len()
+        self.inner.queue.len()
+    }
+
+    pub fn overwrite(&self, frame: Frame) -> Result<(), QueueError> {
+        self.inner.queue.overwrite(frame)
+    }
+
+    /// Interpolate through `trajectory` at the control rate, replacing any queued frames.
+    pub fn play(&self, trajectory: Trajectory) -> Result<(), QueueError> {
+        self.inner.queue.play(trajectory)
+    }
+
+    pub fn play_with(
+        &self,
+        trajectory: Trajectory,
+        options: PlaybackOptions,
+    ) -> Result<(), QueueError> {
+        self.inner.queue.play_with(trajectory, options)
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.inner.queue.is_playing()
     }
 
-    pub fn overwrite(&self, frame: Frame) {
-        self.inner.queue.overwrite(frame);
+    /// Record every frame sent from now on, see [`Recorder`].
+    pub fn start_recording(&self, options: RecordingOptions) -> Result<(), RecordingError> {
+        self.inner.queue.recorder.start(options)
+    }
+
+    pub fn stop_recording(&self) -> Option<MotionClip> {
+        self.inner.queue.recorder.stop()
     }
 
     pub fn advance(&mut self) -> bool {
         if let Some(frame) = self.inner.queue.queue.pop() {
             self.inner.queue.current.swap(Some(frame));
+            self.inner.queue.last_frame.store(Some(Instant::now()));
             return true;
         }
         false
     }
 
-    pub fn push_frame(&self, frame: Frame) {
-        self.inner.queue.queue.push(frame);
+    pub fn push_frame(&self, frame: Frame) -> Result<(), QueueError> {
+        self.inner.queue.push(frame)
     }
 
     pub fn is_complete(&self, current_state: Frame) -> bool {
@@ -90,11 +519,12 @@ impl<H: Humanoid> Runtime<H> {
             return frame == &current_state;
         }
 
-        return false;
+        false
     }
 
-    pub async fn step(&mut self) -> eyre::Result<bool> {
-        let current = match self.inner.queue.current.take() {
+    /// The frame currently being executed, promoting the next queued frame if there is none.
+    pub fn current_frame(&self) -> Option<Frame> {
+        match self.inner.queue.current.take() {
             Some(current) => {
                 let frame = current.clone();
 
@@ -102,27 +532,45 @@ impl<H: Humanoid> Runtime<H> {
                 // This is a hack because of the atomic cell usage
                 self.inner.queue.current.store(Some(current));
 
-                frame
+                Some(frame)
             }
             None => {
-                if let Some(next) = self.inner.queue.queue.pop() {
-                    self.inner.queue.current.store(Some(next.clone()));
-                    next
-                } else {
-                    return Ok(false);
-                }
+                let next = self.inner.queue.queue.pop()?;
+                self.inner.queue.current.store(Some(next.clone()));
+                Some(next)
             }
-        };
+        }
+    }
+
+    /// Send the current frame once. Sends nothing while halted.
+    pub async fn step(&mut self) -> eyre::Result<bool> {
+        if self.inner.queue.halted().is_some() {
+            return Ok(false);
+        }
+
+        if let Some(setpoint) = self.inner.queue.trajectory_setpoint() {
+            self.inner
+                .robot
+                .lock()
+                .await
+                .set_joints(setpoint.joints.clone())
+                .await?;
+            self.inner.queue.recorder.record_command(&setpoint);
 
-        println!("RUNNING CURRENT FRAME: {:?}", current);
+            return Ok(true);
+        }
+
+        let Some(current) = self.current_frame() else {
+            return Ok(false);
+        };
 
         self.inner
             .robot
             .lock()
             .await
             .set_joints(current.joints.clone())
-            .await
-            .unwrap();
+            .await?;
+        self.inner.queue.recorder.record_command(&current);
 
         // loop {
         //     tokio::time::sleep(Duration::from_millis(100)).await;
@@ -0,0 +1,418 @@
+use std::{
+    collections::{BTreeMap, VecDeque},
+    path::Path,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+use tokio::time::Instant;
+
+use crate::{
+    read_config, Calibrate, Calibration, Humanoid, Imu, ImuReading, Joint, JointInfo,
+    JointPosition, RobotIdentity, ServoHealth, ServoProtection, ServoTelemetry, TorqueCutoff,
+};
+
+/// Number of violations kept for [`SafetyLimits::violations`].
+pub const VIOLATION_HISTORY: usize = 256;
+
+/// Time between commands assumed by [`SafetyLimits`], that of a 50 Hz control loop, unless set
+/// with [`SafetyLimits::with_period`].
+pub const DEFAULT_COMMAND_PERIOD: Duration = Duration::from_millis(20);
+
+/// Limits for a single joint, in the same units as the frames sent to the robot.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct JointLimits {
+    pub min: f32,
+    pub max: f32,
+    /// Degrees per second.
+    #[serde(default)]
+    pub max_velocity: Option<f32>,
+    /// Degrees per second squared.
+    #[serde(default)]
+    pub max_acceleration: Option<f32>,
+}
+
+/// Per-joint limits enforced by [`SafetyLimits`].
+///
+/// ```toml
+/// [default]
+/// min = -90.0
+/// max = 90.0
+///
+/// [joints.LeftShoulderPitch]
+/// min = -45.0
+/// max = 45.0
+/// max_velocity = 180.0
+/// max_acceleration = 720.0
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SafetyConfig {
+    /// Limits for any joint without an entry in `joints`.
+    #[serde(default)]
+    pub default: Option<JointLimits>,
+    #[serde(default)]
+    pub joints: BTreeMap<Joint, JointLimits>,
+}
+
+impl SafetyConfig {
+    /// Load limits from a TOML or JSON file.
+    pub fn load(path: impl AsRef<Path>) -> eyre::Result<Self> {
+        let config: Self = read_config(path)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Parse limits in TOML, e.g. ones embedded with `include_str!`.
+    pub fn from_toml(contents: &str) -> eyre::Result<Self> {
+        let config: Self = toml::from_str(contents)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn validate(&self) -> eyre::Result<()> {
+        let all = self
+            .default
+            .iter()
+            .map(|limits| ("default".to_string(), limits))
+            .chain(
+                self.joints
+                    .iter()
+                    .map(|(joint, limits)| (format!("{:?}", joint), limits)),
+            );
+
+        for (name, limits) in all {
+            eyre::ensure!(
+                limits.min.is_finite() && limits.max.is_finite(),
+                "{}: min and max must be finite",
+                name
+            );
+            eyre::ensure!(
+                limits.min <= limits.max,
+                "{}: min {} is above max {}",
+                name,
+                limits.min,
+                limits.max
+            );
+            for (field, value) in [
+                ("max_velocity", limits.max_velocity),
+                ("max_acceleration", limits.max_acceleration),
+            ] {
+                if let Some(value) = value {
+                    eyre::ensure!(value > 0.0, "{}: {} must be positive", name, field);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn limits(&self, joint: Joint) -> Option<&JointLimits> {
+        self.joints.get(&joint).or(self.default.as_ref())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LimitKind {
+    Position,
+    Velocity,
+    Acceleration,
+}
+
+/// A command that had to be changed to stay within its joint's limits.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Violation {
+    pub joint: Joint,
+    pub kind: LimitKind,
+    pub requested: f32,
+    pub applied: f32,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Commanded {
+    /// Where the joint was asked to go, within its range.
+    target: f32,
+    position: f32,
+    velocity: f32,
+    at: Instant,
+}
+
+#[derive(Default)]
+struct SafetyState {
+    commanded: BTreeMap<Joint, Commanded>,
+    violations: VecDeque<Violation>,
+    violation_count: u64,
+}
+
+impl SafetyState {
+    // Not printed, since a joint approaching a far target under a velocity limit is limited on
+    // every control tick
+    fn report(&mut self, violation: Violation) {
+        if self.violations.len() == VIOLATION_HISTORY {
+            self.violations.pop_front();
+        }
+        self.violations.push_back(violation);
+        self.violation_count += 1;
+    }
+}
+
+/// Wraps a [`Humanoid`] so that every command respects per-joint position, velocity and
+/// acceleration limits.
+///
+/// Velocity and acceleration are limited per command, as if commands to the same joint were one
+/// control period apart. Each command moves a joint at most `max_velocity` times the period from
+/// the last commanded position, however long ago that was, so a target that is too far away is
+/// approached over several commands. A joint that went without commands for longer than two
+/// periods is taken to have come to rest. Until a joint reaches its target,
+/// [`Humanoid::short_of_target`] has the control loop re-send the frame on every tick.
+#[derive(Clone)]
+pub struct SafetyLimits<H: Humanoid> {
+    robot: H,
+    config: Arc<SafetyConfig>,
+    period: Duration,
+    state: Arc<Mutex<SafetyState>>,
+}
+
+impl<H: Humanoid> SafetyLimits<H> {
+    pub fn new(robot: H, config: SafetyConfig) -> Self {
+        Self {
+            robot,
+            config: Arc::new(config),
+            period: DEFAULT_COMMAND_PERIOD,
+            state: Default::default(),
+        }
+    }
+
+    /// Limit each command to what a joint may move in `period`, the control loop's period.
+    pub fn with_period(mut self, period: Duration) -> Self {
+        self.period = period;
+        self
+    }
+
+    pub fn config(&self) -> &SafetyConfig {
+        &self.config
+    }
+
+    pub fn inner(&self) -> &H {
+        &self.robot
+    }
+
+    pub fn inner_mut(&mut self) -> &mut H {
+        &mut self.robot
+    }
+
+    /// The most recent violations, oldest first.
+    pub fn violations(&self) -> Vec<Violation> {
+        self.state().violations.iter().cloned().collect()
+    }
+
+    /// Total number of violations since the wrapper was created.
+    pub fn violation_count(&self) -> u64 {
+        self.state().violation_count
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, SafetyState> {
+        self.state.lock().expect("safety state poisoned")
+    }
+
+    /// Apply the limits of `joint` to `requested`, reporting anything that had to change.
+    pub fn limit(&self, joint: Joint, requested: f32) -> f32 {
+        let Some(limits) = self.config.limits(joint) else {
+            return requested;
+        };
+
+        let mut state = self.state();
+        let now = Instant::now();
+
+        let target = requested.clamp(limits.min, limits.max);
+        let mut position = target;
+        if position != requested {
+            state.report(Violation {
+                joint,
+                kind: LimitKind::Position,
+                requested,
+                applied: position,
+            });
+        }
+
+        let dt = self.period.as_secs_f32();
+        let mut velocity = 0.0;
+        if let Some(mut previous) = state.commanded.get(&joint).copied() {
+            if now - previous.at > self.period * 2 {
+                previous.velocity = 0.0;
+            }
+            velocity = (position - previous.position) / dt;
+            let mut limited = false;
+
+            if let Some(max_velocity) = limits.max_velocity {
+                let clamped = velocity.clamp(-max_velocity, max_velocity);
+                if clamped != velocity {
+                    state.report(Violation {
+                        joint,
+                        kind: LimitKind::Velocity,
+                        requested,
+                        applied: previous.position + clamped * dt,
+                    });
+                    velocity = clamped;
+                    limited = true;
+                }
+            }
+
+            if let Some(max_acceleration) = limits.max_acceleration {
+                let max_change = max_acceleration * dt;
+                let clamped = velocity.clamp(
+                    previous.velocity - max_change,
+                    previous.velocity + max_change,
+                );
+                if clamped != velocity {
+                    state.report(Violation {
+                        joint,
+                        kind: LimitKind::Acceleration,
+                        requested,
+                        applied: previous.position + clamped * dt,
+                    });
+                    velocity = clamped;
+                    limited = true;
+                }
+            }
+
+            if limited {
+                position = previous.position + velocity * dt;
+
+                // Momentum stops at the target, and never carries the joint out of its range
+                if (velocity > 0.0 && position >= target) || (velocity < 0.0 && position <= target)
+                {
+                    position = target;
+                    velocity = 0.0;
+                }
+                let bounded = position.clamp(limits.min, limits.max);
+                if bounded != position {
+                    position = bounded;
+                    velocity = 0.0;
+                }
+            }
+        }
+
+        state.commanded.insert(
+            joint,
+            Commanded {
+                target,
+                position,
+                velocity,
+                at: now,
+            },
+        );
+
+        position
+    }
+}
+
+impl<H: Humanoid> Humanoid for SafetyLimits<H> {
+    type JointId = H::JointId;
+
+    /// The inner robot's joints, with their ranges narrowed to the configured limits.
+    fn supported_joints(&self) -> Vec<JointInfo> {
+        self.robot
+            .supported_joints()
+            .into_iter()
+            .map(|info| match self.config.limits(info.joint) {
+                Some(limits) => JointInfo {
+                    min: info.min.max(limits.min),
+                    max: info.max.min(limits.max),
+                    ..info
+                },
+                None => info,
+            })
+            .collect()
+    }
+
+    async fn calibrate(&mut self) -> eyre::Result<()> {
+        self.robot.calibrate().await
+    }
+
+    fn translate(&self, joint: Joint, value: f32) -> f32 {
+        self.robot.translate(joint, value)
+    }
+
+    async fn stabilize(&mut self) -> eyre::Result<()> {
+        self.robot.stabilize().await
+    }
+
+    async fn set_torque_enabled(&mut self, enabled: bool) -> eyre::Result<()> {
+        self.robot.set_torque_enabled(enabled).await
+    }
+
+    fn torque_cutoff(&self) -> Option<TorqueCutoff> {
+        self.robot.torque_cutoff()
+    }
+
+    fn short_of_target(&self) -> bool {
+        self.state()
+            .commanded
+            .values()
+            .any(|commanded| commanded.position != commanded.target)
+            || self.robot.short_of_target()
+    }
+
+    async fn get_joint(&self, joint: Joint) -> eyre::Result<JointPosition> {
+        self.robot.get_joint(joint).await
+    }
+
+    async fn set_joints(&mut self, joints: BTreeMap<Joint, f32>) -> eyre::Result<()> {
+        let joints = joints
+            .into_iter()
+            .map(|(joint, value)| (joint, self.limit(joint, value)))
+            .collect();
+
+        self.robot.set_joints(joints).await
+    }
+
+    async fn set_joint(&mut self, joint: Joint, position: f32) -> eyre::Result<()> {
+        let position = self.limit(joint, position);
+        self.robot.set_joint(joint, position).await
+    }
+}
+
+impl<H: Calibrate> Calibrate for SafetyLimits<H> {
+    async fn identity(&self) -> eyre::Result<RobotIdentity> {
+        self.robot.identity().await
+    }
+
+    fn calibration(&self) -> Option<Calibration> {
+        self.robot.calibration()
+    }
+
+    async fn restore_calibration(&mut self, calibration: Calibration) -> eyre::Result<()> {
+        self.robot.restore_calibration(calibration).await
+    }
+
+    async fn verify_calibration(&self, calibration: &Calibration) -> eyre::Result<bool> {
+        self.robot.verify_calibration(calibration).await
+    }
+}
+
+impl<H: Imu> Imu for SafetyLimits<H> {
+    async fn read_imu(&mut self) -> eyre::Result<ImuReading> {
+        self.robot.read_imu().await
+    }
+}
+
+impl<H: ServoTelemetry> ServoTelemetry for SafetyLimits<H> {
+    async fn servo_health(&self) -> eyre::Result<Vec<ServoHealth>> {
+        self.robot.servo_health().await
+    }
+}
+
+impl<H: ServoProtection> ServoProtection for SafetyLimits<H> {
+    fn nominal_torque(&self) -> f32 {
+        self.robot.nominal_torque()
+    }
+
+    async fn set_torque_limit(&mut self, joint: Joint, torque: f32) -> eyre::Result<()> {
+        self.robot.set_torque_limit(joint, torque).await
+    }
+
+    async fn set_joint_torque_enabled(&mut self, joint: Joint, enabled: bool) -> eyre::Result<()> {
+        self.robot.set_joint_torque_enabled(joint, enabled).await
+    }
+}
@@ -0,0 +1,453 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::{Arc, Mutex},
+};
+
+use strum::IntoEnumIterator;
+use tokio::time::Instant;
+
+use crate::{
+    check_joints, BalanceConfig, BalanceController, EulerAngles, Humanoid, Imu, ImuReading, Joint,
+    JointInfo, JointPosition, Quaternion, ServoHealth, ServoProtection, ServoTelemetry,
+    TorqueCutoff, STANDARD_GRAVITY,
+};
+
+/// Default slew speed of a simulated joint, in degrees per second.
+pub const DEFAULT_SIM_SPEED: f32 = 180.0;
+
+/// Range of every joint of a default [`SimulatedHumanoid`].
+pub const SIM_JOINT_RANGE: (f32, f32) = (-180.0, 180.0);
+
+/// A single command received by a [`SimulatedHumanoid`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum SimCall {
+    SetJoints(BTreeMap<Joint, f32>),
+    SetJoint(Joint, f32),
+}
+
+/// What a [`SimulatedHumanoid`] reports for a joint through [`ServoTelemetry`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SimHealth {
+    /// Degrees Celsius.
+    pub temperature: f32,
+    /// Amperes.
+    pub current: f32,
+    /// Volts.
+    pub voltage: f32,
+}
+
+impl Default for SimHealth {
+    fn default() -> Self {
+        Self {
+            temperature: 30.0,
+            current: 0.0,
+            voltage: 12.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct SimJoint {
+    position: f32,
+    target: f32,
+}
+
+struct SimState {
+    speed: f32,
+    supported: Vec<JointInfo>,
+    joints: BTreeMap<Joint, SimJoint>,
+    calls: Vec<SimCall>,
+    last_update: Instant,
+    orientation: EulerAngles,
+    balance: Option<BalanceController>,
+    torque_enabled: bool,
+    /// Joints with torque disabled on their own, see [`ServoProtection`].
+    limp: BTreeSet<Joint>,
+    torque_limits: BTreeMap<Joint, f32>,
+    nominal_torque: f32,
+    health: BTreeMap<Joint, SimHealth>,
+}
+
+impl SimState {
+    /// Move every joint toward its target by however far it could have travelled since the
+    /// last update.
+    fn update(&mut self) {
+        let now = Instant::now();
+        let max_delta = self.speed * (now - self.last_update).as_secs_f32();
+        self.last_update = now;
+        if !self.torque_enabled {
+            return;
+        }
+
+        for (id, joint) in self.joints.iter_mut() {
+            if self.limp.contains(id) {
+                continue;
+            }
+            let error = joint.target - joint.position;
+            if error.abs() <= max_delta {
+                joint.position = joint.target;
+            } else {
+                joint.position += max_delta.copysign(error);
+            }
+        }
+    }
+
+    fn set_target(&mut self, joint: Joint, target: f32) {
+        self.joints
+            .entry(joint)
+            .or_insert(SimJoint {
+                position: 0.0,
+                target: 0.0,
+            })
+            .target = target;
+    }
+}
+
+/// An in-memory [`Humanoid`] that slews each joint toward its target at a fixed speed and keeps
+/// a log of every command it receives.
+///
+/// Clones share the same state, so a copy kept outside of a [`crate::Runtime`] can be used to
+/// inspect what the runtime sent.
+#[derive(Clone)]
+pub struct SimulatedHumanoid {
+    state: Arc<Mutex<SimState>>,
+}
+
+impl Default for SimulatedHumanoid {
+    fn default() -> Self {
+        Self::new(DEFAULT_SIM_SPEED)
+    }
+}
+
+impl SimulatedHumanoid {
+    /// Create a simulated robot whose joints move at `speed` degrees per second.
+    pub fn new(speed: f32) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(SimState {
+                speed,
+                supported: Joint::iter()
+                    .map(|joint| JointInfo::new(joint, SIM_JOINT_RANGE.0, SIM_JOINT_RANGE.1))
+                    .collect(),
+                joints: BTreeMap::new(),
+                calls: Vec::new(),
+                last_update: Instant::now(),
+                orientation: EulerAngles::default(),
+                balance: None,
+                torque_enabled: true,
+                limp: BTreeSet::new(),
+                torque_limits: BTreeMap::new(),
+                nominal_torque: 100.0,
+                health: BTreeMap::new(),
+            })),
+        }
+    }
+
+    /// Only support the given joints instead of every [`Joint`].
+    pub fn with_joints(self, joints: impl IntoIterator<Item = JointInfo>) -> Self {
+        self.state().supported = joints.into_iter().collect();
+        self
+    }
+
+    /// Run servos at `torque` percent when not throttled, see
+    /// [`ServoProtection::nominal_torque`].
+    pub fn with_nominal_torque(self, torque: f32) -> Self {
+        self.state().nominal_torque = torque;
+        self
+    }
+
+    /// Balance with `config` whenever [`Humanoid::stabilize`] is called, against the
+    /// orientation set with [`SimulatedHumanoid::set_orientation`]. Fails if `config` moves
+    /// unsupported joints.
+    pub fn with_balance(self, config: BalanceConfig) -> eyre::Result<Self> {
+        config.check_joints(&self.state().supported)?;
+        self.state().balance = Some(BalanceController::new(config));
+        Ok(self)
+    }
+
+    /// What the simulated IMU reports. Upright by default.
+    pub fn orientation(&self) -> EulerAngles {
+        self.state().orientation
+    }
+
+    pub fn set_orientation(&self, orientation: EulerAngles) {
+        self.state().orientation = orientation;
+    }
+
+    /// Offsets the balance controller currently adds to each joint.
+    pub fn balance_offsets(&self) -> BTreeMap<Joint, f32> {
+        self.state()
+            .balance
+            .as_ref()
+            .map(|balance| balance.offsets().clone())
+            .unwrap_or_default()
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, SimState> {
+        self.state.lock().expect("sim state poisoned")
+    }
+
+    pub fn speed(&self) -> f32 {
+        self.state().speed
+    }
+
+    pub fn set_speed(&self, speed: f32) {
+        let mut state = self.state();
+        state.update();
+        state.speed = speed;
+    }
+
+    /// Current simulated position of `joint`, or `None` if it was never commanded.
+    pub fn position(&self, joint: Joint) -> Option<f32> {
+        let mut state = self.state();
+        state.update();
+        state.joints.get(&joint).map(|j| j.position)
+    }
+
+    /// Last commanded target of `joint`, or `None` if it was never commanded.
+    pub fn target(&self, joint: Joint) -> Option<f32> {
+        self.state().joints.get(&joint).map(|j| j.target)
+    }
+
+    /// Place `joint` at `position` immediately, without slewing or recording a call.
+    pub fn teleport(&self, joint: Joint, position: f32) {
+        let mut state = self.state();
+        state.update();
+        state.joints.insert(
+            joint,
+            SimJoint {
+                position,
+                target: position,
+            },
+        );
+    }
+
+    /// Whether every commanded joint has reached its target.
+    pub fn is_settled(&self) -> bool {
+        let mut state = self.state();
+        state.update();
+        state.joints.values().all(|j| j.position == j.target)
+    }
+
+    /// Whether the joints are driven. Without torque they stay where they are.
+    pub fn torque_enabled(&self) -> bool {
+        self.state().torque_enabled
+    }
+
+    /// Report `health` for `joint` from now on. Every joint is at [`SimHealth::default`] until set.
+    pub fn set_health(&self, joint: Joint, health: SimHealth) {
+        self.state().health.insert(joint, health);
+    }
+
+    /// Whether `joint` is driven, see [`ServoProtection::set_joint_torque_enabled`].
+    pub fn joint_torque_enabled(&self, joint: Joint) -> bool {
+        let state = self.state();
+        state.torque_enabled && !state.limp.contains(&joint)
+    }
+
+    /// Percent of full torque `joint` is limited to, see [`ServoProtection::set_torque_limit`].
+    pub fn torque_limit(&self, joint: Joint) -> f32 {
+        let state = self.state();
+        state
+            .torque_limits
+            .get(&joint)
+            .copied()
+            .unwrap_or(state.nominal_torque)
+    }
+
+    /// Every `set_joints`/`set_joint` call received so far, oldest first.
+    pub fn calls(&self) -> Vec<SimCall> {
+        self.state().calls.clone()
+    }
+
+    pub fn clear_calls(&self) {
+        self.state().calls.clear();
+    }
+}
+
+impl Humanoid for SimulatedHumanoid {
+    type JointId = Joint;
+
+    fn supported_joints(&self) -> Vec<JointInfo> {
+        self.state().supported.clone()
+    }
+
+    async fn calibrate(&mut self) -> eyre::Result<()> {
+        Ok(())
+    }
+
+    fn translate(&self, _joint: Joint, value: f32) -> f32 {
+        value
+    }
+
+    /// Run the balance controller, if any, once against the simulated IMU.
+    async fn stabilize(&mut self) -> eyre::Result<()> {
+        let mut state = self.state();
+        let orientation = state.orientation;
+        let Some(balance) = state.balance.as_mut() else {
+            return Ok(());
+        };
+
+        let joints = balance.update(orientation);
+        if joints.is_empty() {
+            return Ok(());
+        }
+        state.update();
+        for (joint, value) in &joints {
+            state.set_target(*joint, *value);
+        }
+        state.calls.push(SimCall::SetJoints(joints));
+
+        Ok(())
+    }
+
+    fn torque_cutoff(&self) -> Option<TorqueCutoff> {
+        let sim = self.clone();
+        Some(TorqueCutoff::new(move || {
+            let mut state = sim.state();
+            state.update();
+            state.torque_enabled = false;
+            std::future::ready(Ok(()))
+        }))
+    }
+
+    /// Re-enabling torque holds every joint where it is, as a real servo would.
+    async fn set_torque_enabled(&mut self, enabled: bool) -> eyre::Result<()> {
+        let mut state = self.state();
+        state.update();
+        if enabled {
+            // Like a real robot, every servo is turned back on at full torque
+            let limp = std::mem::take(&mut state.limp);
+            state.torque_limits.clear();
+            let was_enabled = state.torque_enabled;
+            for (id, joint) in state.joints.iter_mut() {
+                if !was_enabled || limp.contains(id) {
+                    joint.target = joint.position;
+                }
+            }
+        }
+        state.torque_enabled = enabled;
+
+        Ok(())
+    }
+
+    async fn get_joint(&self, joint: Joint) -> eyre::Result<JointPosition> {
+        let mut state = self.state();
+        state.update();
+
+        let speed = state.speed;
+        let sim_joint = state
+            .joints
+            .get(&joint)
+            .copied()
+            .ok_or_else(|| eyre::eyre!("Joint {:?} has not been commanded", joint))?;
+
+        Ok(JointPosition {
+            joint,
+            position: sim_joint.position,
+            speed: if sim_joint.position == sim_joint.target {
+                0.0
+            } else {
+                speed
+            },
+        })
+    }
+
+    async fn set_joints(&mut self, joints: BTreeMap<Joint, f32>) -> eyre::Result<()> {
+        let mut state = self.state();
+        check_joints(&state.supported, joints.keys())?;
+        state.update();
+
+        let joints = match state.balance.as_mut() {
+            Some(balance) => balance.apply(joints),
+            None => joints,
+        };
+
+        for (joint, value) in &joints {
+            state.set_target(*joint, *value);
+        }
+        state.calls.push(SimCall::SetJoints(joints));
+
+        Ok(())
+    }
+
+    async fn set_joint(&mut self, joint: Joint, position: f32) -> eyre::Result<()> {
+        let mut state = self.state();
+        check_joints(&state.supported, [&joint])?;
+        state.update();
+
+        let position = match state.balance.as_mut() {
+            Some(balance) => balance.apply([(joint, position)].into())[&joint],
+            None => position,
+        };
+        state.set_target(joint, position);
+        state.calls.push(SimCall::SetJoint(joint, position));
+
+        Ok(())
+    }
+}
+
+impl Imu for SimulatedHumanoid {
+    /// A still IMU at the set orientation, with gravity the only acceleration.
+    async fn read_imu(&mut self) -> eyre::Result<ImuReading> {
+        let orientation = self.orientation();
+        let (sr, cr) = orientation.roll.to_radians().sin_cos();
+        let (sp, cp) = orientation.pitch.to_radians().sin_cos();
+
+        Ok(ImuReading {
+            accel: [-sp, sr * cp, cr * cp].map(|a| a * STANDARD_GRAVITY),
+            gyro: [0.0; 3],
+            mag: None,
+            orientation: Quaternion::from_euler(orientation),
+        })
+    }
+}
+
+impl ServoProtection for SimulatedHumanoid {
+    fn nominal_torque(&self) -> f32 {
+        self.state().nominal_torque
+    }
+
+    async fn set_torque_limit(&mut self, joint: Joint, torque: f32) -> eyre::Result<()> {
+        let mut state = self.state();
+        check_joints(&state.supported, [&joint])?;
+        state.torque_limits.insert(joint, torque);
+        Ok(())
+    }
+
+    /// A joint turned back on holds where it went limp.
+    async fn set_joint_torque_enabled(&mut self, joint: Joint, enabled: bool) -> eyre::Result<()> {
+        let mut state = self.state();
+        check_joints(&state.supported, [&joint])?;
+        state.update();
+        if enabled {
+            if state.limp.remove(&joint) {
+                if let Some(sim_joint) = state.joints.get_mut(&joint) {
+                    sim_joint.target = sim_joint.position;
+                }
+            }
+        } else {
+            state.limp.insert(joint);
+        }
+        Ok(())
+    }
+}
+
+impl ServoTelemetry for SimulatedHumanoid {
+    async fn servo_health(&self) -> eyre::Result<Vec<ServoHealth>> {
+        let state = self.state();
+        Ok(state
+            .supported
+            .iter()
+            .map(|info| {
+                let health = state.health.get(&info.joint).copied().unwrap_or_default();
+                ServoHealth {
+                    joint: info.joint,
+                    id: info.joint.into(),
+                    temperature: Some(health.temperature),
+                    current: Some(health.current),
+                    voltage: Some(health.voltage),
+                }
+            })
+            .collect())
+    }
+}
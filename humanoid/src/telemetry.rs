@@ -0,0 +1,150 @@
+use std::{
+    collections::VecDeque,
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+
+use crate::{Humanoid, Joint, Monitor, Runtime};
+
+/// Snapshots kept for [`TelemetryLog::subscribe`] receivers that fall behind.
+const TELEMETRY_CHANNEL_CAPACITY: usize = 16;
+
+/// Health of one servo. Fields the robot doesn't report are `None`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ServoHealth {
+    pub joint: Joint,
+    /// The robot's own id for the servo.
+    pub id: i32,
+    /// Degrees Celsius.
+    pub temperature: Option<f32>,
+    /// Amperes.
+    pub current: Option<f32>,
+    /// Volts.
+    pub voltage: Option<f32>,
+}
+
+/// The health of every servo at one time.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TelemetrySnapshot {
+    /// Milliseconds since the Unix epoch.
+    pub time: u64,
+    pub servos: Vec<ServoHealth>,
+}
+
+impl TelemetrySnapshot {
+    /// A snapshot of `servos` taken now.
+    pub fn now(servos: Vec<ServoHealth>) -> Self {
+        Self {
+            time: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_millis() as u64),
+            servos,
+        }
+    }
+}
+
+/// A [`Humanoid`] that reports the health of its servos.
+pub trait ServoTelemetry: Humanoid {
+    /// Read the health of every servo the robot drives.
+    fn servo_health(
+        &self,
+    ) -> impl std::future::Future<Output = eyre::Result<Vec<ServoHealth>>> + Send;
+}
+
+/// The latest [`TelemetrySnapshot`]s, oldest first, up to a fixed number.
+pub struct TelemetryLog {
+    capacity: usize,
+    snapshots: std::sync::Mutex<VecDeque<TelemetrySnapshot>>,
+    updates: broadcast::Sender<TelemetrySnapshot>,
+}
+
+impl TelemetryLog {
+    /// Keep at most `capacity` snapshots, and at least one.
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            capacity,
+            snapshots: std::sync::Mutex::new(VecDeque::with_capacity(capacity)),
+            updates: broadcast::channel(TELEMETRY_CHANNEL_CAPACITY).0,
+        }
+    }
+
+    /// Room for `history` of snapshots taken every `interval`.
+    pub fn for_history(history: Duration, interval: Duration) -> Self {
+        let capacity = if interval.is_zero() {
+            1
+        } else {
+            history.as_millis().div_ceil(interval.as_millis()) as usize
+        };
+        Self::new(capacity)
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    fn snapshots(&self) -> std::sync::MutexGuard<'_, VecDeque<TelemetrySnapshot>> {
+        self.snapshots.lock().expect("telemetry lock poisoned")
+    }
+
+    /// Add `snapshot`, dropping the oldest one if full.
+    pub fn push(&self, snapshot: TelemetrySnapshot) {
+        {
+            let mut snapshots = self.snapshots();
+            if snapshots.len() == self.capacity {
+                snapshots.pop_front();
+            }
+            snapshots.push_back(snapshot.clone());
+        }
+        // Nobody may be listening
+        let _ = self.updates.send(snapshot);
+    }
+
+    pub fn latest(&self) -> Option<TelemetrySnapshot> {
+        self.snapshots().back().cloned()
+    }
+
+    /// Every snapshot kept, oldest first.
+    pub fn history(&self) -> Vec<TelemetrySnapshot> {
+        self.snapshots().iter().cloned().collect()
+    }
+
+    /// Snapshots taken after `time`, in milliseconds since the Unix epoch, oldest first.
+    pub fn since(&self, time: u64) -> Vec<TelemetrySnapshot> {
+        self.snapshots()
+            .iter()
+            .filter(|snapshot| snapshot.time > time)
+            .cloned()
+            .collect()
+    }
+
+    /// Receive every snapshot pushed from now on.
+    pub fn subscribe(&self) -> broadcast::Receiver<TelemetrySnapshot> {
+        self.updates.subscribe()
+    }
+}
+
+impl<H: ServoTelemetry> Runtime<H> {
+    /// Spawn a task that reads [`ServoTelemetry::servo_health`] every `interval` into `log`.
+    ///
+    /// Each reading holds the lock on the robot, so the control loop waits for it. Keep the
+    /// interval well above the control period.
+    pub fn poll_telemetry(&self, interval: Duration, log: Arc<TelemetryLog>) -> Monitor {
+        let runtime = self.clone();
+
+        Monitor::spawn(interval, move || {
+            let runtime = runtime.clone();
+            let log = log.clone();
+            async move {
+                let servos = runtime.lock().await.servo_health().await;
+                match servos {
+                    Ok(servos) => log.push(TelemetrySnapshot::now(servos)),
+                    Err(e) => println!("Failed to read servo health: {:?}", e),
+                }
+            }
+        })
+    }
+}
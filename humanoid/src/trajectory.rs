@@ -0,0 +1,196 @@
+use std::{collections::BTreeMap, time::Duration};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Frame, Joint};
+
+/// How setpoints between two keyframes are generated.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Interpolation {
+    /// Constant velocity between keyframes.
+    #[default]
+    Linear,
+    /// Natural cubic spline through every keyframe, continuous in velocity and acceleration.
+    CubicSpline,
+    /// Minimum-jerk profile between each pair of keyframes, coming to rest at every keyframe.
+    MinimumJerk,
+}
+
+/// A frame that should be reached `time` after the start of a trajectory.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Keyframe {
+    pub time: Duration,
+    pub frame: Frame,
+}
+
+/// Samples of a single joint, with the spline's second derivatives if needed.
+#[derive(Debug, Clone)]
+struct Curve {
+    times: Vec<f64>,
+    values: Vec<f64>,
+    second_derivatives: Vec<f64>,
+}
+
+impl Curve {
+    fn new(times: Vec<f64>, values: Vec<f64>, interpolation: Interpolation) -> Self {
+        let second_derivatives = match interpolation {
+            Interpolation::CubicSpline => natural_spline(&times, &values),
+            Interpolation::Linear | Interpolation::MinimumJerk => Vec::new(),
+        };
+
+        Self {
+            times,
+            values,
+            second_derivatives,
+        }
+    }
+
+    fn sample(&self, t: f64, interpolation: Interpolation) -> f64 {
+        let last = self.times.len() - 1;
+        if t <= self.times[0] {
+            return self.values[0];
+        }
+        if t >= self.times[last] {
+            return self.values[last];
+        }
+
+        // Index of the keyframe starting the segment containing `t`
+        let i = self.times.partition_point(|&time| time <= t) - 1;
+        let (t0, t1) = (self.times[i], self.times[i + 1]);
+        let (y0, y1) = (self.values[i], self.values[i + 1]);
+        let h = t1 - t0;
+        let s = (t - t0) / h;
+
+        match interpolation {
+            Interpolation::Linear => y0 + (y1 - y0) * s,
+            Interpolation::MinimumJerk => {
+                let s = s * s * s * (10.0 - 15.0 * s + 6.0 * s * s);
+                y0 + (y1 - y0) * s
+            }
+            Interpolation::CubicSpline => {
+                let (m0, m1) = (self.second_derivatives[i], self.second_derivatives[i + 1]);
+                let (a, b) = (t1 - t, t - t0);
+                m0 * a.powi(3) / (6.0 * h)
+                    + m1 * b.powi(3) / (6.0 * h)
+                    + (y0 / h - m0 * h / 6.0) * a
+                    + (y1 / h - m1 * h / 6.0) * b
+            }
+        }
+    }
+}
+
+/// Second derivatives of the natural cubic spline through the given points.
+fn natural_spline(times: &[f64], values: &[f64]) -> Vec<f64> {
+    let n = times.len();
+    let mut m = vec![0.0; n];
+    if n < 3 {
+        return m;
+    }
+
+    // Solve the tridiagonal system for the interior points with the Thomas algorithm
+    let mut upper = vec![0.0; n];
+    let mut rhs = vec![0.0; n];
+    for i in 1..n - 1 {
+        let h0 = times[i] - times[i - 1];
+        let h1 = times[i + 1] - times[i];
+        let d = 6.0 * ((values[i + 1] - values[i]) / h1 - (values[i] - values[i - 1]) / h0);
+
+        let diagonal = 2.0 * (h0 + h1) - h0 * upper[i - 1];
+        upper[i] = h1 / diagonal;
+        rhs[i] = (d - h0 * rhs[i - 1]) / diagonal;
+    }
+
+    for i in (1..n - 1).rev() {
+        m[i] = rhs[i] - upper[i] * m[i + 1];
+    }
+
+    m
+}
+
+/// A timed motion built from keyframes, sampled by the runtime at the control rate.
+#[derive(Debug, Clone)]
+pub struct Trajectory {
+    interpolation: Interpolation,
+    duration: Duration,
+    curves: BTreeMap<Joint, Curve>,
+}
+
+impl Trajectory {
+    /// Build a trajectory from keyframes in increasing time order.
+    ///
+    /// A joint that is missing from some keyframes is interpolated between the keyframes that
+    /// do mention it.
+    pub fn new(keyframes: Vec<Keyframe>, interpolation: Interpolation) -> eyre::Result<Self> {
+        eyre::ensure!(!keyframes.is_empty(), "Trajectory has no keyframes");
+        eyre::ensure!(
+            keyframes.windows(2).all(|w| w[0].time < w[1].time),
+            "Keyframe times must be strictly increasing"
+        );
+
+        let mut samples: BTreeMap<Joint, (Vec<f64>, Vec<f64>)> = BTreeMap::new();
+        for keyframe in &keyframes {
+            for (joint, value) in &keyframe.frame.joints {
+                let (times, values) = samples.entry(*joint).or_default();
+                times.push(keyframe.time.as_secs_f64());
+                values.push(*value as f64);
+            }
+        }
+
+        let curves = samples
+            .into_iter()
+            .map(|(joint, (times, values))| (joint, Curve::new(times, values, interpolation)))
+            .collect();
+
+        Ok(Self {
+            interpolation,
+            duration: keyframes.last().expect("non-empty").time,
+            curves,
+        })
+    }
+
+    /// Build a trajectory from frames recorded `interval` apart, starting at time zero.
+    pub fn from_frames(
+        frames: Vec<Frame>,
+        interval: Duration,
+        interpolation: Interpolation,
+    ) -> eyre::Result<Self> {
+        let keyframes = frames
+            .into_iter()
+            .enumerate()
+            .map(|(i, frame)| Keyframe {
+                time: interval * i as u32,
+                frame,
+            })
+            .collect();
+
+        Self::new(keyframes, interpolation)
+    }
+
+    /// Every joint mentioned by at least one keyframe.
+    pub fn joints(&self) -> impl Iterator<Item = &Joint> {
+        self.curves.keys()
+    }
+
+    pub fn interpolation(&self) -> Interpolation {
+        self.interpolation
+    }
+
+    /// Time of the last keyframe.
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+
+    /// The setpoint `t` after the start of the trajectory, clamped to its first and last
+    /// keyframes.
+    pub fn sample(&self, t: Duration) -> Frame {
+        let t = t.as_secs_f64();
+        Frame {
+            joints: self
+                .curves
+                .iter()
+                .map(|(joint, curve)| (*joint, curve.sample(t, self.interpolation) as f32))
+                .collect(),
+        }
+    }
+}
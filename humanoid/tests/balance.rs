@@ -0,0 +1,97 @@
+use std::{collections::BTreeMap, time::Duration};
+
+use humanoid::{
+    AxisGains, BalanceConfig, BalanceController, EulerAngles, Frame, Joint, JointInfo, Runtime,
+    SimulatedHumanoid,
+};
+
+fn standing() -> BTreeMap<Joint, f32> {
+    [
+        (Joint::LeftHipPitch, 10.0),
+        (Joint::RightHipPitch, 10.0),
+        (Joint::LeftAnklePitch, -5.0),
+        (Joint::RightAnklePitch, -5.0),
+        (Joint::LeftKneeYaw, 0.0),
+        (Joint::RightKneeYaw, 0.0),
+    ]
+    .into_iter()
+    .collect()
+}
+
+#[tokio::test(start_paused = true)]
+async fn corrects_against_tilt_within_limits() {
+    let mut balance = BalanceController::new(BalanceConfig::default());
+    balance.apply(standing());
+
+    // Within the deadband nothing moves
+    let pose = balance.update(EulerAngles::new(0.5, -0.5, 0.0));
+    assert_eq!(pose, standing());
+
+    // Leaning forward 5 degrees past the deadband pitches every pitch joint back
+    tokio::time::advance(Duration::from_millis(20)).await;
+    let pose = balance.update(EulerAngles::new(0.0, 6.0, 0.0));
+    let offset = pose[&Joint::LeftHipPitch] - 10.0;
+    assert!(offset < 0.0);
+    assert_eq!(pose[&Joint::LeftAnklePitch] + 5.0, offset);
+    assert_eq!(pose[&Joint::LeftKneeYaw], 0.0);
+
+    // New frames keep the correction
+    let sent = balance.apply([(Joint::LeftHipPitch, 20.0)].into());
+    assert_eq!(sent[&Joint::LeftHipPitch], 20.0 + offset);
+
+    // A fall is never corrected by more than max_correction
+    tokio::time::advance(Duration::from_millis(20)).await;
+    let pose = balance.update(EulerAngles::new(-90.0, 0.0, 0.0));
+    assert_eq!(pose[&Joint::RightKneeYaw], 15.0);
+
+    balance.reset();
+    assert!(balance.offsets().is_empty());
+    assert!(BalanceConfig::from_toml("[pitch]\nkp = -1.0").is_err());
+}
+
+#[tokio::test(start_paused = true)]
+async fn keeps_the_simulated_torso_upright() {
+    let config = BalanceConfig {
+        pitch: AxisGains {
+            kp: 0.5,
+            ki: 2.0,
+            kd: 0.0,
+        },
+        ..BalanceConfig::default()
+    };
+    let sim = SimulatedHumanoid::default().with_balance(config).unwrap();
+    let runtime = Runtime::new(sim.clone());
+    runtime.overwrite(Frame { joints: standing() }).unwrap();
+    let handle = runtime.run(50.0).unwrap();
+
+    // A push leans the torso forward 8 degrees, which pitching the ankles back undoes
+    let tilt = |sim: &SimulatedHumanoid| {
+        let ankle = sim.position(Joint::LeftAnklePitch).unwrap_or(-5.0) + 5.0;
+        8.0 + 2.0 * ankle
+    };
+    for _ in 0..150 {
+        sim.set_orientation(EulerAngles::new(0.0, tilt(&sim), 0.0));
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    handle.stop().await.unwrap();
+
+    assert!(tilt(&sim).abs() <= 1.1, "still tilted {}", tilt(&sim));
+    assert!(sim.balance_offsets()[&Joint::LeftAnklePitch] < 0.0);
+}
+
+#[test]
+fn rejects_joints_the_robot_lacks() {
+    let sim = SimulatedHumanoid::default().with_joints([
+        JointInfo::new(Joint::LeftHipPitch, -90.0, 90.0),
+        JointInfo::new(Joint::RightHipPitch, -90.0, 90.0),
+        JointInfo::new(Joint::LeftAnklePitch, -90.0, 90.0),
+        JointInfo::new(Joint::RightAnklePitch, -90.0, 90.0),
+    ]);
+    assert!(sim.clone().with_balance(BalanceConfig::default()).is_err());
+
+    let pitch_only = BalanceConfig {
+        roll_joints: BTreeMap::new(),
+        ..BalanceConfig::default()
+    };
+    assert!(sim.with_balance(pitch_only).is_ok());
+}
@@ -0,0 +1,44 @@
+use humanoid::{Calibration, CalibrationError, RobotIdentity, ServoCalibration};
+
+#[test]
+fn rejects_other_versions_and_robots() {
+    let robot = RobotIdentity {
+        model: "zeroth-01".to_string(),
+        servo_ids: vec![1, 2],
+    };
+    let calibration = Calibration::new(
+        robot.clone(),
+        vec![ServoCalibration {
+            id: 1,
+            min: Some(-90.0),
+            max: Some(90.0),
+        }],
+    );
+
+    let path = std::env::temp_dir().join(format!("calibration-{}.toml", std::process::id()));
+    calibration.save(&path).unwrap();
+    let loaded = Calibration::load(&path).unwrap();
+    assert_eq!(loaded, calibration);
+    assert!(loaded.check_robot(&robot).is_ok());
+
+    let other = RobotIdentity {
+        servo_ids: vec![1, 2, 3],
+        ..robot
+    };
+    assert!(matches!(
+        loaded.check_robot(&other),
+        Err(CalibrationError::WrongRobot { .. })
+    ));
+
+    let newer = std::fs::read_to_string(&path)
+        .unwrap()
+        .replace("version = 1", "version = 2");
+    std::fs::write(&path, newer).unwrap();
+    let err = Calibration::load(&path).unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<CalibrationError>(),
+        Some(&CalibrationError::UnsupportedVersion { found: 2 })
+    );
+
+    std::fs::remove_file(path).unwrap();
+}
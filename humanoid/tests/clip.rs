@@ -0,0 +1,77 @@
+use std::time::Duration;
+
+use humanoid::{AngleUnit, ClipError, Interpolation, Joint, MotionClip};
+
+#[test]
+fn round_trips_and_rejects_other_versions() {
+    let mut clip = MotionClip::new("wave");
+    clip.robot = Some("zeroth-01".to_string());
+    clip.push(
+        Duration::ZERO,
+        [(Joint::LeftShoulderPitch, 0.0)].into_iter().collect(),
+    );
+    clip.push(
+        Duration::from_millis(500),
+        [(Joint::LeftShoulderPitch, 45.0)].into_iter().collect(),
+    );
+
+    for ext in ["json", "toml"] {
+        let path = std::env::temp_dir().join(format!("clip-{}.{}", std::process::id(), ext));
+        clip.save(&path).unwrap();
+        assert_eq!(MotionClip::load(&path).unwrap(), clip);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    let newer = serde_json::to_string(&clip)
+        .unwrap()
+        .replace("\"version\":1", "\"version\":2");
+    let err = MotionClip::from_json(&newer).unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<ClipError>(),
+        Some(&ClipError::UnsupportedVersion { found: 2 })
+    );
+}
+
+#[test]
+fn upgrades_legacy_motions() {
+    let legacy = serde_json::json!([
+        { "15": 10.0, "RightShoulderPitch": -10.0 },
+        { "15": 20.0 },
+        { "15": 30.0 },
+    ]);
+    let clip = MotionClip::from_legacy("flap", &legacy, 20.0).unwrap();
+
+    assert_eq!(clip.fps, Some(20.0));
+    let times: Vec<_> = clip.frames.iter().map(|frame| frame.time).collect();
+    assert_eq!(times, [0.0, 0.05, 0.1]);
+    assert_eq!(
+        clip.frames[0].joints.get(&Joint::RightShoulderPitch),
+        Some(&-10.0)
+    );
+
+    let unknown = serde_json::json!([{ "Tail": 1.0 }]);
+    assert!(MotionClip::from_legacy("bad", &unknown, 20.0).is_err());
+}
+
+#[test]
+fn converts_radians_and_checks_times() {
+    let mut clip = MotionClip::new("radians");
+    clip.units = AngleUnit::Radians;
+    clip.push(
+        Duration::ZERO,
+        [(Joint::LeftElbowYaw, std::f32::consts::FRAC_PI_2)]
+            .into_iter()
+            .collect(),
+    );
+    let degrees = clip.frames()[0].joints[&Joint::LeftElbowYaw];
+    assert!((degrees - 90.0).abs() < 1e-4);
+
+    clip.push(Duration::ZERO, Default::default());
+    assert!(clip.validate().is_err());
+
+    // Times too far out to play are refused rather than overflowing
+    clip.frames[1].time = 1e30;
+    assert!(clip.validate().is_err());
+    assert_eq!(clip.duration(), Duration::MAX);
+    assert!(clip.trajectory(Interpolation::Linear).is_err());
+}
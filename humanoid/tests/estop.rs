@@ -0,0 +1,96 @@
+use std::{collections::BTreeMap, time::Duration};
+
+use humanoid::{Frame, Halt, Humanoid, Joint, Runtime, SimulatedHumanoid};
+
+fn frame(value: f32) -> Frame {
+    Frame {
+        joints: BTreeMap::from([(Joint::LeftElbowYaw, value)]),
+    }
+}
+
+#[tokio::test]
+async fn emergency_stop_cuts_torque_while_the_robot_is_locked() {
+    let sim = SimulatedHumanoid::default();
+    let runtime = Runtime::new(sim.clone());
+    let queue = runtime.queue();
+    queue.overwrite(frame(10.0)).unwrap();
+
+    // As if the control loop were stuck in a command
+    let robot = runtime.lock().await;
+    assert!(queue.emergency_stop(Halt::EmergencyStop).await.unwrap());
+    assert!(!sim.torque_enabled());
+    assert_eq!(queue.halted(), Some(Halt::EmergencyStop));
+    assert_eq!(queue.current(), None);
+
+    // Latched, a second stop keeps the first cause
+    assert!(!queue
+        .emergency_stop(Halt::Watchdog {
+            last_frame_secs: 1.0
+        })
+        .await
+        .unwrap());
+    assert_eq!(queue.halted(), Some(Halt::EmergencyStop));
+    drop(robot);
+
+    assert_eq!(queue.reset_halt(), Some(Halt::EmergencyStop));
+    runtime.lock().await.set_torque_enabled(true).await.unwrap();
+    assert!(sim.torque_enabled());
+}
+
+#[tokio::test(start_paused = true)]
+async fn watchdog_stops_the_robot_when_frames_stop() {
+    let sim = SimulatedHumanoid::default();
+    let runtime = Runtime::new(sim.clone());
+    let control_loop = runtime.run(50.0).unwrap();
+    let watchdog = runtime.watch_frames(Duration::from_millis(200));
+    let queue = runtime.queue();
+
+    // Not armed until the first frame
+    tokio::time::sleep(Duration::from_secs(1)).await;
+    assert_eq!(queue.halted(), None);
+
+    for i in 0..10 {
+        queue.overwrite(frame(i as f32)).unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    assert_eq!(queue.halted(), None);
+
+    tokio::time::sleep(Duration::from_millis(400)).await;
+    let Some(Halt::Watchdog { last_frame_secs }) = queue.halted() else {
+        panic!("expected a watchdog halt, got {:?}", queue.halted());
+    };
+    assert!(last_frame_secs > 0.2);
+    assert!(!sim.torque_enabled());
+
+    // Disarmed again by the reset
+    queue.reset_halt();
+    tokio::time::sleep(Duration::from_secs(1)).await;
+    assert_eq!(queue.halted(), None);
+    assert!(sim.torque_enabled());
+
+    watchdog.stop().await.unwrap();
+    control_loop.stop().await.unwrap();
+}
+
+#[tokio::test(start_paused = true)]
+async fn watchdog_waits_for_queued_frames_to_drain() {
+    let sim = SimulatedHumanoid::default();
+    let runtime = Runtime::new(sim.clone());
+    let control_loop = runtime.run(50.0).unwrap();
+    let watchdog = runtime.watch_frames(Duration::from_millis(200));
+    let queue = runtime.queue();
+
+    // A second of frames at 50 Hz, pushed at once
+    for i in 0..50 {
+        queue.push(frame(i as f32)).unwrap();
+    }
+    tokio::time::sleep(Duration::from_millis(900)).await;
+    assert_eq!(queue.halted(), None);
+    assert!(!queue.is_empty());
+
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    assert!(matches!(queue.halted(), Some(Halt::Watchdog { .. })));
+
+    watchdog.stop().await.unwrap();
+    control_loop.stop().await.unwrap();
+}
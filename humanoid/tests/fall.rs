@@ -0,0 +1,95 @@
+use std::{collections::BTreeMap, time::Duration};
+
+use humanoid::{
+    EulerAngles, FallConfig, FallDetector, FallReason, Frame, Halt, ImuReading, Joint, Quaternion,
+    QueueError, Runtime, SimulatedHumanoid, STANDARD_GRAVITY,
+};
+
+fn reading(orientation: EulerAngles, accel: f32) -> ImuReading {
+    ImuReading {
+        accel: [0.0, 0.0, accel * STANDARD_GRAVITY],
+        orientation: Quaternion::from_euler(orientation),
+        ..Default::default()
+    }
+}
+
+fn frame(value: f32) -> Frame {
+    Frame {
+        joints: BTreeMap::from([(Joint::LeftElbowYaw, value)]),
+    }
+}
+
+#[test]
+fn detects_tilt_and_impacts() {
+    let detector = FallDetector::new(FallConfig::default());
+
+    assert_eq!(
+        detector.check(&reading(EulerAngles::new(10.0, -30.0, 90.0), 1.0)),
+        None
+    );
+
+    let event = detector
+        .check(&reading(EulerAngles::new(0.0, 60.0, 0.0), 1.0))
+        .unwrap();
+    assert_eq!(event.reason, FallReason::Tilt);
+    assert!((event.orientation.pitch - 60.0).abs() < 0.01);
+
+    // An impact while still upright
+    let event = detector
+        .check(&reading(EulerAngles::default(), 4.0))
+        .unwrap();
+    assert_eq!(event.reason, FallReason::Impact);
+    assert!((event.accel - 4.0).abs() < 0.01);
+}
+
+#[tokio::test(start_paused = true)]
+async fn fall_goes_limp_until_reset() {
+    let sim = SimulatedHumanoid::default();
+    let runtime = Runtime::new(sim.clone());
+    let control_loop = runtime.run(50.0).unwrap();
+    let monitor = runtime.detect_falls(FallConfig::default());
+    let queue = runtime.queue();
+    let mut halts = queue.subscribe_halts();
+
+    queue.overwrite(frame(10.0)).unwrap();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert_eq!(sim.target(Joint::LeftElbowYaw), Some(10.0));
+
+    sim.set_orientation(EulerAngles::new(0.0, 80.0, 0.0));
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    let Halt::Fall(event) = halts.try_recv().unwrap() else {
+        panic!("expected a fall");
+    };
+    assert_eq!(event.reason, FallReason::Tilt);
+    assert_eq!(queue.halted(), Some(Halt::Fall(event)));
+    assert!(!sim.torque_enabled());
+
+    // Frames sent while lying on the floor are refused
+    let received = queue.frames_received();
+    assert_eq!(
+        queue.overwrite(frame(20.0)),
+        Err(QueueError::Halted(Halt::Fall(event)))
+    );
+    assert!(matches!(
+        queue.push(frame(20.0)),
+        Err(QueueError::Halted(_))
+    ));
+    assert_eq!(queue.frames_received(), received);
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert_eq!(sim.target(Joint::LeftElbowYaw), Some(10.0));
+    assert_eq!(queue.current(), None);
+
+    // Picked up and reset, the robot holds still until told otherwise
+    sim.set_orientation(EulerAngles::default());
+    assert_eq!(queue.reset_halt(), Some(Halt::Fall(event)));
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(sim.torque_enabled());
+    assert_eq!(queue.halted(), None);
+
+    queue.overwrite(frame(20.0)).unwrap();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert_eq!(sim.target(Joint::LeftElbowYaw), Some(20.0));
+
+    monitor.stop().await.unwrap();
+    control_loop.stop().await.unwrap();
+}
@@ -0,0 +1,75 @@
+use std::time::Duration;
+
+use humanoid::{ComplementaryFilter, EulerAngles, Quaternion, STANDARD_GRAVITY};
+
+const TICK: Duration = Duration::from_millis(10);
+
+fn assert_close(actual: EulerAngles, expected: EulerAngles) {
+    let close = |a: f32, b: f32| (a - b).abs() < 0.05;
+    assert!(
+        close(actual.roll, expected.roll)
+            && close(actual.pitch, expected.pitch)
+            && close(actual.yaw, expected.yaw),
+        "{:?} != {:?}",
+        actual,
+        expected
+    );
+}
+
+#[test]
+fn quaternion_round_trips_euler_angles() {
+    for euler in [
+        EulerAngles::new(0.0, 0.0, 0.0),
+        EulerAngles::new(10.0, -5.0, 30.0),
+        EulerAngles::new(-120.0, 45.0, 170.0),
+    ] {
+        assert_close(Quaternion::from_euler(euler).to_euler(), euler);
+    }
+
+    // A quarter turn about z for a second is 90° of yaw
+    let turned = Quaternion::IDENTITY.integrate([0.0, 0.0, 90.0], Duration::from_secs(1));
+    assert_close(turned.to_euler(), EulerAngles::new(0.0, 0.0, 90.0));
+}
+
+#[test]
+fn complementary_filter_corrects_gyro_drift_towards_gravity() {
+    let mut filter = ComplementaryFilter::new(Duration::from_millis(500));
+    let level = [0.0, 0.0, STANDARD_GRAVITY];
+    assert_close(
+        filter.update_with_dt(level, [0.0; 3], TICK).to_euler(),
+        EulerAngles::default(),
+    );
+
+    // A gyro bias alone would roll the estimate 10° a second, gravity holds the error below
+    // bias times time constant
+    for _ in 0..300 {
+        filter.update_with_dt(level, [10.0, 0.0, 0.0], TICK);
+    }
+    let roll = filter.orientation().unwrap().to_euler().roll;
+    assert!(roll > 4.0 && roll < 5.0, "{}", roll);
+
+    // Tilted 20° with a still gyro, the estimate settles within a few time constants
+    let (s, c) = 20f32.to_radians().sin_cos();
+    let tilted = [0.0, s * STANDARD_GRAVITY, c * STANDARD_GRAVITY];
+    let after_one = (0..50)
+        .map(|_| filter.update_with_dt(tilted, [0.0; 3], TICK))
+        .last()
+        .unwrap()
+        .to_euler()
+        .roll;
+    assert!(after_one > 10.0 && after_one < 16.0, "{}", after_one);
+    for _ in 0..250 {
+        filter.update_with_dt(tilted, [0.0; 3], TICK);
+    }
+    assert_close(
+        filter.orientation().unwrap().to_euler(),
+        EulerAngles::new(20.0, 0.0, 0.0),
+    );
+
+    // Free fall says nothing about gravity, so only the gyro is followed
+    filter.update_with_dt([0.0; 3], [10.0, 0.0, 0.0], Duration::from_secs(1));
+    assert_close(
+        filter.orientation().unwrap().to_euler(),
+        EulerAngles::new(30.0, 0.0, 0.0),
+    );
+}
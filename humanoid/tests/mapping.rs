@@ -0,0 +1,53 @@
+use humanoid::{Joint, JointMapping, JointProfile};
+
+#[test]
+fn fit_maps_range_onto_servo_limits() {
+    let mut mapping = JointMapping::new(1, -45.0, 45.0);
+    mapping.fit(100.0, 280.0);
+    assert_eq!(mapping.to_servo(-45.0), 100.0);
+    assert_eq!(mapping.to_servo(0.0), 190.0);
+    assert_eq!(mapping.to_servo(90.0), 280.0);
+    assert_eq!(mapping.from_servo(190.0), 0.0);
+
+    mapping.inverted = true;
+    mapping.fit(100.0, 280.0);
+    assert_eq!(mapping.to_servo(45.0), 100.0);
+    assert_eq!(mapping.to_servo(-45.0), 280.0);
+    assert_eq!(mapping.from_servo(100.0), 45.0);
+}
+
+#[test]
+fn profile_from_toml() {
+    let profile = JointProfile::from_toml(
+        r#"
+name = "test"
+
+[joints.NeckYaw]
+id = 3
+min = -10.0
+max = 10.0
+offset = 5.0
+scale = 2.0
+inverted = true
+"#,
+    )
+    .unwrap();
+
+    let mapping = profile.mapping(Joint::NeckYaw).unwrap();
+    assert_eq!(mapping.to_servo(2.0), 1.0);
+    assert_eq!(profile.joint_for_id(3), Some(Joint::NeckYaw));
+    assert!(profile.mapping(Joint::NeckPitch).is_err());
+
+    let duplicate = r#"
+[joints.NeckYaw]
+id = 3
+min = 0.0
+max = 1.0
+
+[joints.NeckPitch]
+id = 3
+min = 0.0
+max = 1.0
+"#;
+    assert!(JointProfile::from_toml(duplicate).is_err());
+}
@@ -0,0 +1,242 @@
+use std::{sync::Arc, time::Duration};
+
+use humanoid::{
+    Halt, Humanoid, Joint, JointInfo, OvercurrentRule, ProtectionAction, ProtectionConfig,
+    ProtectionEvents, Runtime, ServoHealth, ServoProtection, ServoProtector, SimHealth,
+    SimulatedHumanoid, TelemetryLog, TelemetrySnapshot, TorqueRule,
+};
+use tokio::time::Instant;
+
+fn snapshot(temperature: f32, current: f32) -> TelemetrySnapshot {
+    TelemetrySnapshot {
+        time: 0,
+        servos: vec![ServoHealth {
+            joint: Joint::LeftElbowYaw,
+            id: 1,
+            temperature: Some(temperature),
+            current: Some(current),
+            voltage: None,
+        }],
+    }
+}
+
+#[test]
+fn rules_fire_once_and_lift_after_cooling_down() {
+    let mut protector = ServoProtector::new(ProtectionConfig {
+        overcurrent: Some(OvercurrentRule {
+            current: 2.0,
+            duration_secs: 3.0,
+        }),
+        ..Default::default()
+    });
+    let start = Instant::now();
+    let mut actions = |temperature, current, secs| {
+        protector
+            .check(
+                &snapshot(temperature, current),
+                start + Duration::from_secs(secs),
+            )
+            .into_iter()
+            .map(|event| event.action)
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(actions(60.0, 0.0, 0), vec![]);
+    assert_eq!(
+        actions(61.0, 0.0, 0),
+        vec![ProtectionAction::ReduceTorque { torque: 25.0 }]
+    );
+    assert_eq!(actions(71.0, 0.0, 0), vec![ProtectionAction::DisableJoint]);
+    assert_eq!(actions(71.0, 0.0, 0), vec![]);
+    // Lifted 5 degrees below each rule
+    assert_eq!(actions(66.0, 0.0, 0), vec![]);
+    assert_eq!(actions(64.0, 0.0, 0), vec![ProtectionAction::EnableJoint]);
+    assert_eq!(actions(56.0, 0.0, 0), vec![]);
+    assert_eq!(actions(54.0, 0.0, 0), vec![ProtectionAction::RestoreTorque]);
+
+    // Only sustained overcurrent counts
+    assert_eq!(actions(40.0, 3.0, 10), vec![]);
+    assert_eq!(actions(40.0, 1.0, 12), vec![]);
+    assert_eq!(actions(40.0, 3.0, 13), vec![]);
+    assert_eq!(actions(40.0, 3.0, 16), vec![ProtectionAction::Halt]);
+}
+
+#[tokio::test(start_paused = true)]
+async fn hot_servos_are_throttled_and_overcurrent_halts() {
+    let sim = SimulatedHumanoid::default().with_joints([
+        JointInfo::new(Joint::LeftElbowYaw, -90.0, 90.0),
+        JointInfo::new(Joint::RightElbowYaw, -90.0, 90.0),
+    ]);
+    let runtime = Runtime::new(sim.clone());
+    let telemetry = Arc::new(TelemetryLog::new(10));
+    let events = Arc::new(ProtectionEvents::new());
+    let poller = runtime.poll_telemetry(Duration::from_millis(100), telemetry.clone());
+    let protection = runtime.protect_servos(
+        ProtectionConfig {
+            overcurrent: Some(OvercurrentRule {
+                current: 2.0,
+                duration_secs: 0.5,
+            }),
+            ..Default::default()
+        },
+        &telemetry,
+        events.clone(),
+    );
+
+    sim.set_health(
+        Joint::LeftElbowYaw,
+        SimHealth {
+            temperature: 72.0,
+            ..Default::default()
+        },
+    );
+    tokio::time::sleep(Duration::from_millis(150)).await;
+    assert_eq!(sim.torque_limit(Joint::LeftElbowYaw), 25.0);
+    assert!(!sim.joint_torque_enabled(Joint::LeftElbowYaw));
+    assert!(sim.joint_torque_enabled(Joint::RightElbowYaw));
+    assert_eq!(events.count(), 2);
+
+    // Turning every servo back on doesn't revive the hot one for long
+    runtime.lock().await.set_torque_enabled(true).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(!sim.joint_torque_enabled(Joint::LeftElbowYaw));
+    assert_eq!(events.count(), 2);
+
+    sim.set_health(
+        Joint::RightElbowYaw,
+        SimHealth {
+            current: 3.0,
+            ..Default::default()
+        },
+    );
+    tokio::time::sleep(Duration::from_millis(800)).await;
+    let Some(Halt::Overcurrent { joint, current }) = runtime.queue().halted() else {
+        panic!(
+            "expected an overcurrent halt, got {:?}",
+            runtime.queue().halted()
+        );
+    };
+    assert_eq!((joint, current), (Joint::RightElbowYaw, 3.0));
+    assert_eq!(
+        events.events().last().unwrap().action,
+        ProtectionAction::Halt
+    );
+
+    protection.stop().await.unwrap();
+    poller.stop().await.unwrap();
+}
+
+#[tokio::test(start_paused = true)]
+async fn throttling_outlives_a_halt_and_lifts_to_nominal_torque() {
+    let sim = SimulatedHumanoid::default().with_joints([JointInfo::new(
+        Joint::LeftElbowYaw,
+        -90.0,
+        90.0,
+    )]);
+    let runtime = Runtime::new(sim.clone());
+    let telemetry = Arc::new(TelemetryLog::new(10));
+    let events = Arc::new(ProtectionEvents::new());
+    sim.set_health(
+        Joint::LeftElbowYaw,
+        SimHealth {
+            temperature: 65.0,
+            ..Default::default()
+        },
+    );
+    let poller = runtime.poll_telemetry(Duration::from_secs(10), telemetry.clone());
+    let protection =
+        runtime.protect_servos(ProtectionConfig::default(), &telemetry, events.clone());
+    let control = runtime.run(50.0).unwrap();
+
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert_eq!(sim.torque_limit(Joint::LeftElbowYaw), 25.0);
+
+    // Enabling torque after the reset restores full torque, and the throttle comes straight
+    // back rather than with the next snapshot
+    runtime.queue().halt(Halt::EmergencyStop);
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    runtime.queue().reset_halt();
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert!(sim.joint_torque_enabled(Joint::LeftElbowYaw));
+    assert_eq!(sim.torque_limit(Joint::LeftElbowYaw), 25.0);
+    assert_eq!(events.count(), 1);
+
+    sim.set_health(
+        Joint::LeftElbowYaw,
+        SimHealth {
+            temperature: 40.0,
+            ..Default::default()
+        },
+    );
+    tokio::time::sleep(Duration::from_secs(10)).await;
+    assert_eq!(
+        events.events().last().unwrap().action,
+        ProtectionAction::RestoreTorque
+    );
+    assert_eq!(sim.torque_limit(Joint::LeftElbowYaw), sim.nominal_torque());
+
+    control.stop().await.unwrap();
+    protection.stop().await.unwrap();
+    poller.stop().await.unwrap();
+}
+
+#[tokio::test(start_paused = true)]
+async fn throttling_never_raises_torque_above_nominal() {
+    let sim = SimulatedHumanoid::default()
+        .with_joints([JointInfo::new(Joint::LeftElbowYaw, -90.0, 90.0)])
+        .with_nominal_torque(50.0);
+    let runtime = Runtime::new(sim.clone());
+    let telemetry = Arc::new(TelemetryLog::new(10));
+    let events = Arc::new(ProtectionEvents::new());
+    sim.set_health(
+        Joint::LeftElbowYaw,
+        SimHealth {
+            temperature: 65.0,
+            ..Default::default()
+        },
+    );
+    let poller = runtime.poll_telemetry(Duration::from_secs(10), telemetry.clone());
+    let protection = runtime.protect_servos(
+        ProtectionConfig {
+            reduce_torque: Some(TorqueRule {
+                temperature: 60.0,
+                torque: 75.0,
+            }),
+            ..Default::default()
+        },
+        &telemetry,
+        events.clone(),
+    );
+    let control = runtime.run(50.0).unwrap();
+
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert_eq!(sim.torque_limit(Joint::LeftElbowYaw), 50.0);
+    assert_eq!(
+        events.events()[0].action,
+        ProtectionAction::ReduceTorque { torque: 50.0 }
+    );
+
+    runtime.queue().halt(Halt::EmergencyStop);
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    runtime.queue().reset_halt();
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert_eq!(sim.torque_limit(Joint::LeftElbowYaw), 50.0);
+
+    control.stop().await.unwrap();
+    protection.stop().await.unwrap();
+    poller.stop().await.unwrap();
+}
+
+#[test]
+fn rejects_overcurrent_durations_out_of_range() {
+    for duration_secs in [-1.0, f32::NAN, f32::INFINITY, 1e30] {
+        let config = ProtectionConfig {
+            overcurrent: Some(OvercurrentRule {
+                current: 2.0,
+                duration_secs,
+            }),
+            ..Default::default()
+        };
+        assert!(config.validate().is_err(), "accepted {duration_secs}");
+    }
+}
@@ -0,0 +1,212 @@
+use std::{collections::BTreeMap, time::Duration};
+
+use humanoid::{
+    ControlLoop, Frame, Halt, Humanoid, Instrumented, Joint, JointInfo, JointLimits, QueueError,
+    Runtime, SafetyConfig, SafetyLimits, SimCall, SimulatedHumanoid, UnsupportedJoints,
+};
+
+fn frame(joints: &[(Joint, f32)]) -> Frame {
+    Frame {
+        joints: joints.iter().copied().collect::<BTreeMap<_, _>>(),
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn sim_slews_toward_target() {
+    let mut sim = SimulatedHumanoid::new(10.0);
+    sim.set_joint(Joint::LeftShoulderPitch, 20.0).await.unwrap();
+
+    tokio::time::advance(Duration::from_secs(1)).await;
+    assert_eq!(sim.position(Joint::LeftShoulderPitch), Some(10.0));
+    assert!(!sim.is_settled());
+
+    tokio::time::advance(Duration::from_secs(5)).await;
+    let joint = sim.get_joint(Joint::LeftShoulderPitch).await.unwrap();
+    assert_eq!(joint.position, 20.0);
+    assert_eq!(joint.speed, 0.0);
+    assert!(sim.is_settled());
+}
+
+#[tokio::test(start_paused = true)]
+async fn step_sends_queued_frames_in_order() {
+    let sim = SimulatedHumanoid::default();
+    let mut runtime = Runtime::new(sim.clone());
+
+    let first = frame(&[(Joint::LeftShoulderPitch, 10.0)]);
+    let second = frame(&[(Joint::LeftShoulderPitch, 20.0), (Joint::NeckYaw, 5.0)]);
+    runtime.push_frame(first.clone()).unwrap();
+    runtime.push_frame(second.clone()).unwrap();
+
+    // The first step promotes the first frame, sends it and advances to the second.
+    assert!(runtime.step().await.unwrap());
+    assert!(!runtime.step().await.unwrap());
+    assert_eq!(runtime.queue_len(), 0);
+
+    assert_eq!(
+        sim.calls(),
+        vec![
+            SimCall::SetJoints(first.joints),
+            SimCall::SetJoints(second.joints.clone()),
+        ]
+    );
+    assert!(runtime.is_complete(second));
+}
+
+#[tokio::test(start_paused = true)]
+async fn overwrite_replaces_pending_frames() {
+    let sim = SimulatedHumanoid::default();
+    let mut runtime = Runtime::new(sim.clone());
+
+    runtime
+        .push_frame(frame(&[(Joint::NeckPitch, 1.0)]))
+        .unwrap();
+    runtime
+        .push_frame(frame(&[(Joint::NeckPitch, 2.0)]))
+        .unwrap();
+    runtime
+        .overwrite(frame(&[(Joint::NeckPitch, 3.0)]))
+        .unwrap();
+    assert_eq!(runtime.queue_len(), 0);
+
+    runtime.step().await.unwrap();
+    assert_eq!(sim.target(Joint::NeckPitch), Some(3.0));
+}
+
+#[test]
+fn push_all_queues_every_frame_or_none() {
+    let sim =
+        SimulatedHumanoid::default().with_joints([JointInfo::new(Joint::NeckYaw, -90.0, 90.0)]);
+    let runtime = Runtime::new(sim);
+    let queue = runtime.queue();
+
+    let err = queue
+        .push_all(vec![
+            frame(&[(Joint::NeckYaw, 1.0)]),
+            frame(&[(Joint::NeckPitch, 1.0)]),
+        ])
+        .unwrap_err();
+    assert!(matches!(err, QueueError::Unsupported(_)));
+    assert_eq!(queue.len(), 0);
+
+    queue
+        .push_all(vec![
+            frame(&[(Joint::NeckYaw, 1.0)]),
+            frame(&[(Joint::NeckYaw, 2.0)]),
+        ])
+        .unwrap();
+    assert_eq!(queue.len(), 2);
+    assert_eq!(queue.frames_received(), 2);
+
+    queue.halt(Halt::EmergencyStop);
+    assert_eq!(queue.len(), 0);
+    let err = queue
+        .push_all(vec![frame(&[(Joint::NeckYaw, 3.0)])])
+        .unwrap_err();
+    assert_eq!(err, QueueError::Halted(Halt::EmergencyStop));
+    assert_eq!(queue.len(), 0);
+}
+
+#[tokio::test(start_paused = true)]
+async fn run_only_resends_on_change_or_keepalive() {
+    let sim = SimulatedHumanoid::default();
+    let runtime = Runtime::new(sim.clone());
+    let handle = runtime
+        .run_with(ControlLoop {
+            rate_hz: 10.0,
+            keepalive: Duration::from_secs(1),
+        })
+        .unwrap();
+
+    runtime.overwrite(frame(&[(Joint::NeckYaw, 1.0)])).unwrap();
+    tokio::time::sleep(Duration::from_millis(550)).await;
+    assert_eq!(sim.calls().len(), 1);
+
+    tokio::time::sleep(Duration::from_millis(600)).await;
+    assert_eq!(sim.calls().len(), 2);
+
+    runtime.overwrite(frame(&[(Joint::NeckYaw, 2.0)])).unwrap();
+    tokio::time::sleep(Duration::from_millis(150)).await;
+    assert_eq!(sim.calls().len(), 3);
+    assert_eq!(sim.target(Joint::NeckYaw), Some(2.0));
+
+    assert!(handle.stats().ticks() >= 12);
+    assert_eq!(handle.stats().commands(), 3);
+    assert_eq!(handle.stats().missed_deadlines(), 0);
+    assert_eq!(runtime.queue().frames_received(), 2);
+    // The sim answers instantly, so steps take no more than the timer's millisecond resolution
+    let latency = handle.stats().step_latency();
+    assert_eq!(latency.count(), handle.stats().ticks());
+    assert_eq!(latency.buckets()[1], (0.001, latency.count()));
+
+    handle.stop().await.unwrap();
+    runtime.overwrite(frame(&[(Joint::NeckYaw, 3.0)])).unwrap();
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    assert_eq!(sim.calls().len(), 3);
+}
+
+#[tokio::test]
+async fn run_refuses_rates_without_a_period() {
+    let runtime = Runtime::new(SimulatedHumanoid::default());
+    for rate_hz in [0.0, -50.0, f32::NAN, f32::INFINITY, 1e-40, 1e30] {
+        assert!(ControlLoop::new(rate_hz).period().is_err(), "{rate_hz}");
+        assert!(runtime.run(rate_hz).is_err(), "{rate_hz}");
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn run_resends_until_velocity_limited_joints_arrive() {
+    let sim = SimulatedHumanoid::default();
+    let config = ControlLoop {
+        rate_hz: 10.0,
+        keepalive: Duration::from_secs(10),
+    };
+    let mut limits = SafetyConfig::default();
+    limits.joints.insert(
+        Joint::NeckYaw,
+        JointLimits {
+            min: -90.0,
+            max: 90.0,
+            max_velocity: Some(50.0),
+            max_acceleration: None,
+        },
+    );
+    let robot = SafetyLimits::new(sim.clone(), limits).with_period(config.period().unwrap());
+    let runtime = Runtime::new(robot);
+    let handle = runtime.run_with(config).unwrap();
+
+    runtime.overwrite(frame(&[(Joint::NeckYaw, 0.0)])).unwrap();
+    tokio::time::sleep(Duration::from_millis(150)).await;
+
+    // 5 degrees per tick, so the joint arrives on the fourth tick and then is left alone
+    runtime.overwrite(frame(&[(Joint::NeckYaw, 20.0)])).unwrap();
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    assert_eq!(sim.target(Joint::NeckYaw), Some(15.0));
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    assert_eq!(sim.target(Joint::NeckYaw), Some(20.0));
+    assert_eq!(handle.stats().commands(), 5);
+
+    handle.stop().await.unwrap();
+}
+
+#[tokio::test]
+async fn instrumented_robot_counts_failed_calls() {
+    let sim =
+        SimulatedHumanoid::default().with_joints([JointInfo::new(Joint::NeckYaw, -90.0, 90.0)]);
+    let mut robot = Instrumented::new(sim.clone(), |_| true);
+    let errors = robot.errors();
+
+    robot.set_joint(Joint::NeckYaw, 10.0).await.unwrap();
+    assert!(robot.set_joint(Joint::NeckPitch, 10.0).await.is_err());
+    assert!(robot.set_joint(Joint::NeckPitch, 10.0).await.is_err());
+    assert!(robot.get_joint(Joint::LeftElbowYaw).await.is_err());
+
+    assert_eq!(errors.get("set_joint"), 2);
+    assert_eq!(errors.get("get_joint"), 1);
+    assert_eq!(errors.get("set_joints"), 0);
+    assert_eq!(errors.counts().len(), 2);
+
+    // Errors the filter leaves out fail the call without being counted
+    let mut robot = Instrumented::new(sim, |e| e.downcast_ref::<UnsupportedJoints>().is_none());
+    assert!(robot.set_joint(Joint::NeckPitch, 10.0).await.is_err());
+    assert!(robot.errors().counts().is_empty());
+}
@@ -0,0 +1,162 @@
+use std::time::Duration;
+
+use humanoid::{
+    write_config, Humanoid, Joint, JointLimits, LimitKind, SafetyConfig, SafetyLimits,
+    SimulatedHumanoid,
+};
+
+fn limits(min: f32, max: f32) -> JointLimits {
+    JointLimits {
+        min,
+        max,
+        max_velocity: None,
+        max_acceleration: None,
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn clamps_position_and_reports() {
+    let sim = SimulatedHumanoid::default();
+    let mut config = SafetyConfig::default();
+    config.joints.insert(Joint::NeckYaw, limits(-30.0, 30.0));
+    let mut robot = SafetyLimits::new(sim.clone(), config);
+
+    robot.set_joint(Joint::NeckYaw, 45.0).await.unwrap();
+    robot.set_joint(Joint::NeckPitch, 45.0).await.unwrap();
+
+    assert_eq!(sim.target(Joint::NeckYaw), Some(30.0));
+    assert_eq!(sim.target(Joint::NeckPitch), Some(45.0));
+
+    let violations = robot.violations();
+    assert_eq!(violations.len(), 1);
+    assert_eq!(violations[0].kind, LimitKind::Position);
+    assert_eq!(violations[0].requested, 45.0);
+    assert_eq!(violations[0].applied, 30.0);
+}
+
+#[tokio::test(start_paused = true)]
+async fn limits_velocity_and_acceleration_per_control_period() {
+    let sim = SimulatedHumanoid::default();
+    let config = SafetyConfig {
+        default: Some(JointLimits {
+            max_velocity: Some(100.0),
+            max_acceleration: Some(500.0),
+            ..limits(-90.0, 90.0)
+        }),
+        ..Default::default()
+    };
+    let mut robot = SafetyLimits::new(sim.clone(), config).with_period(Duration::from_millis(100));
+
+    robot.set_joint(Joint::LeftElbowYaw, 0.0).await.unwrap();
+
+    // One 0.1s period at rest with 500 deg/s^2 allows at most 50 deg/s, or 5 degrees
+    robot.set_joint(Joint::LeftElbowYaw, 80.0).await.unwrap();
+    assert_eq!(sim.target(Joint::LeftElbowYaw), Some(5.0));
+
+    // Now the velocity limit applies before the acceleration limit does
+    tokio::time::advance(Duration::from_millis(100)).await;
+    robot.set_joint(Joint::LeftElbowYaw, 80.0).await.unwrap();
+    assert_eq!(sim.target(Joint::LeftElbowYaw), Some(15.0));
+
+    // Time spent idle doesn't allow a bigger step, and the joint starts again from rest
+    tokio::time::advance(Duration::from_secs(10)).await;
+    robot.set_joint(Joint::LeftElbowYaw, 80.0).await.unwrap();
+    assert_eq!(sim.target(Joint::LeftElbowYaw), Some(20.0));
+
+    let kinds: Vec<_> = robot.violations().iter().map(|v| v.kind).collect();
+    assert_eq!(
+        kinds,
+        vec![
+            LimitKind::Velocity,
+            LimitKind::Acceleration,
+            LimitKind::Velocity,
+            LimitKind::Velocity,
+            LimitKind::Acceleration,
+        ]
+    );
+    assert_eq!(robot.violation_count(), 5);
+}
+
+#[tokio::test(start_paused = true)]
+async fn momentum_never_carries_a_joint_past_its_target_or_range() {
+    let sim = SimulatedHumanoid::default();
+    let config = SafetyConfig {
+        default: Some(JointLimits {
+            max_acceleration: Some(100.0),
+            ..limits(-10.0, 10.0)
+        }),
+        ..Default::default()
+    };
+    let mut robot = SafetyLimits::new(sim.clone(), config);
+
+    robot.set_joint(Joint::LeftElbowYaw, -10.0).await.unwrap();
+    for _ in 0..200 {
+        tokio::time::advance(Duration::from_millis(20)).await;
+        robot.set_joint(Joint::LeftElbowYaw, 10.0).await.unwrap();
+        let target = sim.target(Joint::LeftElbowYaw).unwrap();
+        assert!((-10.0..=10.0).contains(&target), "commanded {target}");
+    }
+    assert_eq!(sim.target(Joint::LeftElbowYaw), Some(10.0));
+
+    // Turning back from the far end starts from rest
+    tokio::time::advance(Duration::from_millis(20)).await;
+    robot.set_joint(Joint::LeftElbowYaw, -10.0).await.unwrap();
+    assert!((sim.target(Joint::LeftElbowYaw).unwrap() - 9.96).abs() < 1e-4);
+}
+
+#[test]
+fn rejects_non_finite_limits() {
+    let mut config = SafetyConfig::default();
+    config.joints.insert(Joint::NeckYaw, limits(f32::NAN, 10.0));
+    assert!(config.validate().is_err());
+
+    config.joints.insert(
+        Joint::NeckYaw,
+        JointLimits {
+            max_velocity: Some(f32::NAN),
+            ..limits(-10.0, 10.0)
+        },
+    );
+    assert!(config.validate().is_err());
+}
+
+#[test]
+fn loads_config_from_toml_and_json() {
+    let dir = std::env::temp_dir().join(format!("safety-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let toml = dir.join("safety.toml");
+    std::fs::write(
+        &toml,
+        r#"
+[default]
+min = -90.0
+max = 90.0
+
+[joints.LeftShoulderPitch]
+min = -45.0
+max = 45.0
+max_velocity = 180.0
+"#,
+    )
+    .unwrap();
+
+    let config = SafetyConfig::load(&toml).unwrap();
+    assert_eq!(config.limits(Joint::NeckYaw), Some(&limits(-90.0, 90.0)));
+    assert_eq!(
+        config
+            .limits(Joint::LeftShoulderPitch)
+            .unwrap()
+            .max_velocity,
+        Some(180.0)
+    );
+
+    let json = dir.join("safety.json");
+    write_config(&json, &config).unwrap();
+    assert_eq!(SafetyConfig::load(&json).unwrap(), config);
+
+    std::fs::write(&toml, "[default]\nmin = 10.0\nmax = -10.0\n").unwrap();
+    assert!(SafetyConfig::load(&toml).is_err());
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
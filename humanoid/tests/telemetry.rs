@@ -0,0 +1,72 @@
+use std::{sync::Arc, time::Duration};
+
+use humanoid::{
+    Joint, JointInfo, Runtime, ServoHealth, SimHealth, SimulatedHumanoid, TelemetryLog,
+    TelemetrySnapshot,
+};
+
+fn snapshot(time: u64) -> TelemetrySnapshot {
+    TelemetrySnapshot {
+        time,
+        servos: vec![ServoHealth {
+            joint: Joint::LeftElbowYaw,
+            id: 1,
+            temperature: Some(time as f32),
+            current: None,
+            voltage: None,
+        }],
+    }
+}
+
+#[test]
+fn telemetry_log_keeps_the_latest_snapshots() {
+    let log = TelemetryLog::for_history(Duration::from_secs(3), Duration::from_millis(1000));
+    assert_eq!(log.capacity(), 3);
+    assert_eq!(log.latest(), None);
+
+    for time in 1..=5 {
+        log.push(snapshot(time));
+    }
+    assert_eq!(log.latest(), Some(snapshot(5)));
+    assert_eq!(log.history(), vec![snapshot(3), snapshot(4), snapshot(5)]);
+    assert_eq!(log.since(3), vec![snapshot(4), snapshot(5)]);
+}
+
+#[tokio::test(start_paused = true)]
+async fn telemetry_is_polled_from_the_robot() {
+    let sim = SimulatedHumanoid::default().with_joints([
+        JointInfo::new(Joint::LeftElbowYaw, -90.0, 90.0),
+        JointInfo::new(Joint::RightElbowYaw, -90.0, 90.0),
+    ]);
+    sim.set_health(
+        Joint::RightElbowYaw,
+        SimHealth {
+            temperature: 70.0,
+            current: 1.5,
+            voltage: 11.1,
+        },
+    );
+    let runtime = Runtime::new(sim.clone());
+    let log = Arc::new(TelemetryLog::new(10));
+    let mut updates = log.subscribe();
+
+    let poller = runtime.poll_telemetry(Duration::from_millis(100), log.clone());
+    let first = updates.recv().await.unwrap();
+    assert_eq!(first.servos.len(), 2);
+    assert_eq!(first.servos[0].temperature, Some(30.0));
+    assert_eq!(
+        first.servos[1],
+        ServoHealth {
+            joint: Joint::RightElbowYaw,
+            id: Joint::RightElbowYaw.into(),
+            temperature: Some(70.0),
+            current: Some(1.5),
+            voltage: Some(11.1),
+        }
+    );
+
+    tokio::time::sleep(Duration::from_millis(450)).await;
+    assert_eq!(log.history().len(), 5);
+
+    poller.stop().await.unwrap();
+}
@@ -0,0 +1,145 @@
+use std::{collections::BTreeMap, time::Duration};
+
+use humanoid::{
+    ControlLoop, Frame, Interpolation, Joint, Keyframe, PlaybackOptions, Runtime, SimCall,
+    SimulatedHumanoid, Trajectory,
+};
+
+fn keyframe(ms: u64, value: f32) -> Keyframe {
+    Keyframe {
+        time: Duration::from_millis(ms),
+        frame: Frame {
+            joints: BTreeMap::from([(Joint::LeftElbowYaw, value)]),
+        },
+    }
+}
+
+fn sample(trajectory: &Trajectory, ms: u64) -> f32 {
+    trajectory.sample(Duration::from_millis(ms)).joints[&Joint::LeftElbowYaw]
+}
+
+#[test]
+fn linear_and_minimum_jerk() {
+    let keyframes = vec![keyframe(0, 0.0), keyframe(1000, 100.0)];
+
+    let linear = Trajectory::new(keyframes.clone(), Interpolation::Linear).unwrap();
+    assert_eq!(sample(&linear, 250), 25.0);
+    assert_eq!(sample(&linear, 2000), 100.0);
+
+    let min_jerk = Trajectory::new(keyframes, Interpolation::MinimumJerk).unwrap();
+    assert_eq!(sample(&min_jerk, 500), 50.0);
+    assert!(sample(&min_jerk, 250) < 25.0);
+    assert!(sample(&min_jerk, 750) > 75.0);
+}
+
+#[test]
+fn cubic_spline_passes_through_keyframes() {
+    let keyframes = vec![
+        keyframe(0, 0.0),
+        keyframe(500, 40.0),
+        keyframe(1500, -10.0),
+        keyframe(2000, 0.0),
+    ];
+    let spline = Trajectory::new(keyframes.clone(), Interpolation::CubicSpline).unwrap();
+
+    for keyframe in &keyframes {
+        let expected = keyframe.frame.joints[&Joint::LeftElbowYaw];
+        let actual = spline.sample(keyframe.time).joints[&Joint::LeftElbowYaw];
+        assert!((actual - expected).abs() < 1e-4, "{actual} != {expected}");
+    }
+
+    // Unlike linear interpolation the spline overshoots the keyframe before turning around
+    assert!(sample(&spline, 600) > 40.0);
+}
+
+#[test]
+fn rejects_unordered_keyframes() {
+    assert!(Trajectory::new(vec![], Interpolation::Linear).is_err());
+    assert!(Trajectory::new(
+        vec![keyframe(10, 0.0), keyframe(5, 1.0)],
+        Interpolation::Linear
+    )
+    .is_err());
+}
+
+#[tokio::test(start_paused = true)]
+async fn runtime_plays_trajectory_at_control_rate() {
+    let sim = SimulatedHumanoid::default();
+    let runtime = Runtime::new(sim.clone());
+
+    let trajectory = Trajectory::new(
+        vec![keyframe(0, 0.0), keyframe(1000, 100.0)],
+        Interpolation::Linear,
+    )
+    .unwrap();
+    runtime.play(trajectory).unwrap();
+    let handle = runtime.run_with(ControlLoop::new(10.0)).unwrap();
+
+    tokio::time::sleep(Duration::from_millis(1500)).await;
+    assert!(!runtime.is_playing());
+
+    let setpoints = sim
+        .calls()
+        .into_iter()
+        .map(|call| match call {
+            SimCall::SetJoints(joints) => joints[&Joint::LeftElbowYaw],
+            SimCall::SetJoint(_, value) => value,
+        })
+        .collect::<Vec<_>>();
+
+    assert_eq!(setpoints.len(), 11);
+    assert_eq!(setpoints.first(), Some(&0.0));
+    assert_eq!(setpoints.last(), Some(&100.0));
+    assert!(setpoints.windows(2).all(|w| w[0] < w[1]));
+
+    handle.stop().await.unwrap();
+}
+
+#[tokio::test(start_paused = true)]
+async fn playback_loops_speeds_up_and_pauses() {
+    let runtime = Runtime::new(SimulatedHumanoid::default());
+    let queue = runtime.queue();
+
+    let trajectory = Trajectory::new(
+        vec![keyframe(0, 0.0), keyframe(1000, 100.0)],
+        Interpolation::Linear,
+    )
+    .unwrap();
+    runtime
+        .play_with(
+            trajectory,
+            PlaybackOptions {
+                loops: 2,
+                speed: 2.0,
+            },
+        )
+        .unwrap();
+    let handle = runtime.run_with(ControlLoop::new(10.0)).unwrap();
+
+    // Twice as fast, so a quarter of a second in is halfway through the first loop
+    tokio::time::sleep(Duration::from_millis(250)).await;
+    let status = queue.playback().unwrap();
+    assert!((0.4..=0.6).contains(&status.position));
+    assert_eq!(status.completed_loops, 0);
+
+    assert!(queue.pause());
+    let held = queue.playback().unwrap().position;
+    tokio::time::sleep(Duration::from_millis(1000)).await;
+    let status = queue.playback().unwrap();
+    assert!(status.paused);
+    assert_eq!(status.position, held);
+
+    assert!(queue.resume());
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    assert_eq!(queue.playback().unwrap().completed_loops, 1);
+
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    assert!(!runtime.is_playing());
+    assert!(!queue.pause());
+    assert_eq!(
+        runtime.current_frame().unwrap().joints[&Joint::LeftElbowYaw],
+        100.0
+    );
+
+    handle.stop().await.unwrap();
+}
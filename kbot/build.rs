@@ -26,7 +26,8 @@ fn main() {
 
     // Configure and compile Protobuf files
     tonic_build::configure()
-        .build_server(false)
+        // The server side is only needed by the simulator
+        .build_server(env::var_os("CARGO_FEATURE_SIM").is_some())
         .build_client(true)
         .build_transport(true)
         .out_dir(out_dir.join("kos"))
@@ -0,0 +1,14 @@
+use kbot::sim::SimKos;
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let addr = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "0.0.0.0:50051".to_string())
+        .parse()?;
+
+    println!("Simulated KOS listening on grpc://{}", addr);
+    SimKos::new().serve(addr).await?;
+
+    Ok(())
+}
@@ -1,25 +1,39 @@
 #![allow(unknown_lints)]
 #![allow(clippy::doc_lazy_continuation)]
+#![allow(clippy::doc_overindented_list_items)]
 
 // pub mod config;
 mod grpc_interface;
+#[cfg(feature = "sim")]
+pub mod sim;
 // pub mod hal;
 // pub mod services;
 // pub mod telemetry;
 // pub mod telemetry_types;
 
-use std::collections::BTreeMap;
+use std::{collections::BTreeMap, time::Duration};
 
 pub use grpc_interface::google as google_proto;
 pub use grpc_interface::kos as kos_proto;
 use grpc_interface::kos::actuator::{ActuatorCommand, CommandActuatorsRequest};
+/// The K-Scale recording format, for reading and writing `.krec` episodes.
+pub use krec;
 
 use num_enum::{IntoPrimitive, TryFromPrimitive};
 use serde::{Deserialize, Serialize};
-use strum::IntoEnumIterator;
 
-use crate::kos_proto::actuator::GetActuatorsStateRequest;
-use tokio::sync::Mutex;
+use crate::google_proto::longrunning::{
+    operation, operations_client::OperationsClient, GetOperationRequest,
+};
+use crate::kos_proto::actuator::{
+    CalibrateActuatorRequest, ConfigureActuatorRequest, GetActuatorsStateRequest,
+};
+
+/// How often [`Client::calibrate_actuators`] checks on running calibrations.
+pub const CALIBRATION_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How long [`Client::calibrate_actuators`] waits for every calibration to finish.
+pub const CALIBRATION_TIMEOUT: Duration = Duration::from_secs(30);
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ServoInfo {
@@ -45,6 +59,44 @@ pub struct TorqueEnableSetting {
     pub enable: bool,
 }
 
+/// Orientation reported by the IMU, in degrees.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ImuOrientation {
+    pub roll: f64,
+    pub pitch: f64,
+    pub yaw: f64,
+}
+
+/// Raw IMU values: acceleration in m/s² and angular velocity in degrees per second.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ImuValues {
+    pub accel: [f64; 3],
+    pub gyro: [f64; 3],
+    /// Only reported by IMUs with a magnetometer.
+    pub mag: Option<[f64; 3]>,
+}
+
+/// Orientation reported by the IMU as a unit quaternion.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ImuQuaternion {
+    pub w: f64,
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+}
+
+/// Health reported by an actuator. Fields KOS leaves out are `None`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ActuatorHealth {
+    pub id: u32,
+    /// Degrees Celsius.
+    pub temperature: Option<f64>,
+    /// Amperes.
+    pub current: Option<f32>,
+    /// Volts.
+    pub voltage: Option<f32>,
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct JointPosition {
     pub id: ActuatorId,
@@ -101,6 +153,9 @@ pub enum Error {
 
     #[snafu(display("Invalid servo id"))]
     ServoNotFound,
+
+    #[snafu(display("Calibration {name} did not finish within {timeout:?}"))]
+    CalibrationTimeout { name: String, timeout: Duration },
 }
 
 impl From<tonic::Status> for Error {
@@ -111,13 +166,14 @@ impl From<tonic::Status> for Error {
     }
 }
 
+/// Cheap to clone, clones share the connection.
+#[derive(Clone)]
 pub struct Client {
-    inner: Mutex<
-        kos_proto::actuator::actuator_service_client::ActuatorServiceClient<
-            tonic::transport::Channel,
-        >,
+    inner: kos_proto::actuator::actuator_service_client::ActuatorServiceClient<
+        tonic::transport::Channel,
     >,
     imu: kos_proto::imu::imu_service_client::ImuServiceClient<tonic::transport::Channel>,
+    operations: OperationsClient<tonic::transport::Channel>,
 }
 
 impl Client {
@@ -134,16 +190,20 @@ impl Client {
         .await
         .map_err(|source| Error::Connection { source })?;
 
+        let operations = OperationsClient::connect(addr.as_ref().to_string())
+            .await
+            .map_err(|source| Error::Connection { source })?;
+
         Ok(Self {
-            inner: Mutex::new(conn),
+            inner: conn,
             imu: imu_conn,
+            operations,
         })
     }
 
     pub async fn set_positions(&self, positions: BTreeMap<ActuatorId, f32>) -> Result<(), Error> {
         self.inner
-            .lock()
-            .await
+            .clone()
             .command_actuators(CommandActuatorsRequest {
                 commands: positions
                     .into_iter()
@@ -160,14 +220,57 @@ impl Client {
         Ok(())
     }
 
+    /// Enable or disable torque on each actuator. Disabled actuators go limp.
+    pub async fn set_torque_enabled(&self, ids: &[ActuatorId], enabled: bool) -> Result<(), Error> {
+        let mut inner = self.inner.clone();
+        for id in ids {
+            let res = inner
+                .configure_actuator(ConfigureActuatorRequest {
+                    actuator_id: Into::<i32>::into(*id) as u32,
+                    torque_enabled: Some(enabled),
+                    ..Default::default()
+                })
+                .await?
+                .into_inner();
+            if let Some(error) = res.error {
+                return Err(Error::Request {
+                    message: error.message,
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Limit each actuator to `torque` percent of its full torque.
+    pub async fn set_max_torque(&self, ids: &[ActuatorId], torque: f32) -> Result<(), Error> {
+        let mut inner = self.inner.clone();
+        for id in ids {
+            let res = inner
+                .configure_actuator(ConfigureActuatorRequest {
+                    actuator_id: Into::<i32>::into(*id) as u32,
+                    max_torque: Some(torque as f64),
+                    ..Default::default()
+                })
+                .await?
+                .into_inner();
+            if let Some(error) = res.error {
+                return Err(Error::Request {
+                    message: error.message,
+                });
+            }
+        }
+
+        Ok(())
+    }
+
     pub async fn get_actuator_state(
         &mut self,
         servo_id: ActuatorId,
     ) -> Result<JointPosition, Error> {
         let res = self
             .inner
-            .lock()
-            .await
+            .clone()
             .get_actuators_state(GetActuatorsStateRequest {
                 actuator_ids: vec![i32::from(servo_id) as u32],
             })
@@ -195,10 +298,157 @@ impl Client {
             })
             .collect();
 
-        if out.len() == 0 {
+        if out.is_empty() {
             return Err(Error::ServoNotFound);
         }
 
         Ok(out.remove(0))
     }
+
+    pub async fn get_orientation(&mut self) -> Result<ImuOrientation, Error> {
+        let res = self.imu.get_euler(()).await?.into_inner();
+        if let Some(error) = res.error {
+            return Err(Error::Request {
+                message: error.message,
+            });
+        }
+
+        Ok(ImuOrientation {
+            roll: res.roll,
+            pitch: res.pitch,
+            yaw: res.yaw,
+        })
+    }
+
+    pub async fn get_imu_values(&mut self) -> Result<ImuValues, Error> {
+        let res = self.imu.get_values(()).await?.into_inner();
+        if let Some(error) = res.error {
+            return Err(Error::Request {
+                message: error.message,
+            });
+        }
+
+        let mag = match (res.mag_x, res.mag_y, res.mag_z) {
+            (Some(x), Some(y), Some(z)) => Some([x, y, z]),
+            _ => None,
+        };
+        Ok(ImuValues {
+            accel: [res.accel_x, res.accel_y, res.accel_z],
+            gyro: [res.gyro_x, res.gyro_y, res.gyro_z],
+            mag,
+        })
+    }
+
+    pub async fn get_quaternion(&mut self) -> Result<ImuQuaternion, Error> {
+        let res = self.imu.get_quaternion(()).await?.into_inner();
+        if let Some(error) = res.error {
+            return Err(Error::Request {
+                message: error.message,
+            });
+        }
+
+        Ok(ImuQuaternion {
+            w: res.w,
+            x: res.x,
+            y: res.y,
+            z: res.z,
+        })
+    }
+
+    /// Temperature, current and voltage of each actuator, in one request. Actuators the robot
+    /// doesn't know are left out.
+    pub async fn get_actuator_health(
+        &self,
+        ids: &[ActuatorId],
+    ) -> Result<Vec<ActuatorHealth>, Error> {
+        let res = self
+            .inner
+            .clone()
+            .get_actuators_state(GetActuatorsStateRequest {
+                actuator_ids: ids.iter().map(|id| i32::from(*id) as u32).collect(),
+            })
+            .await?;
+
+        Ok(res
+            .into_inner()
+            .states
+            .iter()
+            .map(|state| ActuatorHealth {
+                id: state.actuator_id,
+                temperature: state.temperature,
+                current: state.current,
+                voltage: state.voltage,
+            })
+            .collect())
+    }
+
+    /// Ids of every actuator the robot reports, in increasing order.
+    pub async fn actuator_ids(&self) -> Result<Vec<u32>, Error> {
+        let res = self
+            .inner
+            .clone()
+            .get_actuators_state(GetActuatorsStateRequest {
+                actuator_ids: Vec::new(),
+            })
+            .await?;
+
+        let mut ids: Vec<u32> = res
+            .into_inner()
+            .states
+            .iter()
+            .map(|state| state.actuator_id)
+            .collect();
+        ids.sort_unstable();
+
+        Ok(ids)
+    }
+
+    /// Run the robot's calibration routine on each actuator and wait for all of them to finish,
+    /// giving up after [`CALIBRATION_TIMEOUT`].
+    pub async fn calibrate_actuators(&mut self, ids: &[ActuatorId]) -> Result<(), Error> {
+        let deadline = tokio::time::Instant::now() + CALIBRATION_TIMEOUT;
+        let mut pending = Vec::new();
+        for id in ids {
+            let operation = self
+                .inner
+                .clone()
+                .calibrate_actuator(CalibrateActuatorRequest {
+                    actuator_id: Into::<i32>::into(*id) as u32,
+                    calibration_speed: None,
+                    threshold_current: None,
+                })
+                .await?
+                .into_inner();
+            pending.push(operation);
+        }
+
+        while let Some(operation) = pending.pop() {
+            if !operation.done {
+                if tokio::time::Instant::now() >= deadline {
+                    return Err(Error::CalibrationTimeout {
+                        name: operation.name,
+                        timeout: CALIBRATION_TIMEOUT,
+                    });
+                }
+                tokio::time::sleep(CALIBRATION_POLL_INTERVAL).await;
+                let operation = self
+                    .operations
+                    .get_operation(GetOperationRequest {
+                        name: operation.name,
+                    })
+                    .await?
+                    .into_inner();
+                pending.push(operation);
+                continue;
+            }
+
+            if let Some(operation::Result::Error(status)) = operation.result {
+                return Err(Error::Request {
+                    message: format!("Calibration {} failed: {}", operation.name, status.message),
+                });
+            }
+        }
+
+        Ok(())
+    }
 }
@@ -0,0 +1,636 @@
+//! An in-memory stand-in for the KOS actuator and IMU services running on a K-Bot.
+//!
+//! [`SimKos`] serves `ActuatorService`, `IMUService` and the `Operations` service used to poll
+//! long-running calibrations, so that [`crate::Client`] can be exercised without hardware.
+
+// `tonic::Status` is large, but it is what every RPC returns anyway
+#![allow(clippy::result_large_err)]
+
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use prost::Message;
+use strum::IntoEnumIterator;
+use tokio::time::Instant;
+use tokio_stream::wrappers::TcpListenerStream;
+use tonic::{Request, Response, Status};
+
+use crate::{
+    google_proto::{longrunning, rpc},
+    kos_proto::{
+        actuator::{self, actuator_service_server::ActuatorService},
+        common,
+        imu::{self, imu_service_server::ImuService},
+    },
+    ActuatorId,
+};
+
+pub use crate::google_proto::longrunning::operations_server::OperationsServer;
+pub use crate::kos_proto::actuator::actuator_service_server::ActuatorServiceServer;
+pub use crate::kos_proto::imu::imu_service_server::ImuServiceServer;
+
+/// How long a simulated actuator or IMU calibration takes to complete.
+pub const CALIBRATION_DURATION: Duration = Duration::from_secs(2);
+
+/// Speed used when a command does not specify a velocity, in degrees per second.
+pub const DEFAULT_VELOCITY: f64 = 360.0;
+
+const STANDARD_GRAVITY: f64 = 9.80665;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SimActuator {
+    pub position: f64,
+    pub target: f64,
+    pub velocity: f64,
+    pub torque: f64,
+    pub torque_enabled: bool,
+    pub kp: f64,
+    pub kd: f64,
+    pub ki: f64,
+    pub max_torque: f64,
+    pub temperature: f64,
+    pub voltage: f32,
+    pub current: f32,
+    /// A stuck actuator's calibration never finishes.
+    pub stuck: bool,
+}
+
+impl Default for SimActuator {
+    fn default() -> Self {
+        Self {
+            position: 0.0,
+            target: 0.0,
+            velocity: DEFAULT_VELOCITY,
+            torque: 0.0,
+            torque_enabled: true,
+            kp: 32.0,
+            kd: 32.0,
+            ki: 0.0,
+            max_torque: 100.0,
+            temperature: 30.0,
+            voltage: 24.0,
+            current: 0.0,
+            stuck: false,
+        }
+    }
+}
+
+/// Raw IMU readings in m/s² and degrees per second, plus the orientation in degrees.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SimImu {
+    pub accel: [f64; 3],
+    pub gyro: [f64; 3],
+    pub roll: f64,
+    pub pitch: f64,
+    pub yaw: f64,
+}
+
+impl Default for SimImu {
+    fn default() -> Self {
+        Self {
+            accel: [0.0, 0.0, STANDARD_GRAVITY],
+            gyro: [0.0; 3],
+            roll: 0.0,
+            pitch: 0.0,
+            yaw: 0.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum OperationKind {
+    Actuator(u32),
+    Imu,
+}
+
+#[derive(Debug, Clone)]
+struct SimOperation {
+    kind: OperationKind,
+    started: Instant,
+    cancelled: bool,
+}
+
+#[derive(Debug)]
+struct SimState {
+    actuators: BTreeMap<u32, SimActuator>,
+    imu: SimImu,
+    operations: BTreeMap<String, SimOperation>,
+    next_operation: u64,
+    last_update: Instant,
+}
+
+impl SimState {
+    fn update(&mut self) {
+        let now = Instant::now();
+        let elapsed = (now - self.last_update).as_secs_f64();
+        self.last_update = now;
+
+        for actuator in self.actuators.values_mut() {
+            if !actuator.torque_enabled {
+                actuator.current = 0.0;
+                continue;
+            }
+
+            let max_delta = actuator.velocity * elapsed;
+            let error = actuator.target - actuator.position;
+            if error.abs() <= max_delta {
+                actuator.position = actuator.target;
+                actuator.current = 0.0;
+            } else {
+                actuator.position += max_delta.copysign(error);
+                actuator.current = 0.5;
+            }
+        }
+    }
+
+    fn start_operation(&mut self, kind: OperationKind) -> String {
+        let name = format!("operations/calibrate/{}", self.next_operation);
+        self.next_operation += 1;
+        self.operations.insert(
+            name.clone(),
+            SimOperation {
+                kind,
+                started: Instant::now(),
+                cancelled: false,
+            },
+        );
+        name
+    }
+
+    fn operation(&self, name: &str) -> Result<longrunning::Operation, Status> {
+        let operation = self
+            .operations
+            .get(name)
+            .ok_or_else(|| Status::not_found(format!("No operation named {name}")))?;
+
+        let stuck = match operation.kind {
+            OperationKind::Actuator(id) => self.actuators.get(&id).is_some_and(|a| a.stuck),
+            OperationKind::Imu => false,
+        };
+        let done =
+            operation.cancelled || (!stuck && operation.started.elapsed() >= CALIBRATION_DURATION);
+        let status = match (operation.cancelled, done) {
+            (true, _) => "FAILED",
+            (false, true) => "SUCCEEDED",
+            (false, false) => "IN_PROGRESS",
+        };
+
+        let (metadata, response) = match operation.kind {
+            OperationKind::Actuator(actuator_id) => (
+                any(
+                    "kos.actuator.CalibrateActuatorMetadata",
+                    &actuator::CalibrateActuatorMetadata {
+                        actuator_id,
+                        status: status.to_string(),
+                    },
+                ),
+                any(
+                    "kos.actuator.CalibrateActuatorResponse",
+                    &actuator::CalibrateActuatorResponse {
+                        actuator_id,
+                        error: None,
+                    },
+                ),
+            ),
+            OperationKind::Imu => (
+                any(
+                    "kos.imu.CalibrateIMUMetadata",
+                    &imu::CalibrateImuMetadata {
+                        status: status.to_string(),
+                    },
+                ),
+                any(
+                    "kos.imu.CalibrateIMUResponse",
+                    &imu::CalibrateImuResponse { error: None },
+                ),
+            ),
+        };
+
+        let result = match (operation.cancelled, done) {
+            (true, _) => Some(longrunning::operation::Result::Error(rpc::Status {
+                code: tonic::Code::Cancelled as i32,
+                message: "Calibration cancelled".to_string(),
+                details: Vec::new(),
+            })),
+            (false, true) => Some(longrunning::operation::Result::Response(response)),
+            (false, false) => None,
+        };
+
+        Ok(longrunning::Operation {
+            name: name.to_string(),
+            metadata: Some(metadata),
+            done,
+            result,
+        })
+    }
+}
+
+fn any(type_name: &str, message: &impl Message) -> prost_types::Any {
+    prost_types::Any {
+        type_url: format!("type.googleapis.com/{type_name}"),
+        value: message.encode_to_vec(),
+    }
+}
+
+fn invalid_actuator(actuator_id: u32) -> common::Error {
+    common::Error {
+        code: common::ErrorCode::InvalidArgument as i32,
+        message: format!("No actuator with id {actuator_id}"),
+    }
+}
+
+/// Simulated KOS services for a K-Bot.
+///
+/// Clones share the same state, so a handle kept by a test can inspect and modify the robot that
+/// the server is exposing.
+#[derive(Clone)]
+pub struct SimKos {
+    state: Arc<Mutex<SimState>>,
+}
+
+impl Default for SimKos {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SimKos {
+    /// A simulated robot with one actuator per [`ActuatorId`].
+    pub fn new() -> Self {
+        Self::with_actuators(ActuatorId::iter().map(|id| i32::from(id) as u32))
+    }
+
+    pub fn with_actuators(actuator_ids: impl IntoIterator<Item = u32>) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(SimState {
+                actuators: actuator_ids
+                    .into_iter()
+                    .map(|id| (id, SimActuator::default()))
+                    .collect(),
+                imu: SimImu::default(),
+                operations: BTreeMap::new(),
+                next_operation: 0,
+                last_update: Instant::now(),
+            })),
+        }
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, SimState> {
+        let mut state = self.state.lock().expect("sim state poisoned");
+        state.update();
+        state
+    }
+
+    /// Snapshot of an actuator's state, or `None` if no actuator has that id.
+    pub fn actuator(&self, id: u32) -> Option<SimActuator> {
+        self.state().actuators.get(&id).cloned()
+    }
+
+    /// Modify an actuator in place, e.g. to inject a high temperature.
+    pub fn update_actuator(&self, id: u32, f: impl FnOnce(&mut SimActuator)) -> bool {
+        match self.state().actuators.get_mut(&id) {
+            Some(actuator) => {
+                f(actuator);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn imu(&self) -> SimImu {
+        self.state().imu
+    }
+
+    pub fn set_imu(&self, imu: SimImu) {
+        self.state().imu = imu;
+    }
+
+    /// Build a router serving every simulated service.
+    pub fn router(&self) -> tonic::transport::server::Router {
+        tonic::transport::Server::builder()
+            .add_service(ActuatorServiceServer::new(self.clone()))
+            .add_service(ImuServiceServer::new(self.clone()))
+            .add_service(OperationsServer::new(self.clone()))
+    }
+
+    /// Serve on `addr` until the task is dropped or fails.
+    pub async fn serve(self, addr: std::net::SocketAddr) -> Result<(), tonic::transport::Error> {
+        self.router().serve(addr).await
+    }
+
+    /// Bind to an ephemeral localhost port and serve in the background.
+    ///
+    /// Returns the `grpc://` URL to hand to [`crate::Client::connect`].
+    pub async fn spawn(self) -> std::io::Result<(String, tokio::task::JoinHandle<()>)> {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+
+        let router = self.router();
+        let handle = tokio::spawn(async move {
+            router
+                .serve_with_incoming(TcpListenerStream::new(listener))
+                .await
+                .expect("sim server failed");
+        });
+
+        Ok((format!("grpc://{addr}"), handle))
+    }
+}
+
+#[tonic::async_trait]
+impl ActuatorService for SimKos {
+    async fn command_actuators(
+        &self,
+        request: Request<actuator::CommandActuatorsRequest>,
+    ) -> Result<Response<actuator::CommandActuatorsResponse>, Status> {
+        let mut state = self.state();
+
+        let results = request
+            .into_inner()
+            .commands
+            .into_iter()
+            .map(|command| {
+                let Some(actuator) = state.actuators.get_mut(&command.actuator_id) else {
+                    return common::ActionResult {
+                        actuator_id: command.actuator_id,
+                        success: false,
+                        error: Some(invalid_actuator(command.actuator_id)),
+                    };
+                };
+
+                if let Some(position) = command.position {
+                    actuator.target = position;
+                }
+                actuator.velocity = command.velocity.unwrap_or(DEFAULT_VELOCITY).abs();
+                if let Some(torque) = command.torque {
+                    actuator.torque = torque;
+                }
+
+                common::ActionResult {
+                    actuator_id: command.actuator_id,
+                    success: true,
+                    error: None,
+                }
+            })
+            .collect();
+
+        Ok(Response::new(actuator::CommandActuatorsResponse {
+            results,
+        }))
+    }
+
+    async fn configure_actuator(
+        &self,
+        request: Request<actuator::ConfigureActuatorRequest>,
+    ) -> Result<Response<common::ActionResponse>, Status> {
+        let config = request.into_inner();
+        let mut state = self.state();
+
+        if let Some(new_id) = config.new_actuator_id {
+            if state.actuators.contains_key(&new_id) {
+                return Ok(Response::new(common::ActionResponse {
+                    success: false,
+                    error: Some(common::Error {
+                        code: common::ErrorCode::InvalidArgument as i32,
+                        message: format!("Actuator id {new_id} is already in use"),
+                    }),
+                }));
+            }
+        }
+
+        let Some(actuator) = state.actuators.get_mut(&config.actuator_id) else {
+            return Ok(Response::new(common::ActionResponse {
+                success: false,
+                error: Some(invalid_actuator(config.actuator_id)),
+            }));
+        };
+
+        if let Some(kp) = config.kp {
+            actuator.kp = kp;
+        }
+        if let Some(kd) = config.kd {
+            actuator.kd = kd;
+        }
+        if let Some(ki) = config.ki {
+            actuator.ki = ki;
+        }
+        if let Some(max_torque) = config.max_torque {
+            actuator.max_torque = max_torque;
+        }
+        if let Some(torque_enabled) = config.torque_enabled {
+            actuator.torque_enabled = torque_enabled;
+        }
+        if config.zero_position == Some(true) {
+            actuator.position = 0.0;
+            actuator.target = 0.0;
+        }
+
+        if let Some(new_id) = config.new_actuator_id {
+            let actuator = state
+                .actuators
+                .remove(&config.actuator_id)
+                .expect("actuator exists");
+            state.actuators.insert(new_id, actuator);
+        }
+
+        Ok(Response::new(common::ActionResponse {
+            success: true,
+            error: None,
+        }))
+    }
+
+    async fn calibrate_actuator(
+        &self,
+        request: Request<actuator::CalibrateActuatorRequest>,
+    ) -> Result<Response<longrunning::Operation>, Status> {
+        let actuator_id = request.into_inner().actuator_id;
+        let mut state = self.state();
+
+        if !state.actuators.contains_key(&actuator_id) {
+            return Err(Status::invalid_argument(format!(
+                "No actuator with id {actuator_id}"
+            )));
+        }
+
+        let name = state.start_operation(OperationKind::Actuator(actuator_id));
+        Ok(Response::new(state.operation(&name)?))
+    }
+
+    async fn get_actuators_state(
+        &self,
+        request: Request<actuator::GetActuatorsStateRequest>,
+    ) -> Result<Response<actuator::GetActuatorsStateResponse>, Status> {
+        let mut ids = request.into_inner().actuator_ids;
+        let state = self.state();
+
+        if ids.is_empty() {
+            ids = state.actuators.keys().copied().collect();
+        }
+
+        let states = ids
+            .into_iter()
+            .filter_map(|id| {
+                let actuator = state.actuators.get(&id)?;
+                let moving = actuator.torque_enabled && actuator.position != actuator.target;
+
+                Some(actuator::ActuatorStateResponse {
+                    actuator_id: id,
+                    online: true,
+                    position: Some(actuator.position),
+                    velocity: Some(if moving { actuator.velocity } else { 0.0 }),
+                    torque: Some(actuator.torque),
+                    temperature: Some(actuator.temperature),
+                    voltage: Some(actuator.voltage),
+                    current: Some(actuator.current),
+                })
+            })
+            .collect();
+
+        Ok(Response::new(actuator::GetActuatorsStateResponse {
+            states,
+        }))
+    }
+}
+
+#[tonic::async_trait]
+impl ImuService for SimKos {
+    async fn get_values(
+        &self,
+        _request: Request<()>,
+    ) -> Result<Response<imu::ImuValuesResponse>, Status> {
+        let imu = self.state().imu;
+        Ok(Response::new(imu::ImuValuesResponse {
+            accel_x: imu.accel[0],
+            accel_y: imu.accel[1],
+            accel_z: imu.accel[2],
+            gyro_x: imu.gyro[0],
+            gyro_y: imu.gyro[1],
+            gyro_z: imu.gyro[2],
+            mag_x: None,
+            mag_y: None,
+            mag_z: None,
+            error: None,
+        }))
+    }
+
+    async fn calibrate(
+        &self,
+        _request: Request<()>,
+    ) -> Result<Response<longrunning::Operation>, Status> {
+        let mut state = self.state();
+        let name = state.start_operation(OperationKind::Imu);
+        Ok(Response::new(state.operation(&name)?))
+    }
+
+    async fn zero(
+        &self,
+        _request: Request<imu::ZeroImuRequest>,
+    ) -> Result<Response<common::ActionResponse>, Status> {
+        let mut state = self.state();
+        state.imu.roll = 0.0;
+        state.imu.pitch = 0.0;
+        state.imu.yaw = 0.0;
+        state.imu.gyro = [0.0; 3];
+
+        Ok(Response::new(common::ActionResponse {
+            success: true,
+            error: None,
+        }))
+    }
+
+    async fn get_euler(
+        &self,
+        _request: Request<()>,
+    ) -> Result<Response<imu::EulerAnglesResponse>, Status> {
+        let imu = self.state().imu;
+        Ok(Response::new(imu::EulerAnglesResponse {
+            roll: imu.roll,
+            pitch: imu.pitch,
+            yaw: imu.yaw,
+            error: None,
+        }))
+    }
+
+    async fn get_quaternion(
+        &self,
+        _request: Request<()>,
+    ) -> Result<Response<imu::QuaternionResponse>, Status> {
+        let imu = self.state().imu;
+
+        let (sr, cr) = (imu.roll.to_radians() / 2.0).sin_cos();
+        let (sp, cp) = (imu.pitch.to_radians() / 2.0).sin_cos();
+        let (sy, cy) = (imu.yaw.to_radians() / 2.0).sin_cos();
+
+        Ok(Response::new(imu::QuaternionResponse {
+            w: cr * cp * cy + sr * sp * sy,
+            x: sr * cp * cy - cr * sp * sy,
+            y: cr * sp * cy + sr * cp * sy,
+            z: cr * cp * sy - sr * sp * cy,
+            error: None,
+        }))
+    }
+}
+
+#[tonic::async_trait]
+impl longrunning::operations_server::Operations for SimKos {
+    async fn list_operations(
+        &self,
+        _request: Request<longrunning::ListOperationsRequest>,
+    ) -> Result<Response<longrunning::ListOperationsResponse>, Status> {
+        let state = self.state();
+        let operations = state
+            .operations
+            .keys()
+            .map(|name| state.operation(name))
+            .collect::<Result<_, _>>()?;
+
+        Ok(Response::new(longrunning::ListOperationsResponse {
+            operations,
+            next_page_token: String::new(),
+        }))
+    }
+
+    async fn get_operation(
+        &self,
+        request: Request<longrunning::GetOperationRequest>,
+    ) -> Result<Response<longrunning::Operation>, Status> {
+        let name = request.into_inner().name;
+        Ok(Response::new(self.state().operation(&name)?))
+    }
+
+    async fn delete_operation(
+        &self,
+        request: Request<longrunning::DeleteOperationRequest>,
+    ) -> Result<Response<()>, Status> {
+        let name = request.into_inner().name;
+        self.state()
+            .operations
+            .remove(&name)
+            .ok_or_else(|| Status::not_found(format!("No operation named {name}")))?;
+        Ok(Response::new(()))
+    }
+
+    async fn cancel_operation(
+        &self,
+        request: Request<longrunning::CancelOperationRequest>,
+    ) -> Result<Response<()>, Status> {
+        let name = request.into_inner().name;
+        self.state()
+            .operations
+            .get_mut(&name)
+            .ok_or_else(|| Status::not_found(format!("No operation named {name}")))?
+            .cancelled = true;
+        Ok(Response::new(()))
+    }
+
+    async fn wait_operation(
+        &self,
+        request: Request<longrunning::WaitOperationRequest>,
+    ) -> Result<Response<longrunning::Operation>, Status> {
+        let name = request.into_inner().name;
+        Ok(Response::new(self.state().operation(&name)?))
+    }
+}
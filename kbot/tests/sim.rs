@@ -0,0 +1,79 @@
+use std::collections::BTreeMap;
+
+use kbot::{
+    google_proto::longrunning::{
+        operation, operations_client::OperationsClient, GetOperationRequest,
+    },
+    kos_proto::actuator::{
+        actuator_service_client::ActuatorServiceClient, CalibrateActuatorRequest,
+    },
+    sim::SimKos,
+    ActuatorId,
+};
+
+#[tokio::test]
+async fn commands_move_actuators() {
+    let sim = SimKos::new();
+    let (url, _handle) = sim.clone().spawn().await.unwrap();
+    let mut client = kbot::Client::connect(url).await.unwrap();
+
+    client
+        .set_positions(BTreeMap::from([(ActuatorId::RightShoulderPitch, 30.0)]))
+        .await
+        .unwrap();
+    assert_eq!(sim.actuator(13).unwrap().target, 30.0);
+
+    tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+    let state = client
+        .get_actuator_state(ActuatorId::RightShoulderPitch)
+        .await
+        .unwrap();
+    assert_eq!(state.position, 30.0);
+    assert_eq!(state.speed, 0.0);
+}
+
+#[tokio::test(start_paused = true)]
+async fn calibration_is_a_long_running_operation() {
+    let sim = SimKos::new();
+    let (url, _handle) = sim.clone().spawn().await.unwrap();
+
+    let mut actuators = ActuatorServiceClient::connect(url.clone()).await.unwrap();
+    let mut operations = OperationsClient::connect(url).await.unwrap();
+
+    let op = actuators
+        .calibrate_actuator(CalibrateActuatorRequest {
+            actuator_id: 1,
+            calibration_speed: None,
+            threshold_current: None,
+        })
+        .await
+        .unwrap()
+        .into_inner();
+    assert!(!op.done);
+
+    tokio::time::advance(kbot::sim::CALIBRATION_DURATION).await;
+    let op = operations
+        .get_operation(GetOperationRequest { name: op.name })
+        .await
+        .unwrap()
+        .into_inner();
+    assert!(op.done);
+    assert!(matches!(op.result, Some(operation::Result::Response(_))));
+}
+
+#[tokio::test(start_paused = true)]
+async fn calibration_times_out() {
+    let sim = SimKos::new();
+    let (url, _handle) = sim.clone().spawn().await.unwrap();
+    let mut client = kbot::Client::connect(url).await.unwrap();
+    sim.update_actuator(14, |actuator| actuator.stuck = true);
+
+    let started = tokio::time::Instant::now();
+    let err = client
+        .calibrate_actuators(&[ActuatorId::LeftShoulderPitch, ActuatorId::LeftElbowYaw])
+        .await
+        .unwrap_err();
+    assert!(matches!(err, kbot::Error::CalibrationTimeout { .. }));
+    assert!(started.elapsed() >= kbot::CALIBRATION_TIMEOUT);
+    assert!(started.elapsed() < kbot::CALIBRATION_TIMEOUT + kbot::CALIBRATION_POLL_INTERVAL * 2);
+}
@@ -1,7 +1,8 @@
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     tonic_build::configure()
         .build_client(true)
-        .build_server(false)
+        // The server side is only needed by the simulator
+        .build_server(std::env::var_os("CARGO_FEATURE_SIM").is_some())
         .build_transport(true)
         .compile_well_known_types(true)
         .emit_rerun_if_changed(true)
@@ -0,0 +1,14 @@
+use zeroth::sim::SimServoControl;
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let addr = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "0.0.0.0:50051".to_string())
+        .parse()?;
+
+    println!("Simulated Zeroth listening on grpc://{}", addr);
+    SimServoControl::new().serve(addr).await?;
+
+    Ok(())
+}
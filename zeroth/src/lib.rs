@@ -1,7 +1,10 @@
-mod proto {
+pub mod proto {
     tonic::include_proto!("hal_pb");
 }
 
+#[cfg(feature = "sim")]
+pub mod sim;
+
 use num_enum::{IntoPrimitive, TryFromPrimitive};
 pub use proto::{AudioChunk, CalibrationStatus, ImuData, VideoStreamUrls, WifiCredentials};
 use serde::{Deserialize, Serialize};
@@ -96,6 +99,8 @@ impl From<tonic::Status> for Error {
     }
 }
 
+/// Cheap to clone, clones share the connection.
+#[derive(Clone)]
 pub struct Client {
     inner: proto::servo_control_client::ServoControlClient<tonic::transport::Channel>,
 }
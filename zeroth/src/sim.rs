@@ -0,0 +1,653 @@
+//! An in-memory stand-in for the `ServoControl` service running on the Zeroth.
+//!
+//! [`SimServoControl`] keeps plausible state for every RPC so that [`crate::Client`] can be
+//! exercised without hardware. Servos slew toward their commanded position at the requested
+//! speed (degrees per second) while movement and torque are enabled.
+
+// `tonic::Status` is large, but it is what every RPC returns anyway
+#![allow(clippy::result_large_err)]
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use tokio::time::Instant;
+use tokio_stream::wrappers::TcpListenerStream;
+use tonic::{Request, Response, Status, Streaming};
+
+use crate::proto::{
+    self, servo_control_server::ServoControl, AudioChunk, CalibrationStatus, ImuData,
+    VideoStreamUrls, WifiCredentials,
+};
+
+pub use crate::proto::servo_control_server::ServoControlServer;
+
+/// How long a simulated calibration takes before the servo reports as idle again.
+pub const CALIBRATION_DURATION: Duration = Duration::from_secs(2);
+
+/// Size of each chunk returned by `GetRecordedAudio`, in bytes.
+const AUDIO_CHUNK_SIZE: usize = 4096;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SimServo {
+    pub position: f32,
+    pub target: f32,
+    pub speed: f32,
+    pub min_position: f32,
+    pub max_position: f32,
+    pub torque: f32,
+    pub torque_enabled: bool,
+    pub temperature: f32,
+    pub current: f32,
+    pub voltage: f32,
+}
+
+impl Default for SimServo {
+    fn default() -> Self {
+        Self {
+            position: 0.0,
+            target: 0.0,
+            speed: 0.0,
+            min_position: -90.0,
+            max_position: 90.0,
+            torque: 100.0,
+            torque_enabled: false,
+            temperature: 30.0,
+            current: 0.0,
+            voltage: 12.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Calibration {
+    servo_id: i32,
+    started: Instant,
+}
+
+#[derive(Debug, Clone)]
+struct Recording {
+    config: proto::RecordingConfig,
+    started: Instant,
+}
+
+#[derive(Debug)]
+struct SimState {
+    servos: BTreeMap<i32, SimServo>,
+    movement_enabled: bool,
+    wifi: Option<WifiCredentials>,
+    streaming_video: bool,
+    calibration: Option<Calibration>,
+    imu: ImuData,
+    uploads: HashMap<String, Vec<AudioChunk>>,
+    next_upload: u64,
+    playing: Option<(String, f32)>,
+    recording: Option<Recording>,
+    recorded: Vec<AudioChunk>,
+    last_update: Instant,
+}
+
+impl SimState {
+    fn new(servo_ids: impl IntoIterator<Item = i32>) -> Self {
+        Self {
+            servos: servo_ids
+                .into_iter()
+                .map(|id| (id, SimServo::default()))
+                .collect(),
+            movement_enabled: false,
+            wifi: None,
+            streaming_video: false,
+            calibration: None,
+            imu: ImuData {
+                gyro: Some(proto::Vector3 {
+                    x: 0.0,
+                    y: 0.0,
+                    z: 0.0,
+                }),
+                accel: Some(proto::Vector3 {
+                    x: 0.0,
+                    y: 0.0,
+                    z: 1000.0,
+                }),
+            },
+            uploads: HashMap::new(),
+            next_upload: 0,
+            playing: None,
+            recording: None,
+            recorded: Vec::new(),
+            last_update: Instant::now(),
+        }
+    }
+
+    /// Advance every servo toward its target and finish any calibration that has run long
+    /// enough.
+    fn update(&mut self) {
+        let now = Instant::now();
+        let elapsed = (now - self.last_update).as_secs_f32();
+        self.last_update = now;
+
+        for servo in self.servos.values_mut() {
+            if !self.movement_enabled || !servo.torque_enabled {
+                servo.current = 0.0;
+                continue;
+            }
+
+            let max_delta = servo.speed * elapsed;
+            let error = servo.target - servo.position;
+            if error.abs() <= max_delta {
+                servo.position = servo.target;
+                servo.current = 0.0;
+            } else {
+                servo.position += max_delta.copysign(error);
+                servo.current = 0.1 + servo.torque / 1000.0;
+            }
+        }
+
+        if let Some(calibration) = &self.calibration {
+            if now - calibration.started >= CALIBRATION_DURATION {
+                self.calibration = None;
+            }
+        }
+    }
+
+    fn servo_mut(&mut self, id: i32) -> Result<&mut SimServo, Status> {
+        self.servos
+            .get_mut(&id)
+            .ok_or_else(|| Status::not_found(format!("No servo with id {id}")))
+    }
+
+    fn command(&mut self, position: proto::JointPosition) -> Result<(), Status> {
+        let servo = self.servo_mut(position.id)?;
+        servo.target = position
+            .position
+            .clamp(servo.min_position, servo.max_position);
+        servo.speed = position.speed.max(0.0);
+        Ok(())
+    }
+}
+
+fn error_info(message: impl Into<String>, code: i32) -> proto::ErrorInfo {
+    proto::ErrorInfo {
+        message: message.into(),
+        code,
+    }
+}
+
+/// Simulated implementation of the Zeroth `ServoControl` service.
+///
+/// Clones share the same state, so a handle kept by a test can inspect and modify the robot that
+/// the server is exposing.
+#[derive(Clone)]
+pub struct SimServoControl {
+    state: Arc<Mutex<SimState>>,
+}
+
+impl Default for SimServoControl {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SimServoControl {
+    /// A simulated robot with the 16 servos of a stock Zeroth.
+    pub fn new() -> Self {
+        Self::with_servos(1..=16)
+    }
+
+    pub fn with_servos(servo_ids: impl IntoIterator<Item = i32>) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(SimState::new(servo_ids))),
+        }
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, SimState> {
+        let mut state = self.state.lock().expect("sim state poisoned");
+        state.update();
+        state
+    }
+
+    /// Snapshot of a servo's state, or `None` if no servo has that id.
+    pub fn servo(&self, id: i32) -> Option<SimServo> {
+        self.state().servos.get(&id).cloned()
+    }
+
+    /// Modify a servo in place, e.g. to inject a high temperature or different range.
+    pub fn update_servo(&self, id: i32, f: impl FnOnce(&mut SimServo)) -> bool {
+        match self.state().servos.get_mut(&id) {
+            Some(servo) => {
+                f(servo);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn movement_enabled(&self) -> bool {
+        self.state().movement_enabled
+    }
+
+    pub fn set_imu(&self, imu: ImuData) {
+        self.state().imu = imu;
+    }
+
+    pub fn wifi(&self) -> Option<WifiCredentials> {
+        self.state().wifi.clone()
+    }
+
+    /// The audio clip currently playing and its volume.
+    pub fn playing(&self) -> Option<(String, f32)> {
+        self.state().playing.clone()
+    }
+
+    pub fn into_server(self) -> ServoControlServer<Self> {
+        ServoControlServer::new(self)
+    }
+
+    /// Serve on `addr` until the task is dropped or fails.
+    pub async fn serve(self, addr: SocketAddr) -> Result<(), tonic::transport::Error> {
+        tonic::transport::Server::builder()
+            .add_service(self.into_server())
+            .serve(addr)
+            .await
+    }
+
+    /// Bind to an ephemeral localhost port and serve in the background.
+    ///
+    /// Returns the `grpc://` URL to hand to [`crate::Client::connect`].
+    pub async fn spawn(self) -> std::io::Result<(String, tokio::task::JoinHandle<()>)> {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+
+        let handle = tokio::spawn(async move {
+            tonic::transport::Server::builder()
+                .add_service(self.into_server())
+                .serve_with_incoming(TcpListenerStream::new(listener))
+                .await
+                .expect("sim server failed");
+        });
+
+        Ok((format!("grpc://{addr}"), handle))
+    }
+}
+
+#[tonic::async_trait]
+impl ServoControl for SimServoControl {
+    async fn get_positions(
+        &self,
+        _request: Request<proto::Empty>,
+    ) -> Result<Response<proto::JointPositions>, Status> {
+        let state = self.state();
+        Ok(Response::new(proto::JointPositions {
+            positions: state
+                .servos
+                .iter()
+                .map(|(id, servo)| proto::JointPosition {
+                    id: *id,
+                    position: servo.position,
+                    speed: if servo.position == servo.target {
+                        0.0
+                    } else {
+                        servo.speed
+                    },
+                })
+                .collect(),
+        }))
+    }
+
+    async fn set_positions(
+        &self,
+        request: Request<proto::JointPositions>,
+    ) -> Result<Response<proto::Empty>, Status> {
+        let mut state = self.state();
+        for position in request.into_inner().positions {
+            state.command(position)?;
+        }
+        Ok(Response::new(proto::Empty {}))
+    }
+
+    async fn enable_movement(
+        &self,
+        _request: Request<proto::Empty>,
+    ) -> Result<Response<proto::Empty>, Status> {
+        self.state().movement_enabled = true;
+        Ok(Response::new(proto::Empty {}))
+    }
+
+    async fn disable_movement(
+        &self,
+        _request: Request<proto::Empty>,
+    ) -> Result<Response<proto::Empty>, Status> {
+        self.state().movement_enabled = false;
+        Ok(Response::new(proto::Empty {}))
+    }
+
+    async fn set_position(
+        &self,
+        request: Request<proto::JointPosition>,
+    ) -> Result<Response<proto::Empty>, Status> {
+        self.state().command(request.into_inner())?;
+        Ok(Response::new(proto::Empty {}))
+    }
+
+    async fn set_wifi_info(
+        &self,
+        request: Request<WifiCredentials>,
+    ) -> Result<Response<proto::Empty>, Status> {
+        self.state().wifi = Some(request.into_inner());
+        Ok(Response::new(proto::Empty {}))
+    }
+
+    async fn get_servo_info(
+        &self,
+        request: Request<proto::ServoId>,
+    ) -> Result<Response<proto::ServoInfoResponse>, Status> {
+        let id = request.into_inner().id;
+        let state = self.state();
+
+        let result = match state.servos.get(&id) {
+            Some(servo) => proto::servo_info_response::Result::Info(proto::ServoInfo {
+                id,
+                temperature: servo.temperature,
+                current: servo.current,
+                voltage: servo.voltage,
+                speed: servo.speed,
+                current_position: servo.position,
+                min_position: servo.min_position,
+                max_position: servo.max_position,
+            }),
+            None => proto::servo_info_response::Result::Error(error_info(
+                format!("No servo with id {id}"),
+                404,
+            )),
+        };
+
+        Ok(Response::new(proto::ServoInfoResponse {
+            result: Some(result),
+        }))
+    }
+
+    async fn scan(
+        &self,
+        _request: Request<proto::Empty>,
+    ) -> Result<Response<proto::ServoIds>, Status> {
+        Ok(Response::new(proto::ServoIds {
+            ids: self.state().servos.keys().copied().collect(),
+        }))
+    }
+
+    async fn change_id(
+        &self,
+        request: Request<proto::IdChange>,
+    ) -> Result<Response<proto::ChangeIdResponse>, Status> {
+        let proto::IdChange { old_id, new_id } = request.into_inner();
+        let mut state = self.state();
+
+        let result = if state.servos.contains_key(&new_id) {
+            proto::change_id_response::Result::Error(error_info(
+                format!("Servo id {new_id} is already in use"),
+                409,
+            ))
+        } else if let Some(servo) = state.servos.remove(&old_id) {
+            state.servos.insert(new_id, servo);
+            proto::change_id_response::Result::Success(true)
+        } else {
+            proto::change_id_response::Result::Error(error_info(
+                format!("No servo with id {old_id}"),
+                404,
+            ))
+        };
+
+        Ok(Response::new(proto::ChangeIdResponse {
+            result: Some(result),
+        }))
+    }
+
+    async fn start_calibration(
+        &self,
+        request: Request<proto::CalibrationRequest>,
+    ) -> Result<Response<proto::CalibrationResponse>, Status> {
+        let servo_id = request.into_inner().servo_id;
+        let mut state = self.state();
+
+        let result = if !state.servos.contains_key(&servo_id) {
+            proto::calibration_response::Result::Error(error_info(
+                format!("No servo with id {servo_id}"),
+                404,
+            ))
+        } else if state.calibration.is_some() {
+            proto::calibration_response::Result::Error(error_info(
+                "A calibration is already running",
+                409,
+            ))
+        } else {
+            state.calibration = Some(Calibration {
+                servo_id,
+                started: Instant::now(),
+            });
+            proto::calibration_response::Result::Success(true)
+        };
+
+        Ok(Response::new(proto::CalibrationResponse {
+            result: Some(result),
+        }))
+    }
+
+    async fn cancel_calibration(
+        &self,
+        request: Request<proto::ServoId>,
+    ) -> Result<Response<proto::CalibrationResponse>, Status> {
+        let servo_id = request.into_inner().id;
+        let mut state = self.state();
+
+        let result = match &state.calibration {
+            Some(calibration) if calibration.servo_id == servo_id => {
+                state.calibration = None;
+                proto::calibration_response::Result::Success(true)
+            }
+            _ => proto::calibration_response::Result::Error(error_info(
+                format!("Servo {servo_id} is not calibrating"),
+                400,
+            )),
+        };
+
+        Ok(Response::new(proto::CalibrationResponse {
+            result: Some(result),
+        }))
+    }
+
+    async fn start_video_stream(
+        &self,
+        _request: Request<proto::Empty>,
+    ) -> Result<Response<proto::Empty>, Status> {
+        self.state().streaming_video = true;
+        Ok(Response::new(proto::Empty {}))
+    }
+
+    async fn stop_video_stream(
+        &self,
+        _request: Request<proto::Empty>,
+    ) -> Result<Response<proto::Empty>, Status> {
+        self.state().streaming_video = false;
+        Ok(Response::new(proto::Empty {}))
+    }
+
+    async fn get_video_stream_urls(
+        &self,
+        _request: Request<proto::Empty>,
+    ) -> Result<Response<VideoStreamUrls>, Status> {
+        if !self.state().streaming_video {
+            return Ok(Response::new(VideoStreamUrls::default()));
+        }
+
+        Ok(Response::new(VideoStreamUrls {
+            webrtc: vec!["http://127.0.0.1:8889/camera".to_string()],
+            hls: vec!["http://127.0.0.1:8888/camera/index.m3u8".to_string()],
+            hls_ll: vec!["http://127.0.0.1:8888/camera/index.m3u8?ll=1".to_string()],
+            mse: vec!["ws://127.0.0.1:8890/camera".to_string()],
+            rtsp: vec!["rtsp://127.0.0.1:8554/camera".to_string()],
+        }))
+    }
+
+    async fn get_calibration_status(
+        &self,
+        _request: Request<proto::Empty>,
+    ) -> Result<Response<CalibrationStatus>, Status> {
+        let state = self.state();
+        Ok(Response::new(match &state.calibration {
+            Some(calibration) => CalibrationStatus {
+                is_calibrating: true,
+                calibrating_servo_id: calibration.servo_id,
+            },
+            None => CalibrationStatus {
+                is_calibrating: false,
+                calibrating_servo_id: 0,
+            },
+        }))
+    }
+
+    async fn set_torque(
+        &self,
+        request: Request<proto::TorqueSettings>,
+    ) -> Result<Response<proto::Empty>, Status> {
+        let mut state = self.state();
+        for setting in request.into_inner().settings {
+            state.servo_mut(setting.id)?.torque = setting.torque.clamp(0.0, 100.0);
+        }
+        Ok(Response::new(proto::Empty {}))
+    }
+
+    async fn set_torque_enable(
+        &self,
+        request: Request<proto::TorqueEnableSettings>,
+    ) -> Result<Response<proto::Empty>, Status> {
+        let mut state = self.state();
+        for setting in request.into_inner().settings {
+            state.servo_mut(setting.id)?.torque_enabled = setting.enable;
+        }
+        Ok(Response::new(proto::Empty {}))
+    }
+
+    async fn get_imu_data(
+        &self,
+        _request: Request<proto::Empty>,
+    ) -> Result<Response<ImuData>, Status> {
+        Ok(Response::new(self.state().imu))
+    }
+
+    async fn upload_audio(
+        &self,
+        request: Request<Streaming<AudioChunk>>,
+    ) -> Result<Response<proto::UploadResponse>, Status> {
+        let mut stream = request.into_inner();
+        let mut chunks = Vec::new();
+        while let Some(chunk) = stream.message().await? {
+            chunks.push(chunk);
+        }
+
+        let mut state = self.state();
+        let audio_id = format!("audio-{}", state.next_upload);
+        state.next_upload += 1;
+
+        let result = if chunks.is_empty() {
+            proto::upload_response::Result::Error(error_info("No audio data received", 400))
+        } else {
+            state.uploads.insert(audio_id.clone(), chunks);
+            proto::upload_response::Result::Success(true)
+        };
+
+        Ok(Response::new(proto::UploadResponse {
+            audio_id,
+            result: Some(result),
+        }))
+    }
+
+    async fn play_audio(
+        &self,
+        request: Request<proto::PlayRequest>,
+    ) -> Result<Response<proto::Empty>, Status> {
+        let proto::PlayRequest { audio_id, volume } = request.into_inner();
+        let mut state = self.state();
+
+        if !state.uploads.contains_key(&audio_id) {
+            return Err(Status::not_found(format!("No audio with id {audio_id}")));
+        }
+        state.playing = Some((audio_id, volume.clamp(0.0, 1.0)));
+
+        Ok(Response::new(proto::Empty {}))
+    }
+
+    async fn start_recording(
+        &self,
+        request: Request<proto::RecordingConfig>,
+    ) -> Result<Response<proto::Empty>, Status> {
+        let config = request.into_inner();
+        if config.sample_rate <= 0 || config.channels <= 0 {
+            return Err(Status::invalid_argument(
+                "Sample rate and channels must be positive",
+            ));
+        }
+
+        let mut state = self.state();
+        if state.recording.is_some() {
+            return Err(Status::failed_precondition("Already recording"));
+        }
+        state.recording = Some(Recording {
+            config,
+            started: Instant::now(),
+        });
+
+        Ok(Response::new(proto::Empty {}))
+    }
+
+    async fn stop_recording(
+        &self,
+        _request: Request<proto::Empty>,
+    ) -> Result<Response<proto::Empty>, Status> {
+        let mut state = self.state();
+        let recording = state
+            .recording
+            .take()
+            .ok_or_else(|| Status::failed_precondition("Not recording"))?;
+
+        // Record 16-bit silence for as long as the recording ran.
+        let seconds = recording.started.elapsed().as_secs_f64();
+        let samples = (seconds * recording.config.sample_rate as f64) as usize;
+        let bytes = samples * recording.config.channels as usize * 2;
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as i64;
+
+        state.recorded = vec![0u8; bytes]
+            .chunks(AUDIO_CHUNK_SIZE)
+            .map(|data| AudioChunk {
+                data: data.to_vec(),
+                format: recording.config.format.clone(),
+                timestamp,
+            })
+            .collect();
+
+        Ok(Response::new(proto::Empty {}))
+    }
+
+    type GetRecordedAudioStream =
+        tokio_stream::Iter<std::vec::IntoIter<Result<AudioChunk, Status>>>;
+
+    async fn get_recorded_audio(
+        &self,
+        _request: Request<proto::Empty>,
+    ) -> Result<Response<Self::GetRecordedAudioStream>, Status> {
+        let chunks = self
+            .state()
+            .recorded
+            .iter()
+            .cloned()
+            .map(Ok)
+            .collect::<Vec<_>>();
+
+        Ok(Response::new(tokio_stream::iter(chunks)))
+    }
+}
@@ -0,0 +1,101 @@
+use zeroth::{sim::SimServoControl, AudioChunk, JointPosition, ServoId, TorqueEnableSetting};
+
+async fn connect(sim: &SimServoControl) -> zeroth::Client {
+    let (url, _handle) = sim.clone().spawn().await.unwrap();
+    zeroth::Client::connect(url).await.unwrap()
+}
+
+#[tokio::test]
+async fn positions_follow_commands() {
+    let sim = SimServoControl::new();
+    let mut client = connect(&sim).await;
+
+    client.enable_movement().await.unwrap();
+    client
+        .set_torque_enable(vec![TorqueEnableSetting {
+            id: ServoId::LeftShoulderPitch,
+            enable: true,
+        }])
+        .await
+        .unwrap();
+    client
+        .set_position(JointPosition {
+            id: ServoId::LeftShoulderPitch,
+            position: 500.0,
+            speed: 10_000.0,
+        })
+        .await
+        .unwrap();
+
+    // Targets are clamped to the servo range and reached at the commanded speed
+    tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    let info = client
+        .get_servo_info(ServoId::LeftShoulderPitch)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(info.current_position, info.max_position);
+
+    let positions = client.get_positions().await.unwrap();
+    assert_eq!(positions.len(), 16);
+}
+
+#[tokio::test]
+async fn scan_and_change_id() {
+    let sim = SimServoControl::with_servos([1, 2]);
+    let mut client = connect(&sim).await;
+
+    assert_eq!(client.scan().await.unwrap(), vec![1, 2]);
+    client.change_id(2, 7).await.unwrap();
+    assert_eq!(client.scan().await.unwrap(), vec![1, 7]);
+    assert!(client
+        .get_servo_info(ServoId::RightKneePitch)
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn calibration_status() {
+    let sim = SimServoControl::new();
+    let mut client = connect(&sim).await;
+
+    client
+        .start_calibration(ServoId::LeftHipYaw, 10, 0.5)
+        .await
+        .unwrap();
+    let status = client.get_calibration_status().await.unwrap();
+    assert!(status.is_calibrating);
+    assert_eq!(status.calibrating_servo_id, i32::from(ServoId::LeftHipYaw));
+
+    client
+        .cancel_calibration(ServoId::LeftHipYaw)
+        .await
+        .unwrap();
+    assert!(
+        !client
+            .get_calibration_status()
+            .await
+            .unwrap()
+            .is_calibrating
+    );
+}
+
+#[tokio::test]
+async fn audio_upload_and_playback() {
+    let sim = SimServoControl::new();
+    let mut client = connect(&sim).await;
+
+    let chunks = vec![AudioChunk {
+        data: vec![1, 2, 3],
+        format: "wav".to_string(),
+        timestamp: 0,
+    }];
+    let audio_id = client
+        .upload_audio(tokio_stream::iter(chunks))
+        .await
+        .unwrap();
+
+    client.play_audio(audio_id.clone(), 0.5).await.unwrap();
+    assert_eq!(sim.playing(), Some((audio_id, 0.5)));
+    assert!(client.play_audio("missing".into(), 1.0).await.is_err());
+}